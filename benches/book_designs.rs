@@ -0,0 +1,237 @@
+// --- BENCHMARK: NIVELES DE PRECIO VS. UN BTREEMAP POR ORDEN ---
+//
+// Mide el `OrderBook` (un `BTreeMap` de niveles, cada uno con su cola FIFO).
+// Con la feature `bench-btreemap` lo compara con el diseño anterior: un único
+// `BTreeMap` por lado con una entrada por orden, ordenado por (precio, llegada).
+// Los dos corren las mismas cargas detrás del trait `BookDesign`:
+//
+//     cargo bench --bench book_designs
+//     cargo bench --bench book_designs --features bench-btreemap
+//
+// Ojo, no hacen el mismo trabajo por orden. `price_levels` es el camino
+// completo de `add_order` y `cancel_order`: validaciones, `Trade`s con sus
+// ids, posiciones por cuenta e índice por id (sin eventos ni logs). `btreemap_per_order` es sólo la estructura: cruza, descansa y
+// cancela, sin construir trades ni validar nada. La diferencia entre los dos
+// es una cota de lo que cuesta la estructura, no una comparación de motores.
+//
+// - insert: órdenes que no cruzan, repartidas en 200 niveles por lado.
+// - cancel: se cancela todo un libro lleno, en un orden que salta entre niveles.
+// - match: órdenes agresivas que barren varios niveles de un libro lleno.
+
+#[cfg(feature = "bench-btreemap")]
+use std::cmp::Reverse;
+#[cfg(feature = "bench-btreemap")]
+use std::collections::{BTreeMap, HashMap};
+use std::hint::black_box;
+
+use criterion::{BatchSize, BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use rust_decimal::Decimal;
+
+use hft_orderbook::{Order, OrderBook, Side, logging};
+
+const ORDERS: u64 = 10_000;
+const LEVELS: u64 = 200;
+
+/// Lo mínimo que necesita el benchmark de un libro.
+trait BookDesign {
+    fn new() -> Self;
+    /// Cruza lo que pueda y deja el resto en reposo. Devuelve la cantidad de fills.
+    fn insert(&mut self, order: Order) -> usize;
+    fn cancel(&mut self, id: u64) -> bool;
+}
+
+impl BookDesign for OrderBook {
+    fn new() -> Self {
+        OrderBook::builder().allow_self_trades().build()
+    }
+
+    fn insert(&mut self, order: Order) -> usize {
+        self.add_order(order).map_or(0, |trades| trades.len())
+    }
+
+    fn cancel(&mut self, id: u64) -> bool {
+        self.cancel_order(id).is_some()
+    }
+}
+
+// El diseño anterior: una entrada por orden, la llegada desempata dentro del precio.
+#[cfg(feature = "bench-btreemap")]
+#[derive(Default)]
+struct BTreeMapBook {
+    bids: BTreeMap<(Reverse<Decimal>, u64), Order>, // Mejor (más alto) primero
+    asks: BTreeMap<(Decimal, u64), Order>,
+    by_id: HashMap<u64, (Side, Decimal, u64)>,
+    arrival: u64,
+}
+
+#[cfg(feature = "bench-btreemap")]
+impl BTreeMapBook {
+    // Barre el lado contrario mientras cruce; devuelve la cantidad de fills.
+    fn sweep(&mut self, order: &mut Order) -> usize {
+        let mut fills = 0;
+        while order.amount > Decimal::ZERO {
+            let maker = match order.side {
+                Side::Buy => self.asks.first_entry().filter(|entry| entry.key().0 <= order.price).map(|entry| entry.into_mut()),
+                Side::Sell => self.bids.first_entry().filter(|entry| entry.key().0 .0 >= order.price).map(|entry| entry.into_mut()),
+            };
+            let Some(maker) = maker else { break };
+            let amount = maker.amount.min(order.amount);
+            maker.amount -= amount;
+            order.amount -= amount;
+            fills += 1;
+            if maker.amount.is_zero() {
+                let id = maker.id;
+                match order.side {
+                    Side::Buy => drop(self.asks.pop_first()),
+                    Side::Sell => drop(self.bids.pop_first()),
+                }
+                self.by_id.remove(&id);
+            }
+        }
+        fills
+    }
+}
+
+#[cfg(feature = "bench-btreemap")]
+impl BookDesign for BTreeMapBook {
+    fn new() -> Self {
+        Self::default()
+    }
+
+    fn insert(&mut self, mut order: Order) -> usize {
+        let fills = self.sweep(&mut order);
+        if order.amount > Decimal::ZERO {
+            self.arrival += 1;
+            self.by_id.insert(order.id, (order.side, order.price, self.arrival));
+            match order.side {
+                Side::Buy => drop(self.bids.insert((Reverse(order.price), self.arrival), order)),
+                Side::Sell => drop(self.asks.insert((order.price, self.arrival), order)),
+            }
+        }
+        fills
+    }
+
+    fn cancel(&mut self, id: u64) -> bool {
+        let Some((side, price, arrival)) = self.by_id.remove(&id) else { return false };
+        match side {
+            Side::Buy => self.bids.remove(&(Reverse(price), arrival)).is_some(),
+            Side::Sell => self.asks.remove(&(price, arrival)).is_some(),
+        }
+    }
+}
+
+// --- CARGAS ---
+
+fn order(id: u64, side: Side, price: u64, amount: u64) -> Order {
+    Order::builder(id, side, Decimal::from(price), Decimal::from(amount)).timestamp(id).build().expect("orden válida")
+}
+
+// Libro lleno sin cruces: compras de 1_000 hacia abajo y ventas de 1_001 hacia arriba.
+fn resting_orders() -> Vec<Order> {
+    (1..=ORDERS)
+        .map(|id| {
+            let level = (id * 7) % LEVELS;
+            match id % 2 {
+                0 => order(id, Side::Buy, 1_000 - level, 1 + id % 5),
+                _ => order(id, Side::Sell, 1_001 + level, 1 + id % 5),
+            }
+        })
+        .collect()
+}
+
+// Cancelaciones en un orden que salta entre niveles y lados.
+fn cancel_ids() -> Vec<u64> {
+    (0..ORDERS).map(|i| (i * 7_919) % ORDERS + 1).collect()
+}
+
+// Compras agresivas que barren unos cuantos niveles de ventas cada una.
+fn aggressive_orders() -> Vec<Order> {
+    (1..=ORDERS / 10).map(|i| order(ORDERS + i, Side::Buy, 1_001 + LEVELS, 15)).collect()
+}
+
+fn filled<B: BookDesign>(orders: &[Order]) -> B {
+    let mut book = B::new();
+    for order in orders {
+        book.insert(order.clone());
+    }
+    book
+}
+
+fn bench_design<B: BookDesign>(c: &mut Criterion, name: &str) {
+    let resting = resting_orders();
+    let cancels = cancel_ids();
+    let aggressive = aggressive_orders();
+
+    let mut group = c.benchmark_group("insert");
+    group.throughput(Throughput::Elements(ORDERS));
+    group.bench_function(BenchmarkId::from_parameter(name), |b| {
+        b.iter_batched(
+            || (B::new(), resting.clone()),
+            |(mut book, orders)| {
+                for order in orders {
+                    black_box(book.insert(order));
+                }
+                book
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    group.finish();
+
+    let mut group = c.benchmark_group("cancel");
+    group.throughput(Throughput::Elements(ORDERS));
+    group.bench_function(BenchmarkId::from_parameter(name), |b| {
+        b.iter_batched(
+            || filled::<B>(&resting),
+            |mut book| {
+                for &id in &cancels {
+                    black_box(book.cancel(id));
+                }
+                book
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    group.finish();
+
+    let mut group = c.benchmark_group("match");
+    group.throughput(Throughput::Elements(aggressive.len() as u64));
+    group.bench_function(BenchmarkId::from_parameter(name), |b| {
+        b.iter_batched(
+            || (filled::<B>(&resting), aggressive.clone()),
+            |(mut book, orders)| {
+                for order in orders {
+                    black_box(book.insert(order));
+                }
+                book
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    group.finish();
+}
+
+// Fills de la carga de match y cancelaciones exitosas: tienen que coincidir
+// entre los dos diseños para que las cargas sean las mismas.
+#[cfg(feature = "bench-btreemap")]
+fn workload_result<B: BookDesign>() -> (usize, usize) {
+    let resting = resting_orders();
+    let mut book = filled::<B>(&resting);
+    let fills = aggressive_orders().into_iter().map(|order| book.insert(order)).sum();
+    let mut book = filled::<B>(&resting);
+    let cancelled = cancel_ids().into_iter().filter(|&id| book.cancel(id)).count();
+    (fills, cancelled)
+}
+
+fn book_designs(c: &mut Criterion) {
+    logging::set_enabled(false); // Los logs del libro dominarían la medición
+    bench_design::<OrderBook>(c, "price_levels");
+    #[cfg(feature = "bench-btreemap")]
+    {
+        assert_eq!(workload_result::<OrderBook>(), workload_result::<BTreeMapBook>(), "los dos diseños tienen que correr la misma carga");
+        bench_design::<BTreeMapBook>(c, "btreemap_per_order");
+    }
+}
+
+criterion_group!(benches, book_designs);
+criterion_main!(benches);
@@ -0,0 +1,12 @@
+fn main() {
+    // Sólo la feature `grpc` necesita generar código a partir del .proto
+    #[cfg(feature = "grpc")]
+    {
+        if std::env::var_os("PROTOC").is_none() {
+            let protoc = protoc_bin_vendored::protoc_bin_path().expect("protoc vendorizado no disponible");
+            // SAFETY: el build script es single-thread; nadie más lee el entorno en paralelo.
+            unsafe { std::env::set_var("PROTOC", protoc) };
+        }
+        tonic_prost_build::compile_protos("proto/orderbook.proto").expect("no se pudo compilar proto/orderbook.proto");
+    }
+}
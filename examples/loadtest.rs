@@ -0,0 +1,78 @@
+// --- LOAD TEST: THROUGHPUT Y LATENCIA ---
+//
+// Dispara órdenes sintéticas contra el engine y al final imprime una línea
+// `clave=valor` por métrica, fácil de parsear desde CI:
+//
+//     cargo run --release --example loadtest -- [millones de órdenes] [productores] [semilla]
+//
+// La latencia es la de ida y vuelta de cada `submit` (router + inbox + matching).
+
+use std::env;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use hft_orderbook::{logging, Engine, LatencyHistogram, OrderGenerator, SimConfig};
+
+const SYMBOL: &str = "SIM";
+
+fn arg<T: std::str::FromStr>(position: usize, default: T) -> T {
+    env::args().nth(position).and_then(|value| value.parse().ok()).unwrap_or(default)
+}
+
+#[tokio::main]
+async fn main() {
+    let millions: f64 = arg(1, 1.0);
+    let producers: u64 = arg(2, 4).max(1);
+    let seed: u64 = arg(3, 42);
+    let total = (millions * 1_000_000.0) as u64;
+    let per_producer = total / producers;
+
+    logging::set_enabled(false); // Millones de líneas por stdout falsearían la medición
+    let engine = Arc::new(Engine::builder().symbol(SYMBOL).build());
+
+    let started = Instant::now();
+    let tasks: Vec<_> = (0..producers)
+        .map(|producer| {
+            let engine = Arc::clone(&engine);
+            let config = SimConfig { seed: seed + producer, ..SimConfig::default() };
+            // Cada productor usa su propio rango de ids
+            let orders = OrderGenerator::new(config).starting_at(producer * per_producer + 1);
+            tokio::spawn(async move {
+                let mut histogram = LatencyHistogram::new();
+                let mut trades = 0;
+                for order in orders.take(per_producer as usize) {
+                    let sent = Instant::now();
+                    trades += engine.submit(SYMBOL, order).await.map(|ack| ack.trades.len()).unwrap_or_default();
+                    histogram.record(sent.elapsed());
+                }
+                (histogram, trades)
+            })
+        })
+        .collect();
+
+    let mut histogram = LatencyHistogram::new();
+    let mut trades = 0;
+    for task in tasks {
+        let (partial, partial_trades) = task.await.expect("productor terminó con error");
+        histogram.merge(&partial);
+        trades += partial_trades;
+    }
+    let elapsed = started.elapsed();
+
+    let resting = engine.with_book(SYMBOL, |book| book.len()).await.unwrap_or_default();
+    let engine = Arc::into_inner(engine).expect("no quedan productores vivos");
+    engine.shutdown().await;
+
+    let micros = |d: Duration| d.as_secs_f64() * 1e6;
+    println!("orders={}", histogram.count());
+    println!("producers={producers}");
+    println!("trades={trades}");
+    println!("resting={resting}");
+    println!("elapsed_ms={:.3}", elapsed.as_secs_f64() * 1e3);
+    println!("orders_per_sec={:.0}", histogram.count() as f64 / elapsed.as_secs_f64());
+    println!("latency_mean_us={:.3}", micros(histogram.mean()));
+    for (label, p) in [("p50", 50.0), ("p90", 90.0), ("p99", 99.0), ("p999", 99.9)] {
+        println!("latency_{label}_us={:.3}", micros(histogram.percentile(p)));
+    }
+    println!("latency_max_us={:.3}", micros(histogram.max()));
+}
@@ -0,0 +1,164 @@
+// --- SALDOS Y RESERVAS POR CUENTA ---
+//
+// Para spot: cada cuenta tiene saldo del activo base y del cotizado, separado
+// en disponible y reservado. Una orden que entra reserva lo máximo que puede
+// llegar a pagar (el nocional a su límite si compra, la cantidad si vende);
+// cada fill paga desde esa reserva y acredita lo recibido como disponible, y
+// cuando la orden se cancela o termina lo que sobró vuelve a estar disponible.
+
+use std::collections::BTreeMap;
+
+use rust_decimal::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::error::OrderError;
+use crate::orderbook::{Order, OrderType, Side};
+
+/// Activo de un saldo: el que se negocia (base) o en el que se paga (cotizado).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Asset {
+    Base,
+    Quote,
+}
+
+impl Asset {
+    pub fn other(self) -> Asset {
+        match self {
+            Asset::Base => Asset::Quote,
+            Asset::Quote => Asset::Base,
+        }
+    }
+}
+
+/// Saldo de una cuenta en un activo.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Balance {
+    /// Lo que la cuenta puede comprometer en órdenes nuevas.
+    pub available: Decimal,
+    /// Lo comprometido en órdenes vivas.
+    pub reserved: Decimal,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+struct AccountBalances {
+    base: Balance,
+    quote: Balance,
+}
+
+impl AccountBalances {
+    fn get_mut(&mut self, asset: Asset) -> &mut Balance {
+        match asset {
+            Asset::Base => &mut self.base,
+            Asset::Quote => &mut self.quote,
+        }
+    }
+}
+
+// Reserva de una orden viva.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+struct Hold {
+    account: u64,
+    asset: Asset,
+    amount: Decimal,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Balances {
+    accounts: BTreeMap<u64, AccountBalances>,
+    holds: BTreeMap<u64, Hold>, // Por id de orden
+}
+
+impl Balances {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Saldo de la cuenta en `asset` (todo en cero si nunca depositó).
+    pub fn get(&self, account: u64, asset: Asset) -> Balance {
+        self.accounts.get(&account).map_or_else(Balance::default, |balances| match asset {
+            Asset::Base => balances.base,
+            Asset::Quote => balances.quote,
+        })
+    }
+
+    /// Lo que la orden `order_id` tiene reservado ahora.
+    pub fn held(&self, order_id: u64) -> Decimal {
+        self.holds.get(&order_id).map_or(Decimal::zero(), |hold| hold.amount)
+    }
+
+    fn balance_mut(&mut self, account: u64, asset: Asset) -> &mut Balance {
+        self.accounts.entry(account).or_default().get_mut(asset)
+    }
+
+    pub(crate) fn deposit(&mut self, account: u64, asset: Asset, amount: Decimal) {
+        self.balance_mut(account, asset).available += amount;
+    }
+
+    // Reserva `amount` de lo disponible para la orden `order_id`.
+    pub(crate) fn hold(&mut self, order_id: u64, account: u64, asset: Asset, amount: Decimal) -> Result<(), OrderError> {
+        let available = self.get(account, asset).available;
+        if amount > available {
+            return Err(OrderError::InsufficientBalance { account, asset, required: amount, available });
+        }
+        let balance = self.balance_mut(account, asset);
+        balance.available -= amount;
+        balance.reserved += amount;
+        self.holds.insert(order_id, Hold { account, asset, amount });
+        Ok(())
+    }
+
+    // `true` si la cuenta puede pasar la reserva de `order_id` a `amount`.
+    pub(crate) fn can_hold(&self, order_id: u64, account: u64, asset: Asset, amount: Decimal) -> bool {
+        let current = self.holds.get(&order_id).filter(|hold| hold.account == account && hold.asset == asset).map_or(Decimal::zero(), |hold| hold.amount);
+        amount - current <= self.get(account, asset).available
+    }
+
+    // Lleva la reserva de `order_id` a `amount`; la diferencia pasa entre
+    // disponible y reservado. En cero la reserva desaparece.
+    pub(crate) fn set_hold(&mut self, order_id: u64, amount: Decimal) {
+        let Some(hold) = self.holds.get(&order_id).copied() else { return };
+        let diff = amount - hold.amount;
+        let balance = self.balance_mut(hold.account, hold.asset);
+        balance.available -= diff;
+        balance.reserved += diff;
+        if amount.is_zero() {
+            self.holds.remove(&order_id);
+        } else {
+            self.holds.insert(order_id, Hold { amount, ..hold });
+        }
+    }
+
+    // Un fill de `order_id`: paga `paid` desde su reserva y acredita `received`
+    // del otro activo como disponible.
+    pub(crate) fn settle(&mut self, order_id: u64, paid: Decimal, received: Decimal) {
+        let Some(hold) = self.holds.get_mut(&order_id) else { return };
+        let paid = paid.min(hold.amount);
+        hold.amount -= paid;
+        let (account, asset) = (hold.account, hold.asset);
+        if hold.amount.is_zero() {
+            self.holds.remove(&order_id);
+        }
+        self.balance_mut(account, asset).reserved -= paid;
+        self.balance_mut(account, asset.other()).available += received;
+    }
+}
+
+/// Activo y monto que una orden tiene que reservar: la cantidad si vende; si
+/// compra, su nocional al peor precio que puede pagar (el límite, el límite de
+/// protección de una pegged o el presupuesto de una compra por monto). Una
+/// compra de mercado sin tope de precio no se puede reservar.
+pub fn required_hold(order: &Order) -> Result<(Asset, Decimal), OrderError> {
+    if order.side == Side::Sell {
+        return Ok((Asset::Base, order.amount));
+    }
+    let notional = match order.order_type {
+        OrderType::Limit | OrderType::PostOnly | OrderType::AllOrNone => order.price.checked_mul(order.amount),
+        OrderType::PegToOpposite { limit, .. } => limit.checked_mul(order.amount),
+        OrderType::MarketQuote { budget } => Some(budget),
+        OrderType::Market | OrderType::MarketWithSlippage { .. } | OrderType::MarketIfTouched { .. } => {
+            return Err(OrderError::NotSupported("compras de mercado sin presupuesto con control de saldos"));
+        }
+    };
+    let notional = notional.ok_or(OrderError::Overflow("nocional a reservar"))?;
+    Ok((Asset::Quote, notional.max(Decimal::zero())))
+}
@@ -0,0 +1,413 @@
+// --- SNAPSHOT BINARIO ---
+//
+// Formato plano para libros grandes, más rápido de escribir y leer que el
+// JSON de `save_snapshot`. Todo en little endian:
+//
+//     b"HFTB" | versión (u8) | secuencia (u64) | último id de trade (u64)
+//     | estado (u8) | precio de referencia | tick | lote | cantidad de órdenes (u32)
+//     | por orden: largo (u32) + registro | checksum FNV-1a de todo lo anterior (u64)
+//
+// Los `Decimal` van en sus 16 bytes nativos y los opcionales con un byte de
+// presencia adelante; los campos nuevos se agregan al final del registro y un
+// registro más corto los toma como ausentes. Las órdenes se escriben en orden
+// de prioridad, así que cada nivel recupera su cola exacta. Sólo guarda lo que
+// está en reposo, con el progreso de `cancel_at_fill_ratio` de cada orden:
+// posiciones, saldos y órdenes MIT siguen viajando en el snapshot JSON.
+
+use std::collections::BTreeMap;
+use std::io;
+
+use rust_decimal::Decimal;
+
+use crate::config::BookConfig;
+use crate::orderbook::{Order, OrderBook, OrderType, RestingOrder, Side, TimeInForce, TradeIdCounter, TradingState, Visibility};
+use crate::snapshot::{FNV_OFFSET, fnv1a};
+
+const MAGIC: &[u8; 4] = b"HFTB";
+const FORMAT_VERSION: u8 = 1;
+
+fn invalid(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+// --- ESCRITURA ---
+
+#[derive(Default)]
+struct Writer {
+    bytes: Vec<u8>,
+}
+
+impl Writer {
+    fn u8(&mut self, value: u8) {
+        self.bytes.push(value);
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn u64(&mut self, value: u64) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn decimal(&mut self, value: Decimal) {
+        self.bytes.extend_from_slice(&value.serialize());
+    }
+
+    fn opt_decimal(&mut self, value: Option<Decimal>) {
+        self.u8(value.is_some().into());
+        if let Some(value) = value {
+            self.decimal(value);
+        }
+    }
+
+    fn opt_u64(&mut self, value: Option<u64>) {
+        self.u8(value.is_some().into());
+        if let Some(value) = value {
+            self.u64(value);
+        }
+    }
+
+    fn opt_str(&mut self, value: Option<&str>) {
+        self.u8(value.is_some().into());
+        if let Some(value) = value {
+            self.u32(value.len() as u32);
+            self.bytes.extend_from_slice(value.as_bytes());
+        }
+    }
+
+    fn order(&mut self, resting: &RestingOrder, progress: Option<&(Decimal, Decimal)>) {
+        let order = &resting.order;
+        self.u64(order.id);
+        self.u8(match order.side {
+            Side::Buy => 0,
+            Side::Sell => 1,
+        });
+        self.decimal(order.price);
+        self.decimal(order.amount);
+        self.decimal(resting.shown);
+        self.u64(resting.arrival_seq);
+        self.u64(order.timestamp);
+        self.u64(order.account);
+        self.opt_u64(order.subaccount);
+        self.opt_decimal(order.min_fill);
+        self.opt_decimal(order.price_cap);
+        self.opt_str(order.client_order_id.as_deref());
+        self.opt_str(order.strategy_id.as_deref());
+        self.opt_str(order.venue_tag.as_deref());
+        match order.order_type {
+            OrderType::Limit => self.u8(0),
+            OrderType::Market => self.u8(1),
+            OrderType::MarketWithSlippage { max_slippage } => { self.u8(2); self.decimal(max_slippage); },
+            OrderType::MarketQuote { budget } => { self.u8(3); self.decimal(budget); },
+            OrderType::PostOnly => self.u8(4),
+            OrderType::MarketIfTouched { trigger } => { self.u8(5); self.decimal(trigger); },
+            OrderType::AllOrNone => self.u8(6),
+            OrderType::PegToOpposite { offset, limit } => { self.u8(7); self.decimal(offset); self.decimal(limit); },
+        }
+        match order.visibility {
+            Visibility::Visible => self.u8(0),
+            Visibility::Hidden => self.u8(1),
+            Visibility::Iceberg { peak } => { self.u8(2); self.decimal(peak); },
+        }
+        match order.time_in_force {
+            TimeInForce::Gtc => self.u8(0),
+            TimeInForce::Gtt { expire_at } => { self.u8(1); self.u64(expire_at); },
+            TimeInForce::Day => self.u8(2),
+            TimeInForce::Ioc => self.u8(3),
+            TimeInForce::FokOrRest => self.u8(4),
+        }
+        // Campos agregados al final: los registros anteriores a ellos terminan antes
+        self.opt_decimal(order.cancel_at_fill_ratio);
+        // (tamaño original, ejecutado): sin esto el umbral se mediría sobre lo que queda
+        self.u8(progress.is_some().into());
+        if let Some(&(original, filled)) = progress {
+            self.decimal(original);
+            self.decimal(filled);
+        }
+    }
+}
+
+// --- LECTURA ---
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> io::Result<&'a [u8]> {
+        if self.bytes.len() < len {
+            return Err(invalid("snapshot binario truncado"));
+        }
+        let (head, tail) = self.bytes.split_at(len);
+        self.bytes = tail;
+        Ok(head)
+    }
+
+    fn array<const N: usize>(&mut self) -> io::Result<[u8; N]> {
+        Ok(self.take(N)?.try_into().expect("largo pedido"))
+    }
+
+    fn u8(&mut self) -> io::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    fn u64(&mut self) -> io::Result<u64> {
+        Ok(u64::from_le_bytes(self.array()?))
+    }
+
+    fn decimal(&mut self) -> io::Result<Decimal> {
+        let bytes = self.array::<16>()?;
+        // Byte 2 = escala; más de 28 decimales no es un `Decimal` válido
+        if bytes[2] > 28 {
+            return Err(invalid(format!("decimal inválido (escala {})", bytes[2])));
+        }
+        Ok(Decimal::deserialize(bytes))
+    }
+
+    fn present(&mut self) -> io::Result<bool> {
+        match self.u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            other => Err(invalid(format!("marca de opcional inválida: {other}"))),
+        }
+    }
+
+    fn opt_decimal(&mut self) -> io::Result<Option<Decimal>> {
+        if self.present()? { self.decimal().map(Some) } else { Ok(None) }
+    }
+
+    fn opt_u64(&mut self) -> io::Result<Option<u64>> {
+        if self.present()? { self.u64().map(Some) } else { Ok(None) }
+    }
+
+    fn opt_string(&mut self) -> io::Result<Option<String>> {
+        if !self.present()? {
+            return Ok(None);
+        }
+        let len = self.u32()? as usize;
+        let text = std::str::from_utf8(self.take(len)?).map_err(|_| invalid("texto no UTF-8"))?;
+        Ok(Some(text.to_string()))
+    }
+
+    fn order(&mut self) -> io::Result<(RestingOrder, Option<(Decimal, Decimal)>)> {
+        let id = self.u64()?;
+        let side = match self.u8()? {
+            0 => Side::Buy,
+            1 => Side::Sell,
+            other => return Err(invalid(format!("lado inválido: {other}"))),
+        };
+        let (price, amount, shown) = (self.decimal()?, self.decimal()?, self.decimal()?);
+        let (arrival_seq, timestamp, account) = (self.u64()?, self.u64()?, self.u64()?);
+        let subaccount = self.opt_u64()?;
+        let (min_fill, price_cap) = (self.opt_decimal()?, self.opt_decimal()?);
+        let (client_order_id, strategy_id, venue_tag) = (self.opt_string()?, self.opt_string()?, self.opt_string()?);
+        let order_type = match self.u8()? {
+            0 => OrderType::Limit,
+            1 => OrderType::Market,
+            2 => OrderType::MarketWithSlippage { max_slippage: self.decimal()? },
+            3 => OrderType::MarketQuote { budget: self.decimal()? },
+            4 => OrderType::PostOnly,
+            5 => OrderType::MarketIfTouched { trigger: self.decimal()? },
+            6 => OrderType::AllOrNone,
+            7 => OrderType::PegToOpposite { offset: self.decimal()?, limit: self.decimal()? },
+            other => return Err(invalid(format!("tipo de orden inválido: {other}"))),
+        };
+        let visibility = match self.u8()? {
+            0 => Visibility::Visible,
+            1 => Visibility::Hidden,
+            2 => Visibility::Iceberg { peak: self.decimal()? },
+            other => return Err(invalid(format!("visibilidad inválida: {other}"))),
+        };
+        let time_in_force = match self.u8()? {
+            0 => TimeInForce::Gtc,
+            1 => TimeInForce::Gtt { expire_at: self.u64()? },
+            2 => TimeInForce::Day,
+            3 => TimeInForce::Ioc,
+            4 => TimeInForce::FokOrRest,
+            other => return Err(invalid(format!("time-in-force inválido: {other}"))),
+        };
+        let cancel_at_fill_ratio = if self.bytes.is_empty() { None } else { self.opt_decimal()? };
+        let progress = if self.bytes.is_empty() || !self.present()? { None } else { Some((self.decimal()?, self.decimal()?)) };
+        let order = Order {
+            id,
+            price,
+            amount,
+            side,
+            timestamp,
+            account,
+            subaccount,
+            min_fill,
+            price_cap,
+            cancel_at_fill_ratio,
+            client_order_id,
+            strategy_id,
+            venue_tag,
+            order_type,
+            visibility,
+            time_in_force,
+        };
+        Ok((RestingOrder { order, shown, arrival_seq }, progress))
+    }
+}
+
+impl OrderBook {
+    /// Snapshot binario de las órdenes en reposo, con secuencia, tick y lote
+    /// en el encabezado. Se lee con `OrderBook::from_bytes`.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Writer::default();
+        out.bytes.extend_from_slice(MAGIC);
+        out.u8(FORMAT_VERSION);
+        out.u64(self.sequence());
+        out.u64(self.trade_id_counter().last());
+        out.u8(match self.trading_state() {
+            TradingState::Open => 0,
+            TradingState::CancelOnly => 1,
+            TradingState::Halted => 2,
+        });
+        out.opt_decimal(self.reference_price());
+        out.opt_decimal(self.config().tick_size);
+        out.opt_decimal(self.config().lot_size);
+
+        let count_at = out.bytes.len();
+        out.u32(0);
+        let mut record = Writer::default();
+        let mut count = 0u32;
+        for resting in self.resting_orders() {
+            record.bytes.clear();
+            record.order(resting, self.fill_progress().get(&resting.order.id));
+            out.u32(record.bytes.len() as u32);
+            out.bytes.extend_from_slice(&record.bytes);
+            count += 1;
+        }
+        out.bytes[count_at..count_at + 4].copy_from_slice(&count.to_le_bytes());
+        let checksum = fnv1a(FNV_OFFSET, &out.bytes);
+        out.u64(checksum);
+        out.bytes
+    }
+
+    /// Reconstruye un libro desde `to_bytes`. El tick y el lote del
+    /// encabezado reemplazan los de `config`. Un buffer truncado, con el
+    /// checksum roto o con un registro inválido se rechaza con `InvalidData`.
+    pub fn from_bytes(bytes: &[u8], mut config: BookConfig) -> io::Result<OrderBook> {
+        let Some(body_len) = bytes.len().checked_sub(8) else { return Err(invalid("snapshot binario truncado")) };
+        let (body, checksum) = bytes.split_at(body_len);
+        if body.len() < MAGIC.len() || &body[..MAGIC.len()] != MAGIC {
+            return Err(invalid("no es un snapshot binario del libro (magic inválido)"));
+        }
+        if fnv1a(FNV_OFFSET, body) != u64::from_le_bytes(checksum.try_into().expect("8 bytes")) {
+            return Err(invalid("checksum del snapshot binario inválido"));
+        }
+
+        let mut reader = Reader { bytes: &body[MAGIC.len()..] };
+        let version = reader.u8()?;
+        if version != FORMAT_VERSION {
+            return Err(invalid(format!("versión de snapshot binario no soportada: {version}")));
+        }
+        let (sequence, last_trade_id) = (reader.u64()?, reader.u64()?);
+        let trading_state = match reader.u8()? {
+            0 => TradingState::Open,
+            1 => TradingState::CancelOnly,
+            2 => TradingState::Halted,
+            other => return Err(invalid(format!("estado de negociación inválido: {other}"))),
+        };
+        let reference_price = reader.opt_decimal()?;
+        config.tick_size = reader.opt_decimal()?;
+        config.lot_size = reader.opt_decimal()?;
+
+        let count = reader.u32()?;
+        let (mut orders, mut fill_progress) = (Vec::new(), BTreeMap::new());
+        for _ in 0..count {
+            let len = reader.u32()? as usize;
+            let mut record = Reader { bytes: reader.take(len)? };
+            let (resting, progress) = record.order()?;
+            if let Some(progress) = progress {
+                fill_progress.insert(resting.order.id, progress);
+            }
+            orders.push(resting);
+            if !record.bytes.is_empty() {
+                return Err(invalid("registro de orden con bytes de más"));
+            }
+        }
+        if !reader.bytes.is_empty() {
+            return Err(invalid("bytes de más al final del snapshot binario"));
+        }
+
+        let mut book = OrderBook::restore(config, sequence, reference_price, TradeIdCounter::starting_after(last_trade_id), orders);
+        book.restore_trading_state(trading_state);
+        book.restore_fill_progress(fill_progress);
+        Ok(book)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal_macros::dec;
+
+    use super::*;
+    use crate::orderbook::tests::order;
+
+    // Libro grande con una mezcla de tipos, visibilidades y etiquetas, y algún trade.
+    fn large_book() -> OrderBook {
+        let mut book = OrderBook::builder().tick_size(dec!(0.5)).lot_size(dec!(1)).build();
+        for id in 1..=2_000u64 {
+            let level = Decimal::from(id % 50) / dec!(2);
+            let (side, price) = if id % 2 == 0 { (Side::Buy, dec!(1000) - level) } else { (Side::Sell, dec!(1001) + level) };
+            let mut resting = order(id, side, price, Decimal::from(1 + id % 7));
+            match id % 5 {
+                0 => resting.visibility = Visibility::Iceberg { peak: dec!(1) },
+                1 => resting.visibility = Visibility::Hidden,
+                2 => resting.time_in_force = TimeInForce::Gtt { expire_at: u64::MAX },
+                3 => resting.strategy_id = Some(format!("s{}", id % 3)),
+                _ => resting.client_order_id = Some(format!("c-{id}")),
+            }
+            book.add_order(resting).unwrap();
+        }
+        book.add_order(order(5_000, Side::Buy, dec!(1001), dec!(3))).unwrap();
+        // Una orden con `cancel_at_fill_ratio` ejecutada en parte: 2 de 10
+        book.add_order(Order { cancel_at_fill_ratio: Some(dec!(0.5)), ..order(6_000, Side::Buy, dec!(1000.5), dec!(10)) }).unwrap();
+        book.add_order(order(6_001, Side::Sell, dec!(1000.5), dec!(5))).unwrap();
+        book
+    }
+
+    #[test]
+    fn a_large_book_round_trips_to_an_identical_book() {
+        let book = large_book();
+        let bytes = book.to_bytes();
+        let restored = OrderBook::from_bytes(&bytes, BookConfig::default()).unwrap();
+        assert!(restored.diff(&book).is_empty(), "{:?}", restored.diff(&book));
+        assert_eq!(restored.sequence(), book.sequence());
+        assert_eq!(restored.trade_id_counter().last(), book.trade_id_counter().last());
+        assert_eq!((restored.config().tick_size, restored.config().lot_size), (Some(dec!(0.5)), Some(dec!(1))));
+        for side in [Side::Buy, Side::Sell] {
+            assert!(restored.iter_orders(side).eq(book.iter_orders(side)));
+        }
+        assert_eq!(restored.filled_ratio(6_000), Some(dec!(0.2)));
+        assert_eq!(restored.fill_progress(), book.fill_progress());
+        assert_eq!(restored.to_bytes(), bytes);
+    }
+
+    #[test]
+    fn a_truncated_or_corrupt_buffer_is_rejected() {
+        let bytes = large_book().to_bytes();
+        let rejected = |bytes: &[u8]| OrderBook::from_bytes(bytes, BookConfig::default()).unwrap_err().to_string();
+
+        assert_eq!(rejected(&[]), "snapshot binario truncado");
+        assert_eq!(rejected(&bytes[..bytes.len() / 2]), "checksum del snapshot binario inválido");
+        let mut flipped = bytes.clone();
+        flipped[100] ^= 0xff;
+        assert_eq!(rejected(&flipped), "checksum del snapshot binario inválido");
+        assert!(rejected(&bytes[4..]).contains("magic inválido"));
+
+        // Con el checksum recalculado, una versión desconocida también se rechaza
+        let mut future = bytes[..bytes.len() - 8].to_vec();
+        future[MAGIC.len()] = FORMAT_VERSION + 1;
+        let checksum = fnv1a(FNV_OFFSET, &future);
+        future.extend_from_slice(&checksum.to_le_bytes());
+        assert_eq!(rejected(&future), "versión de snapshot binario no soportada: 2");
+    }
+}
@@ -0,0 +1,78 @@
+// --- RELOJ INYECTABLE ---
+//
+// El libro no lee la hora por su cuenta: si tiene un `Clock`, lo usa para
+// sellar las órdenes entrantes y para vencer las órdenes con expiración. En
+// producción va el reloj del sistema; en simulaciones y replays, un reloj
+// virtual que sólo avanza cuando se lo pide, así no hace falta dormir.
+
+use std::fmt;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Fuente de tiempo del libro, en milisegundos.
+pub trait Clock: fmt::Debug + Send + Sync {
+    fn now(&self) -> u64;
+}
+
+/// Reloj de pared: milisegundos desde el epoch Unix.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> u64 {
+        SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or_default()
+    }
+}
+
+/// Reloj simulado. Los clones comparten la misma hora, así que se puede
+/// inyectar en el libro y seguir avanzándolo desde afuera.
+#[derive(Debug, Clone, Default)]
+pub struct VirtualClock(Arc<AtomicU64>);
+
+impl VirtualClock {
+    pub fn new(start: u64) -> Self {
+        Self(Arc::new(AtomicU64::new(start)))
+    }
+
+    /// Adelanta el reloj `millis` y devuelve la nueva hora.
+    pub fn advance(&self, millis: u64) -> u64 {
+        self.0.fetch_add(millis, Ordering::Relaxed) + millis
+    }
+
+    /// Pone el reloj en `now`. No controla que el tiempo vaya hacia adelante.
+    pub fn set(&self, now: u64) {
+        self.0.store(now, Ordering::Relaxed);
+    }
+}
+
+impl Clock for VirtualClock {
+    fn now(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal_macros::dec;
+
+    use super::*;
+    use crate::orderbook::tests::order;
+    use crate::orderbook::{Order, OrderBook, Side, TimeInForce};
+
+    #[test]
+    fn advancing_virtual_time_expires_a_gtt_order() {
+        let clock = VirtualClock::new(1_000);
+        let mut book = OrderBook::builder().clock(clock.clone()).build();
+        let gtt = Order { time_in_force: TimeInForce::Gtt { expire_at: 1_500 }, ..order(1, Side::Buy, dec!(100), dec!(1)) };
+        book.add_order(gtt).unwrap();
+
+        assert_eq!(clock.advance(499), 1_499);
+        assert!(book.expire_orders().is_empty());
+        assert!(book.order(1).is_some());
+
+        clock.advance(1);
+        assert_eq!(book.expire_orders().iter().map(|o| o.id).collect::<Vec<_>>(), [1]);
+        assert!(book.order(1).is_none());
+    }
+}
@@ -0,0 +1,535 @@
+// --- CONFIGURACIÓN DEL LIBRO ---
+
+use std::sync::Arc;
+
+use rust_decimal::{Decimal, RoundingStrategy};
+
+use crate::clock::Clock;
+use crate::matcher::Matcher;
+use crate::settlement::SettlementHandler;
+use crate::orderbook::{Order, OrderBook};
+
+/// Qué hacer cuando una orden entrante cruzaría contra otra de la misma cuenta
+/// (o subcuenta, según `StpScope`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SelfTradePrevention {
+    /// Se cancela el resto de la orden entrante; la del libro sigue.
+    #[default]
+    CancelNewest,
+    /// Se cancela la orden del libro y la entrante sigue matcheando.
+    CancelOldest,
+    /// Se cancelan las dos.
+    CancelBoth,
+    /// Se restan la cantidad menor de ambas: la más chica desaparece y la
+    /// más grande queda reducida. No se genera trade.
+    DecrementAndCancel,
+}
+
+/// Entre qué órdenes se considera self-trade.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StpScope {
+    /// Dos órdenes de la misma cuenta.
+    #[default]
+    Account,
+    /// Dos órdenes de la misma cuenta y la misma subcuenta. Las órdenes sin
+    /// subcuenta sólo chocan entre sí.
+    Subaccount,
+}
+
+impl StpScope {
+    /// `true` si cruzar `a` contra `b` sería un self-trade.
+    pub fn same_owner(self, a: &Order, b: &Order) -> bool {
+        match self {
+            StpScope::Account => a.account == b.account,
+            StpScope::Subaccount => a.account == b.account && a.subaccount == b.subaccount,
+        }
+    }
+}
+
+/// Qué hacer cuando una orden abriría un nivel nuevo en un lado que ya tiene
+/// `max_levels_per_side` niveles.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DepthCapPolicy {
+    /// Se rechaza la orden (o se cancela su resto si ya ejecutó algo).
+    #[default]
+    Reject,
+    /// Se cancelan las órdenes del peor nivel para hacerle lugar. Un nivel que
+    /// sería él mismo el peor se rechaza.
+    EvictWorst,
+}
+
+/// Cuándo se cruzan las órdenes entrantes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MatchingMode {
+    /// Cada orden se cruza al llegar.
+    #[default]
+    Continuous,
+    /// Las órdenes se encolan y se cruzan todas juntas, a un único precio, en
+    /// cada llamada a `OrderBook::uncross` (subastas frecuentes por lotes).
+    Batch,
+}
+
+/// Qué desempata la prioridad entre órdenes al mismo precio.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PriorityTieBreak {
+    /// Orden de llegada al libro (secuencia propia del libro). No depende de
+    /// los relojes de los clientes.
+    #[default]
+    ArrivalSequence,
+    /// El `timestamp` de la orden; a igual timestamp, orden de llegada. Sólo
+    /// tiene sentido si los timestamps son confiables (p. ej. los pone el libro
+    /// con `OrderBook::set_clock`).
+    Timestamp,
+}
+
+/// Prioridad de las órdenes ocultas frente a las visibles del mismo precio.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HiddenPriority {
+    /// Todas compiten por orden de llegada, sin importar la visibilidad.
+    #[default]
+    TimeOrder,
+    /// Las ocultas ejecutan recién cuando no queda nada visible en el nivel.
+    /// Los icebergs cuentan como visibles: al reponer el pico van al final de
+    /// las visibles, delante de las ocultas.
+    BehindVisible,
+}
+
+/// A qué precio ejecuta una orden límite que cruza.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PriceImprovement {
+    /// Al precio de la orden en reposo.
+    #[default]
+    None,
+    /// Al punto medio entre el límite de la orden entrante y el precio en
+    /// reposo: la mejora se reparte entre las dos puntas. Con `tick_size`, un
+    /// medio que cae entre dos ticks se redondea hacia el precio en reposo. No
+    /// aplica a órdenes de mercado (no tienen límite) ni a los repartos de un
+    /// matcher a medida.
+    Midpoint,
+}
+
+/// Qué hacer con una orden de mercado cuando el lado contrario está vacío.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MarketNoLiquidity {
+    /// Se rechaza con `OrderError::NoLiquidity` sin tocar el libro.
+    #[default]
+    Reject,
+    /// Si el libro tiene precio de referencia, se acepta sin ejecutar nada: no
+    /// queda en el libro y el acuse reporta todo como cancelado. Sin
+    /// referencia se rechaza igual que con `Reject`.
+    ZeroFill,
+}
+
+/// Qué hacer con una cancelación que llega antes de `min_resting_time`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MinRestingPolicy {
+    /// Se cancela igual, pero se cuenta en `OrderBook::fast_cancels`.
+    #[default]
+    Flag,
+    /// No se cancela: la orden sigue en el libro.
+    Reject,
+}
+
+/// Qué pasa con la prioridad de una punta que `OrderBook::requote` vuelve a
+/// poner al mismo precio. Los mercados difieren en si un cancel-replace al
+/// mismo precio reinicia el reloj.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RequotePriority {
+    /// Se cancela y entra una orden nueva, al final de la cola.
+    #[default]
+    Reset,
+    /// Si el precio no cambia y la cantidad no crece (contra lo que le queda),
+    /// la punta se achica en el lugar y conserva su id y su lugar en la cola.
+    KeepSamePrice,
+}
+
+/// Cómo se redondean comisiones y PnL a `money_precision` decimales.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RoundingMode {
+    /// Al par más cercano (redondeo bancario): 0.125 -> 0.12, 0.135 -> 0.14.
+    #[default]
+    HalfEven,
+    /// Mitades lejos del cero: 0.125 -> 0.13.
+    HalfUp,
+    /// Mitades hacia el cero: 0.125 -> 0.12.
+    HalfDown,
+    /// Siempre lejos del cero: 0.121 -> 0.13.
+    Up,
+    /// Siempre hacia el cero (truncar): 0.129 -> 0.12.
+    Down,
+}
+
+impl RoundingMode {
+    pub fn round(self, value: Decimal, decimals: u32) -> Decimal {
+        let strategy = match self {
+            RoundingMode::HalfEven => RoundingStrategy::MidpointNearestEven,
+            RoundingMode::HalfUp => RoundingStrategy::MidpointAwayFromZero,
+            RoundingMode::HalfDown => RoundingStrategy::MidpointTowardZero,
+            RoundingMode::Up => RoundingStrategy::AwayFromZero,
+            RoundingMode::Down => RoundingStrategy::ToZero,
+        };
+        value.round_dp_with_strategy(decimals, strategy)
+    }
+}
+
+/// Circuit breaker de volatilidad: si el último precio se mueve más de
+/// `max_move` (fracción: 0.05 = 5%) dentro de `window` ms, el libro pasa a
+/// `CancelOnly` y se reabre solo a los `cooldown` ms.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CircuitBreaker {
+    pub max_move: Decimal,
+    pub window: u64,
+    pub cooldown: u64,
+}
+
+/// Comisiones de un instrumento como fracción del nocional (0.001 = 10 bps).
+/// Una tasa negativa es un rebate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct FeeSchedule {
+    pub maker: Decimal,
+    pub taker: Decimal,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BookConfig {
+    /// `None` desactiva la prevención de self-trade.
+    pub self_trade_prevention: Option<SelfTradePrevention>,
+    pub stp_scope: StpScope,
+    /// Banda de precio como fracción del precio de referencia (0.05 = ±5%).
+    /// Las órdenes límite fuera de la banda se rechazan.
+    pub price_band: Option<Decimal>,
+    /// Tope de niveles de precio por lado, para acotar la memoria.
+    pub max_levels_per_side: Option<usize>,
+    pub depth_cap_policy: DepthCapPolicy,
+    /// Decimales con que se publican los precios (snapshots, deltas, checksums).
+    /// Internamente se guarda la precisión completa; conviene que no sea más
+    /// gruesa que el tick, o dos niveles distintos se verían con el mismo precio.
+    pub price_precision: Option<u32>,
+    /// Descarta los fills de un matcher a medida que ejecutarían peor que el
+    /// mejor precio contrario.
+    pub trade_through_protection: bool,
+    /// Acepta precios cero o negativos (spreads de calendario, energía...).
+    /// La cantidad tiene que seguir siendo positiva.
+    pub allow_negative_prices: bool,
+    pub matching_mode: MatchingMode,
+    /// Hora de cierre de la sesión, en ms desde la medianoche del reloj del libro.
+    /// Al pasarla se cancelan las órdenes `Day`. `None` = sin cierre automático.
+    pub session_end: Option<u64>,
+    /// Tiempo mínimo (ms del reloj del libro) que una orden debe descansar antes
+    /// de poder cancelarla, contra el spoofing. Cuenta desde el timestamp de la
+    /// orden; sin reloj, el tiempo del libro es el mayor timestamp recibido.
+    /// Las cancelaciones del propio libro (vencimientos, cierre de sesión) no se controlan.
+    pub min_resting_time: Option<u64>,
+    pub min_resting_policy: MinRestingPolicy,
+    /// Cuántos trades recientes guarda el libro para `recent_trades` (0 = ninguno).
+    pub recent_trades: usize,
+    /// Cuántas órdenes rechazadas guarda el libro para `recent_rejects` (0 = ninguna).
+    pub recent_rejects: usize,
+    /// Decimales de comisiones y PnL.
+    pub money_precision: u32,
+    pub rounding: RoundingMode,
+    pub tie_break: PriorityTieBreak,
+    pub hidden_priority: HiddenPriority,
+    pub price_improvement: PriceImprovement,
+    pub market_no_liquidity: MarketNoLiquidity,
+    /// Lote mínimo de cantidad: las órdenes cuya cantidad no sea múltiplo se
+    /// rechazan con `OrderError::InvalidLot`. Las órdenes por monto
+    /// (`OrderType::MarketQuote`) también lo usan para dimensionar el último fill.
+    pub lot_size: Option<Decimal>,
+    /// Tick de precio: los precios límite que no sean múltiplo se rechazan con
+    /// `OrderError::InvalidTick`.
+    pub tick_size: Option<Decimal>,
+    /// Nocional mínimo (precio × cantidad, en valor absoluto) de una orden
+    /// límite; por debajo se rechaza con `OrderError::BelowMinNotional`.
+    pub min_notional: Option<Decimal>,
+    /// Comisiones maker y taker (ver `OrderBook::trade_fees`).
+    pub fees: FeeSchedule,
+    /// `None` desactiva el circuit breaker.
+    pub circuit_breaker: Option<CircuitBreaker>,
+    /// Una modificación a cantidad cero cancela la orden (si no, se rechaza).
+    pub amend_to_zero_cancels: bool,
+    pub requote_priority: RequotePriority,
+    /// Trades por lote entregado al `SettlementHandler` (1 = de a uno).
+    pub settlement_batch: usize,
+    /// Entrega el lote incompleto cuando su trade más viejo lleva estos ms
+    /// esperando (medidos con la hora del libro).
+    pub settlement_interval: Option<u64>,
+    /// Ventana de last look en ms (hora del libro): cada fill del matching
+    /// continuo queda pendiente hasta que el maker lo acepta o lo rechaza, y se
+    /// acepta solo al vencer (ver `OrderBook::accept_trade`). Los comandos
+    /// devuelven sólo los fills definitivos: en un `OrderAck`, lo pendiente
+    /// figura en `pending_qty`. Un rechazo no le devuelve la cantidad al taker
+    /// (ver `OrderBook::reject_trade`). `None` = los fills son definitivos al
+    /// cruzar.
+    pub last_look: Option<u64>,
+    /// Posición neta máxima (en valor absoluto) de cada cuenta en este libro.
+    /// Un taker que la alcanza a mitad de barrido deja de ejecutar ahí y su
+    /// resto sigue su time-in-force (se cancela si todavía cruzaría); un maker
+    /// cuya cuenta ya no puede operar se cancela cuando le toca ejecutar.
+    pub position_limit: Option<Decimal>,
+    /// Cada orden reserva de su cuenta lo que puede llegar a pagar y se
+    /// rechaza con `OrderError::InsufficientBalance` si no le alcanza (ver
+    /// `OrderBook::deposit`). Una compra de mercado necesita presupuesto.
+    pub enforce_balances: bool,
+    /// Escala (decimales) a la que se llevan precio y cantidad de cada orden al
+    /// entrar, para que `50000` y `50000.00` se vean igual en niveles, eventos
+    /// y checksums. Una orden con más decimales que la escala se rechaza.
+    pub price_scale: Option<u32>,
+    pub amount_scale: Option<u32>,
+}
+
+impl Default for BookConfig {
+    fn default() -> Self {
+        Self {
+            self_trade_prevention: Some(SelfTradePrevention::default()),
+            stp_scope: StpScope::default(),
+            price_band: None,
+            max_levels_per_side: None,
+            depth_cap_policy: DepthCapPolicy::default(),
+            price_precision: None,
+            trade_through_protection: true,
+            allow_negative_prices: false,
+            matching_mode: MatchingMode::default(),
+            session_end: None,
+            min_resting_time: None,
+            min_resting_policy: MinRestingPolicy::default(),
+            recent_trades: 0,
+            recent_rejects: 0,
+            money_precision: 8,
+            rounding: RoundingMode::default(),
+            tie_break: PriorityTieBreak::default(),
+            hidden_priority: HiddenPriority::default(),
+            price_improvement: PriceImprovement::default(),
+            market_no_liquidity: MarketNoLiquidity::default(),
+            lot_size: None,
+            tick_size: None,
+            min_notional: None,
+            fees: FeeSchedule::default(),
+            circuit_breaker: None,
+            amend_to_zero_cancels: false,
+            requote_priority: RequotePriority::default(),
+            settlement_batch: 1,
+            settlement_interval: None,
+            last_look: None,
+            position_limit: None,
+            enforce_balances: false,
+            price_scale: None,
+            amount_scale: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct OrderBookBuilder {
+    config: BookConfig,
+    matcher: Option<Arc<dyn Matcher>>,
+    clock: Option<Arc<dyn Clock>>,
+    settlement: Option<Arc<dyn SettlementHandler>>,
+}
+
+impl OrderBookBuilder {
+    pub fn self_trade_prevention(mut self, policy: SelfTradePrevention) -> Self {
+        self.config.self_trade_prevention = Some(policy);
+        self
+    }
+
+    pub fn stp_scope(mut self, scope: StpScope) -> Self {
+        self.config.stp_scope = scope;
+        self
+    }
+
+    /// Permite que una cuenta cruce contra sí misma.
+    pub fn allow_self_trades(mut self) -> Self {
+        self.config.self_trade_prevention = None;
+        self
+    }
+
+    pub fn price_band(mut self, band: Decimal) -> Self {
+        self.config.price_band = Some(band);
+        self
+    }
+
+    pub fn max_levels_per_side(mut self, levels: usize) -> Self {
+        self.config.max_levels_per_side = Some(levels);
+        self
+    }
+
+    pub fn depth_cap_policy(mut self, policy: DepthCapPolicy) -> Self {
+        self.config.depth_cap_policy = policy;
+        self
+    }
+
+    pub fn price_precision(mut self, decimals: u32) -> Self {
+        self.config.price_precision = Some(decimals);
+        self
+    }
+
+    pub fn trade_through_protection(mut self, enabled: bool) -> Self {
+        self.config.trade_through_protection = enabled;
+        self
+    }
+
+    pub fn allow_negative_prices(mut self) -> Self {
+        self.config.allow_negative_prices = true;
+        self
+    }
+
+    pub fn matching_mode(mut self, mode: MatchingMode) -> Self {
+        self.config.matching_mode = mode;
+        self
+    }
+
+    /// Cierre diario de la sesión (ms desde la medianoche), p. ej. `22 * 3_600_000`.
+    pub fn session_end(mut self, millis_of_day: u64) -> Self {
+        self.config.session_end = Some(millis_of_day);
+        self
+    }
+
+    /// Exige que las órdenes descansen al menos `millis` antes de cancelarlas
+    /// (ver `BookConfig::min_resting_time`).
+    pub fn min_resting_time(mut self, millis: u64, policy: MinRestingPolicy) -> Self {
+        self.config.min_resting_time = Some(millis);
+        self.config.min_resting_policy = policy;
+        self
+    }
+
+    /// Guarda los últimos `capacity` trades para consumidores que llegan tarde.
+    pub fn recent_trades(mut self, capacity: usize) -> Self {
+        self.config.recent_trades = capacity;
+        self
+    }
+
+    /// Guarda las últimas `capacity` órdenes rechazadas, con su motivo, para auditoría.
+    pub fn recent_rejects(mut self, capacity: usize) -> Self {
+        self.config.recent_rejects = capacity;
+        self
+    }
+
+    /// Redondeo de comisiones y PnL: `decimals` decimales con `mode`.
+    pub fn rounding(mut self, decimals: u32, mode: RoundingMode) -> Self {
+        self.config.money_precision = decimals;
+        self.config.rounding = mode;
+        self
+    }
+
+    pub fn tie_break(mut self, tie_break: PriorityTieBreak) -> Self {
+        self.config.tie_break = tie_break;
+        self
+    }
+
+    pub fn hidden_priority(mut self, priority: HiddenPriority) -> Self {
+        self.config.hidden_priority = priority;
+        self
+    }
+
+    pub fn price_improvement(mut self, improvement: PriceImprovement) -> Self {
+        self.config.price_improvement = improvement;
+        self
+    }
+
+    pub fn market_no_liquidity(mut self, policy: MarketNoLiquidity) -> Self {
+        self.config.market_no_liquidity = policy;
+        self
+    }
+
+    pub fn lot_size(mut self, lot: Decimal) -> Self {
+        self.config.lot_size = Some(lot);
+        self
+    }
+
+    pub fn tick_size(mut self, tick: Decimal) -> Self {
+        self.config.tick_size = Some(tick);
+        self
+    }
+
+    pub fn min_notional(mut self, min: Decimal) -> Self {
+        self.config.min_notional = Some(min);
+        self
+    }
+
+    pub fn fees(mut self, maker: Decimal, taker: Decimal) -> Self {
+        self.config.fees = FeeSchedule { maker, taker };
+        self
+    }
+
+    /// Corta a `CancelOnly` si el precio se mueve más de `max_move` en
+    /// `window` ms, por `cooldown` ms (ver `CircuitBreaker`).
+    pub fn circuit_breaker(mut self, max_move: Decimal, window: u64, cooldown: u64) -> Self {
+        self.config.circuit_breaker = Some(CircuitBreaker { max_move, window, cooldown });
+        self
+    }
+
+    pub fn amend_to_zero_cancels(mut self, enabled: bool) -> Self {
+        self.config.amend_to_zero_cancels = enabled;
+        self
+    }
+
+    pub fn requote_priority(mut self, priority: RequotePriority) -> Self {
+        self.config.requote_priority = priority;
+        self
+    }
+
+    /// Agrupa los trades para el `SettlementHandler` en lotes de `size`, o lo
+    /// que haya cuando el más viejo espera `interval` ms.
+    pub fn settlement_batch(mut self, size: usize, interval: Option<u64>) -> Self {
+        self.config.settlement_batch = size;
+        self.config.settlement_interval = interval;
+        self
+    }
+
+    /// Deja cada fill en last look por `window` ms (ver `BookConfig::last_look`).
+    pub fn last_look(mut self, window: u64) -> Self {
+        self.config.last_look = Some(window);
+        self
+    }
+
+    pub fn position_limit(mut self, limit: Decimal) -> Self {
+        self.config.position_limit = Some(limit);
+        self
+    }
+
+    pub fn enforce_balances(mut self, enabled: bool) -> Self {
+        self.config.enforce_balances = enabled;
+        self
+    }
+
+    /// Normaliza precio y cantidad de las órdenes entrantes a `price_decimals`
+    /// y `amount_decimals` decimales (ver `BookConfig::price_scale`).
+    pub fn normalize_scale(mut self, price_decimals: u32, amount_decimals: u32) -> Self {
+        self.config.price_scale = Some(price_decimals);
+        self.config.amount_scale = Some(amount_decimals);
+        self
+    }
+
+    /// Reparte cada nivel con `matcher` en lugar del FIFO incorporado.
+    pub fn matcher(mut self, matcher: impl Matcher + 'static) -> Self {
+        self.matcher = Some(Arc::new(matcher));
+        self
+    }
+
+    /// Handler que liquida los trades (ver `OrderBook::set_settlement_handler`).
+    pub fn settlement_handler(mut self, handler: impl SettlementHandler + 'static) -> Self {
+        self.settlement = Some(Arc::new(handler));
+        self
+    }
+
+    /// Reloj del libro (ver `OrderBook::set_clock`).
+    pub fn clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Some(Arc::new(clock));
+        self
+    }
+
+    pub fn build(self) -> OrderBook {
+        let mut book = OrderBook::with_config(self.config);
+        if let Some(matcher) = self.matcher {
+            book.set_matcher(matcher);
+        }
+        if let Some(clock) = self.clock {
+            book.set_clock(clock);
+        }
+        if let Some(handler) = self.settlement {
+            book.set_settlement_handler(handler);
+        }
+        book
+    }
+}
@@ -0,0 +1,171 @@
+// --- COPIA LOCAL DEL LIBRO A PARTIR DE DELTAS ---
+//
+// Lado consumidor del feed: arranca de un `Snapshot` y aplica los eventos del
+// libro en orden de secuencia. Todos los eventos numeran (trades, altas...),
+// así que hay que pasarle el stream completo y no sólo los `LevelDelta`: los
+// demás sólo avanzan la secuencia. Si llega un evento que saltea números, la
+// copia ya no es confiable y cada `apply` devuelve `ResyncNeeded` hasta que el
+// consumidor pida un snapshot nuevo y llame a `resync`.
+
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::fmt;
+
+use rust_decimal::Decimal;
+
+use crate::events::{BookEvent, SequencedEvent};
+use crate::orderbook::Side;
+use crate::snapshot::Snapshot;
+
+/// Se perdió al menos un evento entre `expected` y `received`: hay que volver
+/// a pedir un snapshot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResyncNeeded {
+    pub expected: u64,
+    pub received: u64,
+}
+
+impl fmt::Display for ResyncNeeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "hueco en la secuencia: se esperaba {} y llegó {}", self.expected, self.received)
+    }
+}
+
+impl std::error::Error for ResyncNeeded {}
+
+/// Copia local de los niveles públicos del libro, mantenida con deltas.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DeltaApplier {
+    seq: u64,
+    bids: BTreeMap<Reverse<Decimal>, Decimal>, // Mejor (más alto) primero
+    asks: BTreeMap<Decimal, Decimal>,
+    gap: Option<ResyncNeeded>, // Hueco detectado y todavía sin resync
+}
+
+impl DeltaApplier {
+    pub fn from_snapshot(snapshot: &Snapshot) -> Self {
+        let mut applier = Self::default();
+        applier.resync(snapshot);
+        applier
+    }
+
+    /// Reemplaza la copia por `snapshot` y vuelve a aceptar eventos a partir
+    /// de su secuencia. Con un snapshot acotado en profundidad, los niveles
+    /// más allá del tope aparecen recién cuando les llega un delta.
+    pub fn resync(&mut self, snapshot: &Snapshot) {
+        self.seq = snapshot.seq;
+        self.bids = snapshot.bids.iter().map(|level| (Reverse(level.price), level.amount)).collect();
+        self.asks = snapshot.asks.iter().map(|level| (level.price, level.amount)).collect();
+        self.gap = None;
+    }
+
+    /// Aplica el próximo evento del stream. Los de secuencia ya aplicada
+    /// (duplicados o anteriores al snapshot) se ignoran; uno que saltea números
+    /// deja la copia marcada hasta el próximo `resync`.
+    pub fn apply(&mut self, event: &SequencedEvent) -> Result<(), ResyncNeeded> {
+        if let Some(gap) = self.gap {
+            return Err(gap);
+        }
+        if event.seq <= self.seq {
+            return Ok(());
+        }
+        if event.seq != self.seq + 1 {
+            let gap = ResyncNeeded { expected: self.seq + 1, received: event.seq };
+            self.gap = Some(gap);
+            return Err(gap);
+        }
+        self.seq = event.seq;
+        if let BookEvent::LevelDelta { side, price, amount } = event.event {
+            match (side, amount.is_zero()) {
+                (Side::Buy, true) => { self.bids.remove(&Reverse(price)); },
+                (Side::Buy, false) => { self.bids.insert(Reverse(price), amount); },
+                (Side::Sell, true) => { self.asks.remove(&price); },
+                (Side::Sell, false) => { self.asks.insert(price, amount); },
+            }
+        }
+        Ok(())
+    }
+
+    /// Secuencia del último evento aplicado.
+    pub fn seq(&self) -> u64 {
+        self.seq
+    }
+
+    /// `true` si se detectó un hueco y falta el `resync`.
+    pub fn needs_resync(&self) -> bool {
+        self.gap.is_some()
+    }
+
+    /// (precio, cantidad visible) de un lado, mejor nivel primero.
+    pub fn levels(&self, side: Side) -> Box<dyn Iterator<Item = (Decimal, Decimal)> + '_> {
+        match side {
+            Side::Buy => Box::new(self.bids.iter().map(|(Reverse(price), amount)| (*price, *amount))),
+            Side::Sell => Box::new(self.asks.iter().map(|(price, amount)| (*price, *amount))),
+        }
+    }
+
+    pub fn best_bid(&self) -> Option<Decimal> {
+        self.bids.keys().next().map(|Reverse(price)| *price)
+    }
+
+    pub fn best_ask(&self) -> Option<Decimal> {
+        self.asks.keys().next().copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal_macros::dec;
+
+    use super::*;
+    use crate::orderbook::OrderBook;
+    use crate::orderbook::tests::order;
+
+    #[test]
+    fn in_order_deltas_keep_the_copy_in_sync() {
+        let mut book = OrderBook::new();
+        book.enable_events();
+        book.add_order(order(1, Side::Buy, dec!(99), dec!(2))).unwrap();
+        let mut copy = DeltaApplier::from_snapshot(&book.snapshot(10));
+        book.drain_events();
+
+        book.add_order(order(2, Side::Sell, dec!(101), dec!(3))).unwrap();
+        book.add_order(order(3, Side::Buy, dec!(101), dec!(1))).unwrap();
+        book.cancel_order(1).unwrap();
+        let events = book.drain_events();
+        for event in &events {
+            copy.apply(event).unwrap();
+        }
+        // Un duplicado se ignora
+        copy.apply(&events[0]).unwrap();
+        assert_eq!(copy.seq(), book.sequence());
+        assert_eq!((copy.best_bid(), copy.best_ask()), (book.best_bid(), book.best_ask()));
+        assert_eq!(copy.levels(Side::Sell).collect::<Vec<_>>(), [(dec!(101), dec!(2))]);
+    }
+
+    #[test]
+    fn a_gap_asks_for_a_resync_until_a_new_snapshot_arrives() {
+        let mut book = OrderBook::new();
+        book.enable_events();
+        let mut copy = DeltaApplier::from_snapshot(&book.snapshot(10));
+        book.add_order(order(1, Side::Buy, dec!(99), dec!(2))).unwrap();
+        book.add_order(order(2, Side::Buy, dec!(98), dec!(2))).unwrap();
+        let events = book.drain_events();
+
+        // Se pierde el primero: el segundo llega fuera de secuencia
+        let gap = ResyncNeeded { expected: 1, received: events[1].seq };
+        assert_eq!(copy.apply(&events[1]), Err(gap));
+        assert!(copy.needs_resync());
+        // Ni siquiera el que faltaba arregla la copia
+        assert_eq!(copy.apply(&events[0]), Err(gap));
+
+        copy.resync(&book.snapshot(10));
+        assert!(!copy.needs_resync());
+        assert_eq!(copy.levels(Side::Buy).collect::<Vec<_>>(), [(dec!(99), dec!(2)), (dec!(98), dec!(2))]);
+        book.cancel_order(2).unwrap();
+        for event in book.drain_events() {
+            copy.apply(&event).unwrap();
+        }
+        assert_eq!(copy.levels(Side::Buy).collect::<Vec<_>>(), [(dec!(99), dec!(2))]);
+    }
+}
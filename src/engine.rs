@@ -0,0 +1,528 @@
+// --- MOTOR SHARDEADO (UNA TAREA POR SÍMBOLO) ---
+//
+// Cada símbolo tiene su propio libro, su propia tarea de tokio y su propio
+// inbox mpsc. El `Engine` hace de router: mira el símbolo y despacha el
+// comando a la tarea correspondiente. Los libros nunca comparten estado, así
+// que no hay contención entre símbolos.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+use std::time::Duration;
+
+use rust_decimal::Decimal;
+use tokio::sync::{broadcast, mpsc, oneshot};
+use tokio::task::JoinHandle;
+use tokio::time::{Instant, Interval, MissedTickBehavior};
+use tokio_stream::wrappers::BroadcastStream;
+use tokio_stream::wrappers::errors::BroadcastStreamRecvError;
+use tokio_stream::{Stream, StreamExt};
+
+use crate::clock::Clock;
+use crate::error::OrderError;
+use crate::metrics::{EngineCounters, EngineMetrics};
+use crate::orderbook::{Order, OrderAck, OrderBook, Trade, TradeIdCounter};
+use crate::snapshot::{FNV_OFFSET, fnv1a};
+use crate::tape::{AggTrade, SymbolTrade, TradeAggregator};
+
+mod connection;
+mod health;
+mod snapshots;
+
+pub use connection::Connection;
+pub use snapshots::{SnapshotPolicy, latest_snapshot, list_snapshots};
+
+use health::ShardHealth;
+use snapshots::ShardSnapshots;
+
+/// Capacidad por defecto del inbox de cada símbolo (ver `EngineBuilder::inbox_capacity`).
+pub const DEFAULT_INBOX_CAPACITY: usize = 100;
+
+/// Capacidad de los canales del tape; un suscriptor más lento que esto pierde trades.
+pub const TAPE_CAPACITY: usize = 1024;
+
+type Inspect = Box<dyn FnOnce(&OrderBook) + Send>;
+
+/// Notificación de un fill parcial sobre una orden en reposo.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FillEvent {
+    pub order_id: u64,
+    pub client_order_id: Option<String>,
+    /// Cantidad ejecutada en este fill.
+    pub filled: Decimal,
+    /// Lo que le queda a la orden después del fill (0 = completada).
+    pub remaining: Decimal,
+    pub price: Decimal,
+}
+
+/// Elemento de `Engine::trade_stream`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StreamedTrade {
+    Trade(SymbolTrade),
+    /// El consumidor quedó atrás y se perdió esta cantidad de trades; el
+    /// stream sigue con el más viejo que todavía está en el canal.
+    Lagged(u64),
+}
+
+/// Mensajes que recibe la tarea de un símbolo.
+enum Command {
+    Submit {
+        order: Box<Order>, // En caja: una `Order` ocupa mucho más que el resto de los comandos
+        // Canal unbounded a propósito: la tarea del libro nunca espera a un cliente lento
+        fills: Option<mpsc::UnboundedSender<FillEvent>>,
+        reply: oneshot::Sender<Result<OrderAck, OrderError>>,
+    },
+    Cancel { id: u64, reply: oneshot::Sender<Option<Order>> },
+    // Cancel-on-disconnect: no pasa por el control de `min_resting_time`
+    DisconnectCancel { id: u64, reply: oneshot::Sender<Option<Order>> },
+    CancelBatch { ids: Vec<u64>, reply: oneshot::Sender<Vec<Option<Order>>> },
+    CancelByClientId { client_order_id: String, reply: oneshot::Sender<Option<Order>> },
+    Inspect(Inspect),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EngineError {
+    /// No hay ningún libro para ese símbolo.
+    UnknownSymbol(String),
+    /// La tarea del símbolo ya terminó (el engine se está apagando).
+    Stopped(String),
+    /// El libro rechazó la orden.
+    Rejected(OrderError),
+}
+
+impl From<OrderError> for EngineError {
+    fn from(e: OrderError) -> Self {
+        EngineError::Rejected(e)
+    }
+}
+
+impl fmt::Display for EngineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EngineError::UnknownSymbol(symbol) => write!(f, "símbolo desconocido: {symbol}"),
+            EngineError::Stopped(symbol) => write!(f, "el libro de {symbol} ya no acepta comandos"),
+            EngineError::Rejected(e) => write!(f, "orden rechazada: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for EngineError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            EngineError::Rejected(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+struct Shard {
+    inbox: mpsc::Sender<Command>,
+    handle: JoinHandle<()>,
+    resting: Arc<AtomicUsize>, // Órdenes en reposo, según la última actualización de la tarea
+}
+
+pub struct Engine {
+    shards: BTreeMap<String, Shard>, // Ordenado: `symbols` y `shutdown` recorren siempre igual
+    trades: broadcast::Sender<SymbolTrade>,
+    agg_trades: broadcast::Sender<AggTrade>,
+    counters: Arc<EngineCounters>,
+}
+
+/// Alcance de la numeración de trades.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TradeIdScope {
+    /// Un solo contador para todo el engine: los ids crecen entre símbolos.
+    #[default]
+    Global,
+    /// Cada símbolo numera sus trades desde 1.
+    PerSymbol,
+}
+
+#[derive(Default)]
+pub struct EngineBuilder {
+    symbols: Vec<(String, Option<OrderBook>)>, // `None` = libro vacío
+    trade_id_scope: TradeIdScope,
+    clock: Option<Arc<dyn Clock>>,
+    snapshots: Option<SnapshotPolicy>,
+    inbox_capacity: Option<usize>, // `None` = `DEFAULT_INBOX_CAPACITY`
+    health_interval: Option<Duration>,
+}
+
+impl EngineBuilder {
+    pub fn symbol(mut self, symbol: impl Into<String>) -> Self {
+        self.symbols.push((symbol.into(), None));
+        self
+    }
+
+    /// Registra `symbol` con un libro ya armado (p. ej. recuperado de disco) en
+    /// vez de uno vacío. Con `TradeIdScope::Global`, el contador compartido
+    /// sigue después del último id de trade de los libros registrados así.
+    pub fn book(mut self, symbol: impl Into<String>, book: OrderBook) -> Self {
+        self.symbols.push((symbol.into(), Some(book)));
+        self
+    }
+
+    pub fn trade_id_scope(mut self, scope: TradeIdScope) -> Self {
+        self.trade_id_scope = scope;
+        self
+    }
+
+    /// Reloj compartido por todos los libros (ver `OrderBook::set_clock`).
+    pub fn clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Some(Arc::new(clock));
+        self
+    }
+
+    /// Cuántos comandos puede encolar cada símbolo antes de que los clientes
+    /// tengan que esperar. Más chico acota memoria y latencia en cola; más
+    /// grande absorbe mejor las ráfagas. Mínimo 1.
+    pub fn inbox_capacity(mut self, capacity: usize) -> Self {
+        self.inbox_capacity = Some(capacity.max(1));
+        self
+    }
+
+    /// Snapshots periódicos de cada libro a disco (ver `SnapshotPolicy`).
+    pub fn snapshots(mut self, policy: SnapshotPolicy) -> Self {
+        self.snapshots = Some(policy);
+        self
+    }
+
+    /// Resumen de salud de cada libro cada `interval`, como evento de `tracing`
+    /// con target `hft_orderbook::health`.
+    pub fn health_reports(mut self, interval: Duration) -> Self {
+        self.health_interval = Some(interval);
+        self
+    }
+
+    /// Lanza una tarea por símbolo. Debe llamarse dentro de un runtime de tokio.
+    pub fn build(self) -> Engine {
+        let last_trade_id = self.symbols.iter()
+            .filter_map(|(_, book)| book.as_ref())
+            .map(|book| book.trade_id_counter().last())
+            .max()
+            .unwrap_or_default();
+        let shared_trade_ids = TradeIdCounter::starting_after(last_trade_id);
+        let (trades, _) = broadcast::channel(TAPE_CAPACITY);
+        let (agg_trades, _) = broadcast::channel(TAPE_CAPACITY);
+        let counters = Arc::new(EngineCounters::default());
+        let inbox_capacity = self.inbox_capacity.unwrap_or(DEFAULT_INBOX_CAPACITY);
+        let shards = self.symbols.into_iter()
+            .map(|(symbol, book)| {
+                let mut book = book.unwrap_or_default();
+                if self.trade_id_scope == TradeIdScope::Global {
+                    book.set_trade_id_counter(shared_trade_ids.clone());
+                }
+                if let Some(clock) = &self.clock {
+                    book.set_clock(Arc::clone(clock));
+                }
+                let tape = ShardTape {
+                    symbol: symbol.clone(),
+                    trades: trades.clone(),
+                    agg_trades: agg_trades.clone(),
+                    aggregator: TradeAggregator::new(symbol.clone()),
+                };
+                let stats = ShardStats { counters: Arc::clone(&counters), resting: Arc::new(AtomicUsize::new(book.len())) };
+                let resting = Arc::clone(&stats.resting);
+                let snapshots = self.snapshots.clone()
+                    .map(|policy| ShardSnapshots { policy, symbol: symbol.clone(), last_sequence: None });
+                let health = self.health_interval.map(|interval| (interval, ShardHealth::new(symbol.clone())));
+                let (inbox, rx) = mpsc::channel(inbox_capacity);
+                let handle = tokio::spawn(run_shard(book, rx, tape, stats, snapshots, health));
+                (symbol, Shard { inbox, handle, resting })
+            })
+            .collect();
+        Engine { shards, trades, agg_trades, counters }
+    }
+}
+
+// Dueño de una orden en reposo que pidió notificaciones de fills.
+struct FillListener {
+    client_order_id: Option<String>,
+    sender: mpsc::UnboundedSender<FillEvent>,
+}
+
+// Salida del tape de un símbolo hacia los canales del engine.
+struct ShardTape {
+    symbol: String,
+    trades: broadcast::Sender<SymbolTrade>,
+    agg_trades: broadcast::Sender<AggTrade>,
+    aggregator: TradeAggregator,
+}
+
+impl ShardTape {
+    fn publish(&mut self, trades: &[Trade]) {
+        // Sin suscriptores `send` falla; no es un error
+        for trade in trades {
+            let _ = self.trades.send(SymbolTrade { symbol: self.symbol.clone(), trade: trade.clone() });
+        }
+        for agg in self.aggregator.aggregate(trades) {
+            let _ = self.agg_trades.send(agg);
+        }
+    }
+}
+
+// Métricas que actualiza la tarea de un símbolo.
+struct ShardStats {
+    counters: Arc<EngineCounters>,
+    resting: Arc<AtomicUsize>,
+}
+
+// Próximo tick de un timer (snapshots, salud); sin timer no llega nunca.
+async fn next_tick(ticker: &mut Option<Interval>) {
+    match ticker {
+        Some(ticker) => { ticker.tick().await; },
+        None => std::future::pending().await,
+    }
+}
+
+// Timer que dispara cada `period`, empezando dentro de un período.
+fn ticker(period: Duration) -> Interval {
+    let mut ticker = tokio::time::interval_at(Instant::now() + period, period);
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    ticker
+}
+
+// Loop de la tarea de un símbolo: consume su inbox hasta que se cierra y, si
+// hay política de snapshots, guarda el libro en cada tick y al terminar. Con
+// reporte de salud, además emite el resumen en cada tick de su timer.
+async fn run_shard(
+    mut book: OrderBook,
+    mut inbox: mpsc::Receiver<Command>,
+    mut tape: ShardTape,
+    stats: ShardStats,
+    mut snapshots: Option<ShardSnapshots>,
+    health: Option<(Duration, ShardHealth)>,
+) {
+    let mut listeners: HashMap<u64, FillListener> = HashMap::new();
+    let mut snapshot_ticker = snapshots.as_ref().map(|s| ticker(s.policy.interval()));
+    let (mut health_ticker, mut health) = match health {
+        Some((interval, health)) => (Some(ticker(interval)), Some(health)),
+        None => (None, None),
+    };
+
+    loop {
+        let command = tokio::select! {
+            command = inbox.recv() => match command {
+                Some(command) => command,
+                None => break,
+            },
+            _ = next_tick(&mut snapshot_ticker) => {
+                if let Some(snapshots) = snapshots.as_mut() {
+                    snapshots.take(&book);
+                }
+                continue;
+            }
+            _ = next_tick(&mut health_ticker) => {
+                if let Some(health) = health.as_mut() {
+                    health.report(&book);
+                }
+                continue;
+            }
+        };
+        match command {
+            Command::Submit { order, fills, reply } => {
+                if let Some(health) = health.as_mut() {
+                    health.orders += 1;
+                }
+                let (id, client_order_id) = (order.id, order.client_order_id.clone());
+                // Los fills de last look cuya ventana venció se publican antes:
+                // no pueden depender de que la orden entrante sea aceptada.
+                let accepted = book.expire_last_look();
+                if !accepted.is_empty() {
+                    stats.counters.record_trades(accepted.len());
+                    notify_fills(&book, &mut listeners, &accepted);
+                    tape.publish(&accepted);
+                }
+                let result = book.submit(*order);
+                match &result {
+                    Ok(ack) => {
+                        stats.counters.record_accepted(ack.trades.len());
+                        notify_fills(&book, &mut listeners, &ack.trades);
+                        tape.publish(&ack.trades);
+                    }
+                    Err(e) => stats.counters.record_rejected(e),
+                }
+
+                // Sólo tiene sentido escuchar si quedó algo en el libro
+                if let Some(sender) = fills
+                    && book.order(id).is_some()
+                {
+                    listeners.insert(id, FillListener { client_order_id, sender });
+                }
+                let _ = reply.send(result);
+            },
+            Command::Cancel { id, reply } => {
+                listeners.remove(&id); // Al soltar el sender el cliente ve el canal cerrado
+                let _ = reply.send(book.cancel_order(id));
+            },
+            Command::DisconnectCancel { id, reply } => {
+                listeners.remove(&id);
+                let _ = reply.send(book.cancel_on_disconnect(id));
+            },
+            Command::CancelBatch { ids, reply } => {
+                for id in &ids {
+                    listeners.remove(id);
+                }
+                let _ = reply.send(book.cancel_batch(&ids));
+            },
+            Command::CancelByClientId { client_order_id, reply } => {
+                let cancelled = book.cancel_by_client_id(&client_order_id);
+                if let Some(order) = &cancelled {
+                    listeners.remove(&order.id);
+                }
+                let _ = reply.send(cancelled);
+            },
+            Command::Inspect(inspect) => inspect(&book),
+        }
+        stats.resting.store(book.len(), AtomicOrdering::Relaxed);
+    }
+
+    if let Some(snapshots) = snapshots.as_mut() {
+        snapshots.take(&book);
+    }
+}
+
+// Avisa a los makers registrados de cada fill que sufrieron.
+fn notify_fills(book: &OrderBook, listeners: &mut HashMap<u64, FillListener>, trades: &[Trade]) {
+    for trade in trades {
+        let Some(listener) = listeners.get(&trade.maker_id) else { continue };
+        let remaining = book.order(trade.maker_id).map(|o| o.amount).unwrap_or_default();
+        let event = FillEvent {
+            order_id: trade.maker_id,
+            client_order_id: listener.client_order_id.clone(),
+            filled: trade.amount,
+            remaining,
+            price: trade.price,
+        };
+
+        // Orden completada o cliente que ya no escucha: se deja de notificar
+        if listener.sender.send(event).is_err() || remaining.is_zero() {
+            listeners.remove(&trade.maker_id);
+        }
+    }
+}
+
+impl Engine {
+    pub fn builder() -> EngineBuilder {
+        EngineBuilder::default()
+    }
+
+    pub fn symbols(&self) -> impl Iterator<Item = &str> {
+        self.shards.keys().map(String::as_str)
+    }
+
+    /// Foto de los contadores del engine. Las órdenes en reposo de cada símbolo
+    /// son las del último comando que procesó su tarea.
+    pub fn metrics(&self) -> EngineMetrics {
+        self.counters.snapshot(self.shards.iter().map(|(symbol, shard)| {
+            let queued = shard.inbox.max_capacity() - shard.inbox.capacity();
+            (symbol.as_str(), &*shard.resting, queued)
+        }))
+    }
+
+    /// Suscripción a todos los trades del engine, de todos los símbolos.
+    pub fn subscribe_trades(&self) -> broadcast::Receiver<SymbolTrade> {
+        self.trades.subscribe()
+    }
+
+    /// Los trades del engine como `Stream`, para usar combinadores (`filter`,
+    /// `throttle`...). Un consumidor lento no corta el stream: recibe un
+    /// `StreamedTrade::Lagged` con lo que se perdió y sigue.
+    pub fn trade_stream(&self) -> impl Stream<Item = StreamedTrade> + Send + use<> {
+        BroadcastStream::new(self.trades.subscribe()).map(|received| match received {
+            Ok(trade) => StreamedTrade::Trade(trade),
+            Err(BroadcastStreamRecvError::Lagged(missed)) => StreamedTrade::Lagged(missed),
+        })
+    }
+
+    /// Suscripción a los trades agregados (ver `AggTrade`).
+    pub fn subscribe_agg_trades(&self) -> broadcast::Receiver<AggTrade> {
+        self.agg_trades.subscribe()
+    }
+
+    // Router: encuentra el inbox del símbolo y le entrega el comando.
+    async fn route(&self, symbol: &str, command: Command) -> Result<(), EngineError> {
+        let shard = self.shards.get(symbol).ok_or_else(|| EngineError::UnknownSymbol(symbol.to_string()))?;
+        shard.inbox.send(command).await.map_err(|_| EngineError::Stopped(symbol.to_string()))
+    }
+
+    /// Envía una orden al libro de `symbol` y espera su ack (trades y destino del resto).
+    pub async fn submit(&self, symbol: &str, order: Order) -> Result<OrderAck, EngineError> {
+        let (reply, response) = oneshot::channel();
+        self.route(symbol, Command::Submit { order: Box::new(order), fills: None, reply }).await?;
+        let ack = response.await.map_err(|_| EngineError::Stopped(symbol.to_string()))??;
+        Ok(ack)
+    }
+
+    /// Como `submit`, pero además devuelve un canal que recibe un `FillEvent` cada vez
+    /// que la parte en reposo de la orden es ejecutada. Los fills inmediatos vienen en
+    /// los trades del ack; el canal se cierra al completarse o cancelarse la orden.
+    pub async fn submit_with_fills(
+        &self,
+        symbol: &str,
+        order: Order,
+    ) -> Result<(OrderAck, mpsc::UnboundedReceiver<FillEvent>), EngineError> {
+        let (fills, fill_events) = mpsc::unbounded_channel();
+        let (reply, response) = oneshot::channel();
+        self.route(symbol, Command::Submit { order: Box::new(order), fills: Some(fills), reply }).await?;
+        let ack = response.await.map_err(|_| EngineError::Stopped(symbol.to_string()))??;
+        Ok((ack, fill_events))
+    }
+
+    pub async fn cancel(&self, symbol: &str, id: u64) -> Result<Option<Order>, EngineError> {
+        let (reply, response) = oneshot::channel();
+        self.route(symbol, Command::Cancel { id, reply }).await?;
+        response.await.map_err(|_| EngineError::Stopped(symbol.to_string()))
+    }
+
+    /// Cancela varias órdenes de `symbol` en un solo comando (ver `OrderBook::cancel_batch`).
+    pub async fn cancel_batch(&self, symbol: &str, ids: Vec<u64>) -> Result<Vec<Option<Order>>, EngineError> {
+        let (reply, response) = oneshot::channel();
+        self.route(symbol, Command::CancelBatch { ids, reply }).await?;
+        response.await.map_err(|_| EngineError::Stopped(symbol.to_string()))
+    }
+
+    pub async fn cancel_by_client_id(&self, symbol: &str, client_order_id: impl Into<String>) -> Result<Option<Order>, EngineError> {
+        let (reply, response) = oneshot::channel();
+        self.route(symbol, Command::CancelByClientId { client_order_id: client_order_id.into(), reply }).await?;
+        response.await.map_err(|_| EngineError::Stopped(symbol.to_string()))
+    }
+
+    /// Ejecuta `f` sobre el libro dentro de su propia tarea, en orden con el resto
+    /// de los comandos del símbolo, y devuelve el resultado.
+    pub async fn with_book<R, F>(&self, symbol: &str, f: F) -> Result<R, EngineError>
+    where
+        R: Send + 'static,
+        F: FnOnce(&OrderBook) -> R + Send + 'static,
+    {
+        let (reply, response) = oneshot::channel();
+        let inspect: Inspect = Box::new(move |book| { let _ = reply.send(f(book)); });
+        self.route(symbol, Command::Inspect(inspect)).await?;
+        response.await.map_err(|_| EngineError::Stopped(symbol.to_string()))
+    }
+
+    /// Hash estable del estado de todo el engine: combina, por símbolo en orden
+    /// alfabético, el `OrderBook::state_hash` de cada libro. Sirve para comparar
+    /// dos replays con un solo número.
+    pub async fn state_hash(&self) -> Result<u64, EngineError> {
+        let mut hash = FNV_OFFSET;
+        for symbol in self.shards.keys() {
+            let book_hash = self.with_book(symbol, OrderBook::state_hash).await?;
+            hash = fnv1a(hash, symbol.as_bytes());
+            hash = fnv1a(hash, &book_hash.to_le_bytes());
+        }
+        Ok(hash)
+    }
+
+    /// Cierra todos los inboxes y espera a que cada tarea procese lo pendiente
+    /// (y guarde su último snapshot, si hay política de snapshots).
+    pub async fn shutdown(self) {
+        for (symbol, shard) in self.shards {
+            drop(shard.inbox);
+            if let Err(e) = shard.handle.await {
+                eprintln!("⚠️  La tarea de {symbol} terminó con error: {e}");
+            }
+        }
+    }
+}
@@ -0,0 +1,120 @@
+// --- CANCEL-ON-DISCONNECT ---
+//
+// Un gateway abre una `Connection` por cliente y manda las órdenes a través de
+// ella. La conexión recuerda qué órdenes quedaron en reposo y, al cerrarse
+// (explícitamente o porque se soltó el handle), el engine las cancela todas,
+// aunque no hayan cumplido `min_resting_time`.
+// Las órdenes de otras conexiones, o enviadas directo al engine, no se tocan.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Mutex;
+
+use tokio::sync::{mpsc, oneshot};
+
+use super::{Command, Engine, EngineError};
+use crate::logging::book_log;
+use crate::orderbook::{Order, OrderAck};
+
+pub struct Connection {
+    // Referencias débiles: una conexión abierta no impide el `shutdown` del engine
+    inboxes: BTreeMap<String, mpsc::WeakSender<Command>>,
+    orders: Mutex<BTreeSet<(String, u64)>>, // (símbolo, id) que quedaron en reposo
+    closed: bool,
+}
+
+impl Engine {
+    /// Abre una conexión cuyas órdenes en reposo se cancelan al cerrarla.
+    pub fn connect(&self) -> Connection {
+        Connection {
+            inboxes: self.shards.iter().map(|(symbol, shard)| (symbol.clone(), shard.inbox.downgrade())).collect(),
+            orders: Mutex::new(BTreeSet::new()),
+            closed: false,
+        }
+    }
+}
+
+impl Connection {
+    /// Como `Engine::submit`; si queda algo en reposo, la orden pasa a ser de esta conexión.
+    pub async fn submit(&self, symbol: &str, order: Order) -> Result<OrderAck, EngineError> {
+        let id = order.id;
+        let (reply, response) = oneshot::channel();
+        self.send(symbol, Command::Submit { order: Box::new(order), fills: None, reply }).await?;
+        let ack = response.await.map_err(|_| EngineError::Stopped(symbol.to_string()))??;
+        if !ack.resting_qty.is_zero() {
+            self.lock().insert((symbol.to_string(), id));
+        }
+        Ok(ack)
+    }
+
+    /// Cancela una orden de la conexión. Si el libro no la cancela (parado, o
+    /// antes de `min_resting_time`), la conexión la sigue cubriendo.
+    pub async fn cancel(&self, symbol: &str, id: u64) -> Result<Option<Order>, EngineError> {
+        let (reply, response) = oneshot::channel();
+        self.send(symbol, Command::Cancel { id, reply }).await?;
+        let cancelled = response.await.map_err(|_| EngineError::Stopped(symbol.to_string()))?;
+        if cancelled.is_some() {
+            self.lock().remove(&(symbol.to_string(), id));
+        }
+        Ok(cancelled)
+    }
+
+    /// Cantidad de órdenes a cancelar si la conexión se cierra ahora (puede
+    /// incluir algunas que ya se ejecutaron del todo).
+    pub fn open_order_count(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Cierra la conexión y espera a que se cancelen sus órdenes. Devuelve las
+    /// que seguían en el libro.
+    pub async fn disconnect(mut self) -> Vec<Order> {
+        self.closed = true;
+        let orders = std::mem::take(&mut *self.lock());
+        book_log!("🔌 Conexión cerrada: cancelando {} órdenes", orders.len());
+        let mut cancelled = Vec::new();
+        for (symbol, id) in orders {
+            let (reply, response) = oneshot::channel();
+            if self.send(&symbol, Command::DisconnectCancel { id, reply }).await.is_ok()
+                && let Ok(Some(order)) = response.await
+            {
+                cancelled.push(order);
+            }
+        }
+        cancelled
+    }
+
+    async fn send(&self, symbol: &str, command: Command) -> Result<(), EngineError> {
+        let inbox = self.inboxes.get(symbol)
+            .ok_or_else(|| EngineError::UnknownSymbol(symbol.to_string()))?
+            .upgrade()
+            .ok_or_else(|| EngineError::Stopped(symbol.to_string()))?;
+        inbox.send(command).await.map_err(|_| EngineError::Stopped(symbol.to_string()))
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, BTreeSet<(String, u64)>> {
+        self.orders.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+// Conexión soltada sin `disconnect` (cliente caído, tarea abortada...): se
+// cancelan sus órdenes en segundo plano, sin esperar respuesta.
+impl Drop for Connection {
+    fn drop(&mut self) {
+        let orders = std::mem::take(&mut *self.lock());
+        if self.closed || orders.is_empty() {
+            return;
+        }
+        book_log!("🔌 Conexión perdida: cancelando {} órdenes", orders.len());
+        for (symbol, id) in orders {
+            let Some(inbox) = self.inboxes.get(&symbol).and_then(mpsc::WeakSender::upgrade) else { continue };
+            let (reply, _) = oneshot::channel();
+            // Con el inbox lleno no se puede esperar dentro de `drop`: se encola en una tarea
+            if let Err(mpsc::error::TrySendError::Full(command)) = inbox.try_send(Command::DisconnectCancel { id, reply })
+                && let Ok(runtime) = tokio::runtime::Handle::try_current()
+            {
+                runtime.spawn(async move {
+                    let _ = inbox.send(command).await;
+                });
+            }
+        }
+    }
+}
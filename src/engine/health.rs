@@ -0,0 +1,64 @@
+// --- RESUMEN PERIÓDICO DE SALUD ---
+//
+// Con `EngineBuilder::health_reports`, la tarea de cada símbolo emite cada
+// cierto intervalo un evento de `tracing` (target `hft_orderbook::health`) con
+// el estado del libro: mejor bid/ask, spread en bps, profundidad a menos de 1%
+// del mid, órdenes en reposo y órdenes por segundo desde el resumen anterior.
+// Es el latido del motor: si deja de llegar, la tarea del símbolo está trabada.
+
+use rust_decimal::prelude::*;
+use tokio::time::Instant;
+
+use crate::orderbook::{OrderBook, Side};
+
+// Estado del reporte de salud de un símbolo dentro de su tarea.
+pub(super) struct ShardHealth {
+    pub(super) symbol: String,
+    pub(super) orders: u64, // Órdenes recibidas desde el último resumen
+    pub(super) since: Instant,
+}
+
+impl ShardHealth {
+    pub(super) fn new(symbol: String) -> Self {
+        Self { symbol, orders: 0, since: Instant::now() }
+    }
+
+    // Emite el resumen y reinicia el conteo de órdenes.
+    pub(super) fn report(&mut self, book: &OrderBook) {
+        let elapsed = self.since.elapsed().as_secs_f64();
+        let orders_per_sec = if elapsed > 0.0 { self.orders as f64 / elapsed } else { 0.0 };
+        let (best_bid, best_ask) = (book.best_bid(), book.best_ask());
+        let spread_bps = match (best_bid, best_ask, book.mid_price()) {
+            (Some(bid), Some(ask), Some(mid)) if !mid.is_zero() => Some(((ask - bid) / mid * Decimal::from(10_000)).round_dp(2)),
+            _ => None,
+        };
+        let (bid_depth, ask_depth) = match book.mid_price() {
+            Some(mid) => (depth_within(book, Side::Buy, mid), depth_within(book, Side::Sell, mid)),
+            None => (Decimal::zero(), Decimal::zero()),
+        };
+
+        tracing::info!(
+            target: "hft_orderbook::health",
+            symbol = %self.symbol,
+            best_bid = ?best_bid,
+            best_ask = ?best_ask,
+            spread_bps = ?spread_bps,
+            bid_depth_1pct = %bid_depth,
+            ask_depth_1pct = %ask_depth,
+            resting_orders = book.len(),
+            orders_per_sec,
+            "💓 Salud del libro"
+        );
+        self.orders = 0;
+        self.since = Instant::now();
+    }
+}
+
+// Cantidad visible de `side` a menos de 1% de `mid`.
+fn depth_within(book: &OrderBook, side: Side, mid: Decimal) -> Decimal {
+    let band = mid.abs() / Decimal::ONE_HUNDRED;
+    book.levels(side)
+        .take_while(|level| (level.price - mid).abs() <= band)
+        .map(|level| level.amount)
+        .sum()
+}
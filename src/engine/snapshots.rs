@@ -0,0 +1,131 @@
+// --- SNAPSHOTS PERIÓDICOS ---
+//
+// Con una `SnapshotPolicy`, la tarea de cada símbolo guarda su libro en disco
+// cada cierto intervalo y una última vez al apagarse. Los archivos se llaman
+// `<símbolo>-<secuencia>.snap` y se escriben de forma atómica; de cada símbolo
+// se conservan sólo los últimos K. Para recuperar alcanza con cargar el más
+// reciente y reaplicar lo que grabó el journal después de esa secuencia.
+//
+// El snapshot se escribe dentro de la tarea del símbolo: mientras dura, ese
+// símbolo no procesa órdenes (los demás siguen normalmente).
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use super::Engine;
+use crate::config::BookConfig;
+use crate::logging::book_log;
+use crate::orderbook::OrderBook;
+use crate::persist::{Compression, recover_book, save_snapshot_atomic};
+
+/// Cada cuánto y dónde guardar los snapshots de los libros del engine.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnapshotPolicy {
+    dir: PathBuf,
+    interval: Duration,
+    keep: usize,
+    compression: Compression,
+}
+
+impl SnapshotPolicy {
+    /// Un snapshot por símbolo cada `interval` en `dir`, conservando los últimos 3.
+    pub fn new(dir: impl Into<PathBuf>, interval: Duration) -> Self {
+        Self { dir: dir.into(), interval, keep: 3, compression: Compression::None }
+    }
+
+    /// Cuántos snapshots conservar por símbolo (al menos 1).
+    pub fn keep(mut self, keep: usize) -> Self {
+        self.keep = keep.max(1);
+        self
+    }
+
+    pub fn compression(mut self, compression: Compression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+}
+
+fn snapshot_path(dir: &Path, symbol: &str, sequence: u64) -> PathBuf {
+    dir.join(format!("{symbol}-{sequence:020}.snap"))
+}
+
+/// Snapshots de `symbol` en `dir` como (secuencia, ruta), del más viejo al más nuevo.
+pub fn list_snapshots(dir: impl AsRef<Path>, symbol: &str) -> io::Result<Vec<(u64, PathBuf)>> {
+    let prefix = format!("{symbol}-");
+    let mut snapshots = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let Some(name) = path.file_name().and_then(|name| name.to_str()) else { continue };
+        // Los de otro símbolo con el mismo prefijo ("BTC" vs "BTC-USD") no parsean
+        if let Some(sequence) = name.strip_prefix(&prefix)
+            .and_then(|rest| rest.strip_suffix(".snap"))
+            .and_then(|sequence| sequence.parse().ok())
+        {
+            snapshots.push((sequence, path));
+        }
+    }
+    snapshots.sort();
+    Ok(snapshots)
+}
+
+/// El snapshot más reciente de `symbol` en `dir`, si hay alguno.
+pub fn latest_snapshot(dir: impl AsRef<Path>, symbol: &str) -> io::Result<Option<PathBuf>> {
+    Ok(list_snapshots(dir, symbol)?.pop().map(|(_, path)| path))
+}
+
+impl Engine {
+    /// Levanta un engine con un solo símbolo a partir de un snapshot y del
+    /// journal grabado desde entonces (ver `recover_book`). `config` tiene que
+    /// ser la del libro que escribió el journal: el replay vuelve a cruzar las
+    /// órdenes con esas reglas. Debe llamarse dentro de un runtime de tokio.
+    pub fn recover(symbol: impl Into<String>, snapshot: impl AsRef<Path>, journal: impl AsRef<Path>, config: BookConfig) -> io::Result<Engine> {
+        let book = recover_book(snapshot, journal, config)?;
+        Ok(Engine::builder().book(symbol, book).build())
+    }
+}
+
+// Estado de los snapshots de un símbolo dentro de su tarea.
+pub(super) struct ShardSnapshots {
+    pub(super) policy: SnapshotPolicy,
+    pub(super) symbol: String,
+    pub(super) last_sequence: Option<u64>,
+}
+
+impl ShardSnapshots {
+    // Guarda el libro si cambió desde el último snapshot y borra los que sobran.
+    pub(super) fn take(&mut self, book: &OrderBook) {
+        if self.last_sequence == Some(book.sequence()) {
+            return;
+        }
+        match self.write(book) {
+            Ok(()) => {
+                book_log!("📸 Snapshot de {} en la secuencia {}", self.symbol, book.sequence());
+                self.last_sequence = Some(book.sequence());
+            }
+            Err(e) => eprintln!("⚠️  No se pudo guardar el snapshot de {}: {e}", self.symbol),
+        }
+    }
+
+    fn write(&self, book: &OrderBook) -> io::Result<()> {
+        fs::create_dir_all(&self.policy.dir)?;
+        let path = snapshot_path(&self.policy.dir, &self.symbol, book.sequence());
+        save_snapshot_atomic(&path, book, self.policy.compression)?;
+
+        let snapshots = list_snapshots(&self.policy.dir, &self.symbol)?;
+        let excess = snapshots.len().saturating_sub(self.policy.keep);
+        for (_, old) in &snapshots[..excess] {
+            fs::remove_file(old)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,141 @@
+use std::fmt;
+
+use rust_decimal::Decimal;
+
+use crate::balances::Asset;
+use crate::orderbook::{Side, TradingState};
+
+// --- ERRORES DE ÓRDENES ---
+
+/// Motivo por el que el libro rechaza una orden.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OrderError {
+    /// La cantidad debe ser positiva.
+    InvalidAmount(Decimal),
+    /// La cantidad no es múltiplo del lote. `lower` y `upper` son los tamaños
+    /// válidos más cercanos por debajo y por encima (`lower` es `None` si la
+    /// cantidad no llega a un lote).
+    InvalidLot { amount: Decimal, lot: Decimal, lower: Option<Decimal>, upper: Decimal },
+    /// El precio límite debe ser positivo (salvo con `allow_negative_prices`).
+    InvalidPrice(Decimal),
+    /// El precio no es múltiplo del tick del instrumento.
+    InvalidTick { price: Decimal, tick: Decimal },
+    /// El nocional de la orden no llega al mínimo del instrumento.
+    BelowMinNotional { notional: Decimal, min: Decimal },
+    /// El precio cae fuera de la banda permitida alrededor del precio de referencia.
+    PriceOutOfBand { price: Decimal, reference: Decimal, band: Decimal },
+    /// El pico visible de un iceberg debe ser positivo.
+    InvalidPeak(Decimal),
+    /// Una orden post-only llegó a un precio que ejecutaría contra `best`.
+    PostOnlyWouldCross { price: Decimal, best: Decimal },
+    /// Una orden all-or-none llegó a un precio que ejecutaría contra `best`.
+    AllOrNoneWouldCross { price: Decimal, best: Decimal },
+    /// La orden `Gtt` ya estaba vencida al llegar.
+    AlreadyExpired { expire_at: u64, now: u64 },
+    /// El porcentaje de posición debe estar en (0, 100].
+    InvalidPercent(Decimal),
+    /// La fracción de `cancel_at_fill_ratio` debe estar en (0, 1].
+    InvalidFillRatio(Decimal),
+    /// La cuenta no tiene posición que cerrar.
+    FlatPosition { account: u64 },
+    /// Una orden de mercado llegó con el lado `side` vacío (ver `MarketNoLiquidity`).
+    NoLiquidity { side: Side },
+    /// La orden abriría un nivel más allá del tope de profundidad del lado.
+    DepthExceeded { side: Side, max_levels: usize },
+    /// Con `enforce_balances`, a la cuenta no le alcanza el saldo disponible
+    /// de `asset` para reservar lo que pide la orden.
+    InsufficientBalance { account: u64, asset: Asset, required: Decimal, available: Decimal },
+    /// El libro está parado (`Halted` o `CancelOnly`) y no acepta órdenes nuevas.
+    /// No es un rechazo de la orden: se puede reintentar cuando reabra.
+    MarketHalted(TradingState),
+    /// Con `MinRestingPolicy::Reject`, la orden `id` descansó `rested` ms y no
+    /// se puede retirar antes de `min` (p. ej. una punta de un `requote`).
+    CancelTooSoon { id: u64, rested: u64, min: u64 },
+    /// El libro no soporta esta combinación en su configuración actual.
+    NotSupported(&'static str),
+    /// Una cuenta (nocional, comisión...) no entra en un `Decimal`.
+    Overflow(&'static str),
+    /// Se rompió un invariante del libro (p. ej. un fill mayor que la orden en reposo).
+    /// No debería pasar nunca; el matching se corta antes de dejar cantidades negativas.
+    Internal(String),
+}
+
+impl OrderError {
+    /// Nombre de la variante, para contar rechazos por motivo.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            OrderError::InvalidAmount(_) => "InvalidAmount",
+            OrderError::InvalidLot { .. } => "InvalidLot",
+            OrderError::InvalidPrice(_) => "InvalidPrice",
+            OrderError::InvalidTick { .. } => "InvalidTick",
+            OrderError::BelowMinNotional { .. } => "BelowMinNotional",
+            OrderError::PriceOutOfBand { .. } => "PriceOutOfBand",
+            OrderError::InvalidPeak(_) => "InvalidPeak",
+            OrderError::PostOnlyWouldCross { .. } => "PostOnlyWouldCross",
+            OrderError::AllOrNoneWouldCross { .. } => "AllOrNoneWouldCross",
+            OrderError::AlreadyExpired { .. } => "AlreadyExpired",
+            OrderError::InvalidPercent(_) => "InvalidPercent",
+            OrderError::InvalidFillRatio(_) => "InvalidFillRatio",
+            OrderError::FlatPosition { .. } => "FlatPosition",
+            OrderError::NoLiquidity { .. } => "NoLiquidity",
+            OrderError::DepthExceeded { .. } => "DepthExceeded",
+            OrderError::InsufficientBalance { .. } => "InsufficientBalance",
+            OrderError::MarketHalted(_) => "MarketHalted",
+            OrderError::CancelTooSoon { .. } => "CancelTooSoon",
+            OrderError::NotSupported(_) => "NotSupported",
+            OrderError::Overflow(_) => "Overflow",
+            OrderError::Internal(_) => "Internal",
+        }
+    }
+}
+
+impl fmt::Display for OrderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OrderError::InvalidAmount(amount) => write!(f, "cantidad inválida: {amount}"),
+            OrderError::InvalidLot { amount, lot, lower: Some(lower), upper } => {
+                write!(f, "la cantidad {amount} no es múltiplo del lote {lot}: probá con {lower} o {upper}")
+            }
+            OrderError::InvalidLot { amount, lot, lower: None, upper } => {
+                write!(f, "la cantidad {amount} no llega al lote {lot}: probá con {upper}")
+            }
+            OrderError::InvalidPrice(price) => write!(f, "precio inválido: {price}"),
+            OrderError::InvalidTick { price, tick } => write!(f, "el precio {price} no es múltiplo del tick {tick}"),
+            OrderError::BelowMinNotional { notional, min } => {
+                write!(f, "nocional {notional} por debajo del mínimo {min}")
+            }
+            OrderError::PriceOutOfBand { price, reference, band } => {
+                write!(f, "precio {price} fuera de la banda de ±{band} alrededor de {reference}")
+            }
+            OrderError::InvalidPeak(peak) => write!(f, "pico de iceberg inválido: {peak}"),
+            OrderError::PostOnlyWouldCross { price, best } => {
+                write!(f, "post-only a {price} cruzaría contra el mejor precio contrario {best}")
+            }
+            OrderError::AllOrNoneWouldCross { price, best } => {
+                write!(f, "all-or-none a {price} cruzaría contra el mejor precio contrario {best}")
+            }
+            OrderError::AlreadyExpired { expire_at, now } => {
+                write!(f, "la orden vence en {expire_at} y el reloj del libro ya marca {now}")
+            }
+            OrderError::InvalidPercent(percent) => write!(f, "porcentaje de posición inválido: {percent}"),
+            OrderError::InvalidFillRatio(ratio) => write!(f, "fracción de ejecución inválida: {ratio} (tiene que estar en (0, 1])"),
+            OrderError::FlatPosition { account } => write!(f, "la cuenta {account} no tiene posición abierta"),
+            OrderError::NoLiquidity { side } => write!(f, "no hay liquidez del lado {side:?} para la orden de mercado"),
+            OrderError::DepthExceeded { side, max_levels } => {
+                write!(f, "el lado {side:?} ya tiene el máximo de {max_levels} niveles")
+            }
+            OrderError::InsufficientBalance { account, asset, required, available } => {
+                write!(f, "saldo insuficiente en la cuenta {account}: la orden reserva {required} {asset:?} y hay {available} disponible")
+            }
+            OrderError::MarketHalted(state) => write!(f, "el libro no acepta órdenes ({state:?})"),
+            OrderError::CancelTooSoon { id, rested, min } => {
+                write!(f, "la orden #{id} descansó {rested} ms y no se puede retirar antes de {min}")
+            }
+            OrderError::NotSupported(what) => write!(f, "no soportado: {what}"),
+            OrderError::Overflow(what) => write!(f, "desbordamiento al calcular {what}"),
+            OrderError::Internal(reason) => write!(f, "error interno del libro: {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for OrderError {}
@@ -0,0 +1,52 @@
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::balances::Asset;
+use crate::orderbook::{Order, Side, Trade, TradingState};
+
+// --- EVENTOS DEL LIBRO ---
+
+/// Todo lo que le pasa al libro. Altas, cancelaciones, modificaciones, cambios
+/// de estado, ticks de subasta, undos, depósitos y respuestas de last look son
+/// comandos (alcanzan para reconstruirlo); `Trade`, `TradePending` y
+/// `LevelDelta` son consecuencias, útiles para auditoría y para los feeds de
+/// market data.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BookEvent {
+    /// Orden entrante tal como llegó, antes del matching.
+    OrderAdded { order: Order },
+    /// Orden cancelada, con la cantidad que le quedaba. `by_book` marca las que
+    /// canceló el libro por su cuenta (vencimiento, cierre de sesión, fracción
+    /// ejecutada, STP, resto de una IOC...) y no un cliente.
+    OrderCancelled {
+        order: Order,
+        #[serde(default)] // Las grabaciones viejas no lo tienen
+        by_book: bool,
+    },
+    /// Reducción de cantidad en el lugar: la orden conserva su lugar en la cola.
+    /// Una modificación que pierde prioridad se publica como cancelación más alta.
+    OrderModified { id: u64, price: Decimal, amount: Decimal },
+    /// El libro pasó a otro estado de negociación (halt, resume...).
+    TradingStateChanged { state: TradingState },
+    /// Tick del modo batch: las órdenes encoladas se cruzan a un único precio.
+    AuctionUncross,
+    /// Se deshizo el último comando (ver `OrderBook::undo_last`).
+    Undone,
+    /// Depósito en el saldo disponible de una cuenta (ver `OrderBook::deposit`).
+    Deposit { account: u64, asset: Asset, amount: Decimal },
+    /// Respuesta del maker a un fill en last look (ver `OrderBook::accept_trade`).
+    /// Si lo acepta, le sigue el `Trade`; si no, el fill queda anulado.
+    LastLookDecision { trade_id: u64, accepted: bool },
+    Trade { trade: Trade },
+    /// Fill cruzado que espera el last look del maker: todavía no es definitivo.
+    TradePending { trade: Trade },
+    /// Nuevo total de un nivel de precio (0 = el nivel desapareció).
+    LevelDelta { side: Side, price: Decimal, amount: Decimal },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SequencedEvent {
+    pub seq: u64,
+    pub event: BookEvent,
+}
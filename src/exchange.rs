@@ -0,0 +1,444 @@
+// --- EXCHANGE (VARIOS LIBROS EN UN MISMO HILO) ---
+//
+// Contraparte síncrona del `Engine`: todos los libros viven en el mismo
+// struct, así que una operación sobre todos los símbolos (kill switch,
+// triggers entre mercados) ocurre de una sola vez, sin que ningún libro
+// procese órdenes a mitad de camino.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use rust_decimal::Decimal;
+
+use crate::config::BookConfig;
+use crate::error::OrderError;
+use crate::logging::book_log;
+use crate::orderbook::{Order, OrderAck, OrderBook, TradingState};
+use crate::snapshot::Touch;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExchangeError {
+    /// No hay ningún libro para ese símbolo.
+    UnknownSymbol(String),
+    /// `create_symbol` con un símbolo que ya tiene libro.
+    DuplicateSymbol(String),
+    /// El libro rechazó la orden.
+    Rejected(OrderError),
+}
+
+impl From<OrderError> for ExchangeError {
+    fn from(e: OrderError) -> Self {
+        ExchangeError::Rejected(e)
+    }
+}
+
+impl fmt::Display for ExchangeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExchangeError::UnknownSymbol(symbol) => write!(f, "símbolo desconocido: {symbol}"),
+            ExchangeError::DuplicateSymbol(symbol) => write!(f, "el símbolo {symbol} ya existe"),
+            ExchangeError::Rejected(e) => write!(f, "orden rechazada: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for ExchangeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ExchangeError::Rejected(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// Cuándo se dispara un trigger entre mercados, según el último precio del símbolo observado.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TriggerCondition {
+    /// El último precio llega a este nivel o lo supera.
+    AtOrAbove(Decimal),
+    /// El último precio llega a este nivel o baja de él.
+    AtOrBelow(Decimal),
+}
+
+impl TriggerCondition {
+    pub fn is_met(self, price: Decimal) -> bool {
+        match self {
+            TriggerCondition::AtOrAbove(level) => price >= level,
+            TriggerCondition::AtOrBelow(level) => price <= level,
+        }
+    }
+}
+
+/// Orden dormida para `symbol` que se envía cuando el último precio de `watch`
+/// cumple `condition` (un stop intermercado).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CrossTrigger {
+    pub symbol: String,
+    pub order: Order,
+    pub watch: String,
+    pub condition: TriggerCondition,
+}
+
+/// Una cotización de doble punta dentro de un `Exchange::mass_quote`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MassQuote {
+    pub symbol: String,
+    pub bid: Decimal,
+    pub ask: Decimal,
+    pub size: Decimal,
+}
+
+// Ids (bid, ask) de una cotización, o por qué se rechazó.
+type QuoteResult = Result<(u64, u64), ExchangeError>;
+
+#[derive(Debug, Default)]
+pub struct Exchange {
+    books: BTreeMap<String, OrderBook>, // Ordenado: las operaciones globales recorren siempre igual
+    cross_triggers: Vec<CrossTrigger>,  // En orden de llegada
+    quotes: BTreeMap<(String, u64), (u64, u64)>, // Cotización viva (bid, ask) por símbolo y cuenta
+}
+
+impl Exchange {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registra un libro para `symbol`. Devuelve el anterior si ya existía.
+    pub fn add_symbol(&mut self, symbol: impl Into<String>, book: OrderBook) -> Option<OrderBook> {
+        self.books.insert(symbol.into(), book)
+    }
+
+    /// Crea el libro de `symbol` con sus propios parámetros de negociación
+    /// (tick, lote, nocional mínimo, comisiones, modo de matching...): cada
+    /// orden enviada a ese símbolo se valida contra su `config`. Un matcher a
+    /// medida se instala después con `book_mut(symbol)?.set_matcher(...)`.
+    pub fn create_symbol(&mut self, symbol: impl Into<String>, config: BookConfig) -> Result<(), ExchangeError> {
+        let symbol = symbol.into();
+        if self.books.contains_key(&symbol) {
+            return Err(ExchangeError::DuplicateSymbol(symbol));
+        }
+        book_log!("🆕 Símbolo {} creado", symbol);
+        self.books.insert(symbol, OrderBook::with_config(config));
+        Ok(())
+    }
+
+    /// Parámetros de negociación de `symbol`.
+    pub fn config(&self, symbol: &str) -> Option<&BookConfig> {
+        self.books.get(symbol).map(OrderBook::config)
+    }
+
+    pub fn symbols(&self) -> impl Iterator<Item = &str> {
+        self.books.keys().map(String::as_str)
+    }
+
+    pub fn book(&self, symbol: &str) -> Option<&OrderBook> {
+        self.books.get(symbol)
+    }
+
+    pub fn book_mut(&mut self, symbol: &str) -> Result<&mut OrderBook, ExchangeError> {
+        self.books.get_mut(symbol).ok_or_else(|| ExchangeError::UnknownSymbol(symbol.to_string()))
+    }
+
+    /// Ticker (puntas, tamaños y último precio) de cada símbolo, en orden
+    /// alfabético, para una vista general del mercado. Cada uno sale del mejor
+    /// nivel de su libro sin recorrer la profundidad; un libro que nunca
+    /// recibió órdenes aparece con las puntas vacías.
+    pub fn tickers(&self) -> Vec<(String, Touch)> {
+        self.books
+            .iter()
+            .map(|(symbol, book)| {
+                let touch = book.touch().unwrap_or_else(|| Touch {
+                    bid: None,
+                    bid_size: Decimal::ZERO,
+                    ask: None,
+                    ask_size: Decimal::ZERO,
+                    last: None,
+                    ts: book.current_time(),
+                });
+                (symbol.clone(), touch)
+            })
+            .collect()
+    }
+
+    /// Envía la orden y después dispara los triggers entre mercados que su
+    /// ejecución haya activado.
+    pub fn submit(&mut self, symbol: &str, order: Order) -> Result<OrderAck, ExchangeError> {
+        let ack = self.book_mut(symbol)?.submit(order)?;
+        self.fire_cross_triggers();
+        Ok(ack)
+    }
+
+    /// Cancela una orden del libro o, si todavía no se disparó, el trigger
+    /// entre mercados que la lleva.
+    pub fn cancel(&mut self, symbol: &str, id: u64) -> Result<Option<Order>, ExchangeError> {
+        if let Some(order) = self.book_mut(symbol)?.cancel_order(id) {
+            return Ok(Some(order));
+        }
+        let pos = self.cross_triggers.iter().position(|t| t.symbol == symbol && t.order.id == id);
+        Ok(pos.map(|pos| self.cross_triggers.remove(pos).order))
+    }
+
+    // --- COTIZACIÓN MASIVA ---
+
+    /// Cotiza `account` en varios símbolos de una sola vez. En cada símbolo
+    /// reemplaza la cotización anterior de la cuenta (si la hay) por la nueva,
+    /// todo o nada por símbolo: si una punta se rechaza, ese símbolo queda sin
+    /// cotizar y los demás siguen. La excepción es `CancelTooSoon`: la
+    /// cotización anterior todavía no se puede retirar y sigue vigente.
+    /// Devuelve, en orden, los ids (bid, ask) o el error de cada símbolo.
+    pub fn mass_quote(&mut self, account: u64, quotes: &[MassQuote]) -> Vec<(String, QuoteResult)> {
+        book_log!("📣 Cotización masiva de la cuenta {} en {} símbolos", account, quotes.len());
+        let mut results = Vec::with_capacity(quotes.len());
+        for quote in quotes {
+            let key = (quote.symbol.clone(), account);
+            let previous = self.quotes.get(&key).copied();
+            let result = self.book_mut(&quote.symbol).and_then(|book| {
+                let ids = match previous {
+                    Some(previous) => book.requote(previous, quote.bid, quote.ask, quote.size, account),
+                    None => book.quote(quote.bid, quote.ask, quote.size, account),
+                };
+                ids.map_err(ExchangeError::from)
+            });
+            match &result {
+                Ok(ids) => {
+                    self.quotes.insert(key, *ids);
+                }
+                Err(e) => {
+                    book_log!("   💥 Cotización en {} rechazada: {}", quote.symbol, e);
+                    // Una recotización que no pudo retirar la anterior la deja viva
+                    let still_live = previous.zip(self.books.get(&quote.symbol))
+                        .is_some_and(|((bid, ask), book)| book.order(bid).is_some() || book.order(ask).is_some());
+                    if !still_live {
+                        self.quotes.remove(&key);
+                    }
+                }
+            }
+            results.push((quote.symbol.clone(), result));
+        }
+        self.fire_cross_triggers();
+        results
+    }
+
+    /// Ids (bid, ask) de la última cotización masiva de `account` en `symbol`.
+    /// Las puntas pueden haberse ejecutado o cancelado desde entonces.
+    pub fn live_quote(&self, symbol: &str, account: u64) -> Option<(u64, u64)> {
+        self.quotes.get(&(symbol.to_string(), account)).copied()
+    }
+
+    // --- TRIGGERS ENTRE MERCADOS ---
+
+    /// Deja `order` dormida hasta que el último precio de `watch` cumpla
+    /// `condition`; entonces se envía al libro de `symbol`. Si la condición ya
+    /// se cumple, sale en el acto. La orden se valida recién al dispararse.
+    pub fn add_cross_trigger(&mut self, symbol: &str, order: Order, watch: &str, condition: TriggerCondition) -> Result<(), ExchangeError> {
+        for known in [symbol, watch] {
+            if !self.books.contains_key(known) {
+                return Err(ExchangeError::UnknownSymbol(known.to_string()));
+            }
+        }
+        book_log!("🎯 Orden #{} de {} esperando que {} cumpla {:?}", order.id, symbol, watch, condition);
+        self.cross_triggers.push(CrossTrigger { symbol: symbol.to_string(), order, watch: watch.to_string(), condition });
+        self.fire_cross_triggers();
+        Ok(())
+    }
+
+    /// Triggers entre mercados que todavía no se dispararon, en orden de llegada.
+    pub fn pending_cross_triggers(&self) -> &[CrossTrigger] {
+        &self.cross_triggers
+    }
+
+    /// Envía, en orden de llegada, las órdenes cuyos triggers ya se cumplen y
+    /// devuelve el resultado de cada una con su símbolo. Una orden disparada
+    /// puede mover otro precio y activar más triggers, en cascada. `submit` lo
+    /// llama solo; hace falta llamarlo a mano después de operar un libro con
+    /// `book_mut`.
+    pub fn fire_cross_triggers(&mut self) -> Vec<(String, Result<OrderAck, OrderError>)> {
+        let mut fired = Vec::new();
+        while let Some(pos) = self.cross_triggers.iter().position(|t| {
+            self.books.get(&t.watch).and_then(OrderBook::last_price).is_some_and(|price| t.condition.is_met(price))
+        }) {
+            let trigger = self.cross_triggers.remove(pos);
+            book_log!("🎯 {} cumplió {:?}: sale la orden #{} en {}", trigger.watch, trigger.condition, trigger.order.id, trigger.symbol);
+            // `add_symbol` pudo reemplazar el libro, pero nunca lo quita
+            let Some(book) = self.books.get_mut(&trigger.symbol) else { continue };
+            let result = book.submit(trigger.order);
+            if let Err(e) = &result {
+                book_log!("   💥 Orden disparada rechazada en {}: {}", trigger.symbol, e);
+            }
+            fired.push((trigger.symbol, result));
+        }
+        fired
+    }
+
+    /// Kill switch: pasa todos los libros a `CancelOnly`. Cada libro registra
+    /// su propio evento de cambio de estado.
+    pub fn halt_all(&mut self) {
+        book_log!("🛑 Kill switch: {} símbolos pasan a CancelOnly", self.books.len());
+        self.set_all(TradingState::CancelOnly);
+    }
+
+    /// Vuelve a abrir todos los libros.
+    pub fn resume_all(&mut self) {
+        book_log!("🟢 Reanudando {} símbolos", self.books.len());
+        self.set_all(TradingState::Open);
+    }
+
+    fn set_all(&mut self, state: TradingState) {
+        for book in self.books.values_mut() {
+            book.set_trading_state(state);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal_macros::dec;
+
+    use super::*;
+    use crate::events::BookEvent;
+    use crate::orderbook::Side;
+    use crate::orderbook::tests::order;
+
+    fn exchange(symbols: &[&str]) -> Exchange {
+        let mut exchange = Exchange::new();
+        for symbol in symbols {
+            let mut book = OrderBook::new();
+            book.enable_events();
+            exchange.add_symbol(*symbol, book);
+        }
+        exchange
+    }
+
+    // --- KILL SWITCH ---
+
+    #[test]
+    fn halt_all_rejects_everywhere_until_resume() {
+        let mut exchange = exchange(&["BTC", "ETH"]);
+        exchange.halt_all();
+        for symbol in ["BTC", "ETH"] {
+            let halted = exchange.submit(symbol, order(1, Side::Buy, dec!(100), dec!(1)));
+            assert_eq!(halted, Err(ExchangeError::Rejected(OrderError::MarketHalted(TradingState::CancelOnly))));
+            let events = exchange.book_mut(symbol).unwrap().drain_events();
+            assert!(events.iter().any(|e| e.event == BookEvent::TradingStateChanged { state: TradingState::CancelOnly }));
+        }
+
+        exchange.resume_all();
+        for symbol in ["BTC", "ETH"] {
+            assert!(exchange.submit(symbol, order(1, Side::Buy, dec!(100), dec!(1))).is_ok());
+        }
+    }
+
+    // --- TRIGGERS ENTRE MERCADOS ---
+
+    #[test]
+    fn a_trade_in_one_symbol_wakes_an_order_in_another() {
+        let mut exchange = exchange(&["BTC", "ETH"]);
+        exchange.submit("ETH", order(1, Side::Sell, dec!(3000), dec!(5))).unwrap();
+        exchange.submit("BTC", order(2, Side::Buy, dec!(49000), dec!(1))).unwrap();
+
+        let stop = order(3, Side::Buy, dec!(3000), dec!(2));
+        exchange.add_cross_trigger("ETH", stop, "BTC", TriggerCondition::AtOrBelow(dec!(49500))).unwrap();
+        assert_eq!(exchange.pending_cross_triggers().len(), 1);
+        assert_eq!(exchange.book("ETH").unwrap().best_ask(), Some(dec!(3000)));
+
+        // BTC opera a 49000: cumple la condición y la compra sale en ETH
+        exchange.submit("BTC", order(4, Side::Sell, dec!(49000), dec!(1))).unwrap();
+        assert!(exchange.pending_cross_triggers().is_empty());
+        assert_eq!(exchange.book("ETH").unwrap().order(1).map(|o| o.amount), Some(dec!(3)));
+        assert_eq!(exchange.book("ETH").unwrap().last_price(), Some(dec!(3000)));
+    }
+
+    #[test]
+    fn cross_triggers_need_both_symbols() {
+        let mut exchange = exchange(&["BTC"]);
+        let unknown = exchange.add_cross_trigger("BTC", order(1, Side::Buy, dec!(1), dec!(1)), "SOL", TriggerCondition::AtOrAbove(dec!(1)));
+        assert_eq!(unknown, Err(ExchangeError::UnknownSymbol("SOL".into())));
+    }
+
+    // --- COTIZACIÓN MASIVA ---
+
+    #[test]
+    fn mass_quote_puts_a_two_sided_top_on_each_symbol() {
+        let mut exchange = exchange(&["BTC", "ETH", "SOL"]);
+        let quote = |symbol: &str, bid, ask, size| MassQuote { symbol: symbol.into(), bid, ask, size };
+        let quotes = [quote("BTC", dec!(49990), dec!(50010), dec!(1)), quote("ETH", dec!(2999), dec!(3001), dec!(5)), quote("SOL", dec!(99), dec!(101), dec!(20))];
+
+        let results = exchange.mass_quote(7, &quotes);
+        assert_eq!(results.iter().map(|(symbol, _)| symbol.as_str()).collect::<Vec<_>>(), ["BTC", "ETH", "SOL"]);
+        assert!(results.iter().all(|(_, result)| result.is_ok()), "{results:?}");
+        for quote in &quotes {
+            let book = exchange.book(&quote.symbol).unwrap();
+            assert_eq!((book.best_bid(), book.best_ask()), (Some(quote.bid), Some(quote.ask)));
+            assert_eq!((book.total_volume(Side::Buy), book.total_volume(Side::Sell)), (quote.size, quote.size));
+        }
+
+        // La segunda vuelta reemplaza; un símbolo que falla queda sin cotizar y no frena a los demás
+        let results = exchange.mass_quote(7, &[quote("BTC", dec!(50000), dec!(50020), dec!(1)), quote("ETH", dec!(3001), dec!(2999), dec!(5))]);
+        assert!(results[0].1.is_ok());
+        assert!(matches!(results[1].1, Err(ExchangeError::Rejected(OrderError::PostOnlyWouldCross { .. }))));
+        let btc = exchange.book("BTC").unwrap();
+        assert_eq!((btc.best_bid(), btc.best_ask(), btc.len()), (Some(dec!(50000)), Some(dec!(50020)), 2));
+        assert!(exchange.book("ETH").unwrap().is_empty());
+        assert_eq!(exchange.book("SOL").unwrap().len(), 2);
+    }
+
+    // --- PARÁMETROS POR SÍMBOLO ---
+
+    #[test]
+    fn each_symbol_enforces_its_own_tick_size() {
+        let mut exchange = Exchange::new();
+        exchange.create_symbol("BTC", BookConfig { tick_size: Some(dec!(0.5)), ..BookConfig::default() }).unwrap();
+        exchange.create_symbol("ETH", BookConfig { tick_size: Some(dec!(0.01)), ..BookConfig::default() }).unwrap();
+        assert_eq!(exchange.config("ETH").and_then(|config| config.tick_size), Some(dec!(0.01)));
+
+        let rejected = exchange.submit("BTC", order(1, Side::Buy, dec!(100.25), dec!(1)));
+        assert_eq!(rejected, Err(ExchangeError::Rejected(OrderError::InvalidTick { price: dec!(100.25), tick: dec!(0.5) })));
+        assert!(exchange.submit("BTC", order(2, Side::Buy, dec!(100.5), dec!(1))).is_ok());
+        assert!(exchange.submit("ETH", order(3, Side::Buy, dec!(100.25), dec!(1))).is_ok());
+
+        let duplicate = exchange.create_symbol("BTC", BookConfig::default());
+        assert_eq!(duplicate, Err(ExchangeError::DuplicateSymbol("BTC".into())));
+    }
+
+    // --- TICKERS ---
+
+    #[test]
+    fn tickers_cover_every_symbol_with_its_top_and_last_price() {
+        let mut exchange = exchange(&["SOL", "BTC", "ETH"]);
+        exchange.submit("BTC", order(1, Side::Sell, dec!(50010), dec!(2))).unwrap();
+        exchange.submit("BTC", order(2, Side::Buy, dec!(49990), dec!(1))).unwrap();
+        exchange.submit("ETH", order(3, Side::Sell, dec!(3000), dec!(5))).unwrap();
+        exchange.submit("ETH", order(4, Side::Buy, dec!(3000), dec!(2))).unwrap();
+
+        let tickers = exchange.tickers();
+        assert_eq!(tickers.iter().map(|(symbol, _)| symbol.as_str()).collect::<Vec<_>>(), ["BTC", "ETH", "SOL"]);
+        let top = |touch: &Touch| (touch.bid, touch.bid_size, touch.ask, touch.ask_size, touch.last);
+        assert_eq!(top(&tickers[0].1), (Some(dec!(49990)), dec!(1), Some(dec!(50010)), dec!(2), None));
+        assert_eq!(top(&tickers[1].1), (None, dec!(0), Some(dec!(3000)), dec!(3), Some(dec!(3000))));
+        // Sin órdenes: puntas vacías con la hora del libro
+        assert_eq!(top(&tickers[2].1), (None, dec!(0), None, dec!(0), None));
+        assert_eq!(tickers[1].1.ts, 4);
+        assert_eq!(Some(tickers[0].1.clone()), exchange.book("BTC").unwrap().touch());
+    }
+
+    #[test]
+    fn a_mass_requote_that_cannot_pull_the_old_quote_keeps_tracking_it() {
+        let clock = crate::clock::VirtualClock::new(0);
+        let mut exchange = Exchange::new();
+        exchange.add_symbol("BTC", OrderBook::builder().clock(clock.clone()).min_resting_time(100, crate::config::MinRestingPolicy::Reject).build());
+        let quote = |bid, ask| [MassQuote { symbol: "BTC".into(), bid, ask, size: dec!(1) }];
+        let first = exchange.mass_quote(7, &quote(dec!(99), dec!(101)))[0].1.clone().unwrap();
+
+        clock.set(10);
+        let results = exchange.mass_quote(7, &quote(dec!(98), dec!(102)));
+        assert!(matches!(results[0].1, Err(ExchangeError::Rejected(OrderError::CancelTooSoon { .. }))));
+        assert_eq!(exchange.book("BTC").unwrap().len(), 2);
+
+        // Pasado el mínimo, la siguiente vuelta reemplaza la cotización original
+        clock.set(100);
+        let second = exchange.mass_quote(7, &quote(dec!(98), dec!(102)))[0].1.clone().unwrap();
+        let book = exchange.book("BTC").unwrap();
+        assert_eq!(book.len(), 2);
+        assert!(book.order(first.0).is_none() && book.order(second.0).is_some());
+    }
+}
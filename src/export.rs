@@ -0,0 +1,171 @@
+// --- EXPORTACIÓN PARQUET (ANALÍTICA) ---
+//
+// Escribe trades y snapshots del libro en formato columnar para cargarlos
+// directamente desde pandas / polars. Precios y cantidades se guardan como
+// Decimal128 con escala fija para no perder precisión en el camino.
+
+use std::fs::File;
+use std::path::Path;
+use std::sync::Arc;
+
+use arrow::array::{ArrayRef, Decimal128Array, StringArray, UInt64Array};
+use arrow::datatypes::{DataType, Field, Schema, SchemaRef};
+use arrow::record_batch::RecordBatch;
+use parquet::arrow::ArrowWriter;
+use parquet::errors::{ParquetError, Result};
+use rust_decimal::Decimal;
+
+use crate::orderbook::{Order, OrderBook, Side, Trade};
+
+/// Precisión y escala de las columnas decimales (precio y cantidad).
+pub const DECIMAL_PRECISION: u8 = 38;
+pub const DECIMAL_SCALE: i8 = 8;
+
+fn decimal_field(name: &str) -> Field {
+    Field::new(name, DataType::Decimal128(DECIMAL_PRECISION, DECIMAL_SCALE), false)
+}
+
+pub fn trades_schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new("trade_id", DataType::UInt64, false),
+        Field::new("timestamp", DataType::UInt64, false),
+        decimal_field("price"),
+        decimal_field("amount"),
+        Field::new("maker_id", DataType::UInt64, false),
+        Field::new("taker_id", DataType::UInt64, false),
+        Field::new("aggressor", DataType::Utf8, false),
+    ]))
+}
+
+pub fn snapshot_schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new("timestamp", DataType::UInt64, false),
+        Field::new("side", DataType::Utf8, false),
+        decimal_field("price"),
+        decimal_field("amount"),
+        Field::new("order_id", DataType::UInt64, false),
+    ]))
+}
+
+// Lleva el Decimal a la escala fija de la columna y devuelve la mantisa.
+fn to_decimal128(value: Decimal) -> Result<i128> {
+    let mut scaled = value;
+    scaled.rescale(DECIMAL_SCALE as u32);
+    if scaled.scale() != DECIMAL_SCALE as u32 {
+        return Err(ParquetError::General(format!("valor fuera de rango para Decimal128: {value}")));
+    }
+    Ok(scaled.mantissa())
+}
+
+fn decimal_column(values: impl IntoIterator<Item = Decimal>) -> Result<ArrayRef> {
+    let mantissas = values.into_iter().map(to_decimal128).collect::<Result<Vec<_>>>()?;
+    let array = Decimal128Array::from(mantissas)
+        .with_precision_and_scale(DECIMAL_PRECISION, DECIMAL_SCALE)?;
+    Ok(Arc::new(array))
+}
+
+fn side_label(side: Side) -> &'static str {
+    match side {
+        Side::Buy => "buy",
+        Side::Sell => "sell",
+    }
+}
+
+/// Escribe una lista de trades en un único archivo Parquet.
+pub fn write_trades<P: AsRef<Path>>(path: P, trades: &[Trade]) -> Result<()> {
+    let schema = trades_schema();
+    let batch = RecordBatch::try_new(schema.clone(), vec![
+        Arc::new(UInt64Array::from_iter_values(trades.iter().map(|t| t.trade_id))),
+        Arc::new(UInt64Array::from_iter_values(trades.iter().map(|t| t.timestamp))),
+        decimal_column(trades.iter().map(|t| t.price))?,
+        decimal_column(trades.iter().map(|t| t.amount))?,
+        Arc::new(UInt64Array::from_iter_values(trades.iter().map(|t| t.maker_id))),
+        Arc::new(UInt64Array::from_iter_values(trades.iter().map(|t| t.taker_id))),
+        Arc::new(StringArray::from_iter_values(trades.iter().map(|t| side_label(t.aggressor)))),
+    ])?;
+
+    let mut writer = ArrowWriter::try_new(File::create(path)?, schema, None)?;
+    writer.write(&batch)?;
+    writer.close()?;
+    Ok(())
+}
+
+/// Escritor de snapshots periódicos: cada llamada a `write_snapshot` agrega
+/// todas las órdenes en reposo del libro con el timestamp indicado.
+pub struct SnapshotWriter {
+    writer: ArrowWriter<File>,
+}
+
+impl SnapshotWriter {
+    pub fn create<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let writer = ArrowWriter::try_new(File::create(path)?, snapshot_schema(), None)?;
+        Ok(Self { writer })
+    }
+
+    pub fn write_snapshot(&mut self, book: &OrderBook, timestamp: u64) -> Result<()> {
+        // Bids primero (mejor precio al principio), luego asks
+        let rows: Vec<&Order> = book.iter_orders(Side::Buy)
+            .chain(book.iter_orders(Side::Sell))
+            .collect();
+
+        let batch = RecordBatch::try_new(snapshot_schema(), vec![
+            Arc::new(UInt64Array::from_iter_values(rows.iter().map(|_| timestamp))),
+            Arc::new(StringArray::from_iter_values(rows.iter().map(|o| side_label(o.side)))),
+            decimal_column(rows.iter().map(|o| o.price))?,
+            decimal_column(rows.iter().map(|o| o.amount))?,
+            Arc::new(UInt64Array::from_iter_values(rows.iter().map(|o| o.id))),
+        ])?;
+
+        self.writer.write(&batch)?;
+        // Un row group por snapshot, así cada foto queda delimitada en el archivo
+        self.writer.flush()
+    }
+
+    pub fn close(self) -> Result<()> {
+        self.writer.close()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow::array::AsArray;
+    use arrow::datatypes::{Decimal128Type, UInt64Type};
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+    use rust_decimal_macros::dec;
+
+    use super::*;
+
+    #[test]
+    fn trades_round_trip_through_parquet() {
+        let mut book = OrderBook::new();
+        for (id, price) in [(1, dec!(100)), (2, dec!(100.5)), (3, dec!(101.25))] {
+            book.add_order(Order::builder(id, Side::Sell, price, dec!(2)).account(1).build().unwrap()).unwrap();
+        }
+        let trades = book.add_order(Order::builder(4, Side::Buy, dec!(102), dec!(5)).account(2).build().unwrap()).unwrap();
+        assert_eq!(trades.len(), 3);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("trades.parquet");
+        write_trades(&path, &trades).unwrap();
+
+        let reader = ParquetRecordBatchReaderBuilder::try_new(File::open(&path).unwrap()).unwrap().build().unwrap();
+        let batches: Vec<RecordBatch> = reader.collect::<std::result::Result<_, _>>().unwrap();
+        assert_eq!(batches.len(), 1);
+        let batch = &batches[0];
+        assert_eq!(batch.num_rows(), 3);
+
+        let ids = batch.column(0).as_primitive::<UInt64Type>();
+        let prices = batch.column(2).as_primitive::<Decimal128Type>();
+        let amounts = batch.column(3).as_primitive::<Decimal128Type>();
+        let makers = batch.column(4).as_primitive::<UInt64Type>();
+        let aggressors = batch.column(6).as_string::<i32>();
+        for (row, trade) in trades.iter().enumerate() {
+            assert_eq!(ids.value(row), trade.trade_id);
+            assert_eq!(Decimal::from_i128_with_scale(prices.value(row), DECIMAL_SCALE as u32), trade.price);
+            assert_eq!(Decimal::from_i128_with_scale(amounts.value(row), DECIMAL_SCALE as u32), trade.amount);
+            assert_eq!(makers.value(row), trade.maker_id);
+            assert_eq!(aggressors.value(row), "buy");
+        }
+    }
+}
@@ -0,0 +1,269 @@
+// --- SERVICIO gRPC (ORDER ENTRY + MARKET DATA) ---
+//
+// Expone el libro por gRPC: alta y cancelación de órdenes, y un stream de
+// market data que arranca con un snapshot y sigue con los deltas de nivel.
+// El libro vive detrás de un Mutex; los eventos se publican con el lock
+// tomado para que el stream respete el orden de secuencia.
+
+use std::pin::Pin;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use rust_decimal::Decimal;
+use tokio::sync::broadcast;
+use tokio_stream::wrappers::BroadcastStream;
+use tokio_stream::wrappers::errors::BroadcastStreamRecvError;
+use tokio_stream::{Stream, StreamExt};
+use tonic::{Request, Response, Status};
+
+use crate::error::OrderError;
+use crate::events::{BookEvent, SequencedEvent};
+use crate::orderbook::{Order, OrderBook, OrderType, Side, TimeInForce, Trade, Visibility};
+use crate::snapshot::{SnapshotLevel, SnapshotMode};
+
+pub mod proto {
+    tonic::include_proto!("orderbook");
+}
+
+use proto::market_data::Payload;
+use proto::order_book_service_server::{OrderBookService, OrderBookServiceServer};
+
+/// Capacidad del canal de deltas; un suscriptor más lento que esto recibe `DATA_LOSS`.
+const UPDATES_CAPACITY: usize = 1024;
+
+pub struct BookService {
+    book: Arc<Mutex<OrderBook>>,
+    updates: broadcast::Sender<SequencedEvent>,
+}
+
+impl BookService {
+    pub fn new(mut book: OrderBook) -> Self {
+        book.enable_events();
+        let (updates, _) = broadcast::channel(UPDATES_CAPACITY);
+        Self { book: Arc::new(Mutex::new(book)), updates }
+    }
+
+    pub fn into_server(self) -> OrderBookServiceServer<Self> {
+        OrderBookServiceServer::new(self)
+    }
+
+    fn lock(&self) -> Result<MutexGuard<'_, OrderBook>, Status> {
+        self.book.lock().map_err(|_| Status::internal("libro envenenado"))
+    }
+
+    // Reenvía a los suscriptores los eventos pendientes del libro.
+    fn publish(&self, book: &mut OrderBook) {
+        for event in book.drain_events() {
+            let _ = self.updates.send(event); // Sin suscriptores no es un error
+        }
+    }
+}
+
+// --- CONVERSIONES proto <-> dominio ---
+
+fn parse_decimal(field: &str, value: &str) -> Result<Decimal, Status> {
+    Decimal::from_str(value).map_err(|_| Status::invalid_argument(format!("{field} inválido: {value:?}")))
+}
+
+fn side_from_proto(side: i32) -> Result<Side, Status> {
+    match proto::Side::try_from(side) {
+        Ok(proto::Side::Buy) => Ok(Side::Buy),
+        Ok(proto::Side::Sell) => Ok(Side::Sell),
+        _ => Err(Status::invalid_argument("side inválido")),
+    }
+}
+
+fn side_to_proto(side: Side) -> proto::Side {
+    match side {
+        Side::Buy => proto::Side::Buy,
+        Side::Sell => proto::Side::Sell,
+    }
+}
+
+fn order_type_from_proto(order_type: i32, max_slippage: Option<&str>, budget: Option<&str>) -> Result<OrderType, Status> {
+    if budget.is_some() && (max_slippage.is_some() || order_type != proto::OrderType::Market as i32) {
+        return Err(Status::invalid_argument("budget sólo aplica a órdenes de mercado sin max_slippage"));
+    }
+    match (proto::OrderType::try_from(order_type), max_slippage) {
+        (Ok(proto::OrderType::Limit), None) => Ok(OrderType::Limit),
+        (Ok(proto::OrderType::Limit | proto::OrderType::PostOnly | proto::OrderType::AllOrNone), Some(_)) => {
+            Err(Status::invalid_argument("max_slippage sólo aplica a órdenes de mercado"))
+        }
+        (Ok(proto::OrderType::PostOnly), None) => Ok(OrderType::PostOnly),
+        (Ok(proto::OrderType::AllOrNone), None) => Ok(OrderType::AllOrNone),
+        (Ok(proto::OrderType::Market), None) => match budget {
+            Some(value) => Ok(OrderType::MarketQuote { budget: parse_decimal("budget", value)? }),
+            None => Ok(OrderType::Market),
+        },
+        (Ok(proto::OrderType::Market), Some(value)) => Ok(OrderType::MarketWithSlippage {
+            max_slippage: parse_decimal("max_slippage", value)?,
+        }),
+        (Err(_), _) => Err(Status::invalid_argument("order_type inválido")),
+    }
+}
+
+fn visibility_from_proto(display_amount: Option<&str>, hidden: bool) -> Result<Visibility, Status> {
+    match (display_amount, hidden) {
+        (None, false) => Ok(Visibility::Visible),
+        (None, true) => Ok(Visibility::Hidden),
+        (Some(value), false) => Ok(Visibility::Iceberg { peak: parse_decimal("display_amount", value)? }),
+        (Some(_), true) => Err(Status::invalid_argument("display_amount y hidden son excluyentes")),
+    }
+}
+
+fn time_in_force_from_proto(expire_at: Option<u64>, day: bool, ioc: bool, fok_or_rest: bool) -> Result<TimeInForce, Status> {
+    match (expire_at, day, ioc, fok_or_rest) {
+        (None, false, false, false) => Ok(TimeInForce::Gtc),
+        (Some(expire_at), false, false, false) => Ok(TimeInForce::Gtt { expire_at }),
+        (None, true, false, false) => Ok(TimeInForce::Day),
+        (None, false, true, false) => Ok(TimeInForce::Ioc),
+        (None, false, false, true) => Ok(TimeInForce::FokOrRest),
+        _ => Err(Status::invalid_argument("expire_at, day, ioc y fok_or_rest son excluyentes")),
+    }
+}
+
+fn order_from_proto(request: proto::SubmitOrderRequest) -> Result<Order, Status> {
+    Ok(Order {
+        id: request.id,
+        price: parse_decimal("price", &request.price)?,
+        amount: parse_decimal("amount", &request.amount)?,
+        side: side_from_proto(request.side)?,
+        timestamp: request.timestamp,
+        account: request.account,
+        subaccount: request.subaccount,
+        min_fill: request.min_fill.as_deref().map(|value| parse_decimal("min_fill", value)).transpose()?,
+        price_cap: request.price_cap.as_deref().map(|value| parse_decimal("price_cap", value)).transpose()?,
+        cancel_at_fill_ratio: request.cancel_at_fill_ratio.as_deref().map(|value| parse_decimal("cancel_at_fill_ratio", value)).transpose()?,
+        order_type: order_type_from_proto(request.order_type, request.max_slippage.as_deref(), request.budget.as_deref())?,
+        visibility: visibility_from_proto(request.display_amount.as_deref(), request.hidden)?,
+        time_in_force: time_in_force_from_proto(request.expire_at, request.day, request.ioc, request.fok_or_rest)?,
+        client_order_id: request.client_order_id,
+        strategy_id: request.strategy_id,
+        venue_tag: request.venue_tag,
+    })
+}
+
+fn trade_to_proto(trade: &Trade) -> proto::Trade {
+    proto::Trade {
+        maker_id: trade.maker_id,
+        taker_id: trade.taker_id,
+        price: trade.price.to_string(),
+        amount: trade.amount.to_string(),
+        timestamp: trade.timestamp,
+        aggressor: side_to_proto(trade.aggressor).into(),
+        trade_id: trade.trade_id,
+        prevailing_mid: trade.prevailing_mid.map(|mid| mid.to_string()),
+        taker_strategy_id: trade.taker_strategy_id.clone(),
+        maker_strategy_id: trade.maker_strategy_id.clone(),
+        taker_venue_tag: trade.taker_venue_tag.clone(),
+        maker_venue_tag: trade.maker_venue_tag.clone(),
+    }
+}
+
+fn levels_to_proto(levels: Vec<SnapshotLevel>) -> Vec<proto::Level> {
+    levels.into_iter()
+        .map(|level| proto::Level { price: level.price.to_string(), amount: level.visible_amount.to_string() })
+        .collect()
+}
+
+fn snapshot_to_proto(book: &OrderBook, depth: usize) -> proto::MarketData {
+    let snapshot = book.depth_snapshot(depth, SnapshotMode::Public);
+    proto::MarketData {
+        payload: Some(Payload::Snapshot(proto::BookSnapshot {
+            seq: snapshot.seq,
+            bids: levels_to_proto(snapshot.bids),
+            asks: levels_to_proto(snapshot.asks),
+        })),
+    }
+}
+
+fn delta_to_proto(event: SequencedEvent) -> Option<proto::MarketData> {
+    let BookEvent::LevelDelta { side, price, amount } = event.event else { return None };
+    Some(proto::MarketData {
+        payload: Some(Payload::Delta(proto::LevelDelta {
+            seq: event.seq,
+            side: side_to_proto(side).into(),
+            price: price.to_string(),
+            amount: amount.to_string(),
+        })),
+    })
+}
+
+// --- RPCs ---
+
+type MarketDataStream = Pin<Box<dyn Stream<Item = Result<proto::MarketData, Status>> + Send>>;
+
+#[tonic::async_trait]
+impl OrderBookService for BookService {
+    async fn submit_order(
+        &self,
+        request: Request<proto::SubmitOrderRequest>,
+    ) -> Result<Response<proto::SubmitOrderResponse>, Status> {
+        let order = order_from_proto(request.into_inner())?;
+
+        let mut book = self.lock()?;
+        let ack = book.submit(order).map_err(|e| match e {
+            // Parada del mercado: el cliente puede reintentar más tarde
+            OrderError::MarketHalted(_) => Status::unavailable(e.to_string()),
+            _ => Status::invalid_argument(e.to_string()),
+        })?;
+        self.publish(&mut book);
+
+        Ok(Response::new(proto::SubmitOrderResponse {
+            trades: ack.trades.iter().map(trade_to_proto).collect(),
+            client_order_id: ack.client_order_id,
+            filled_qty: ack.filled_qty.to_string(),
+            resting_qty: ack.resting_qty.to_string(),
+            cancelled_qty: ack.cancelled_qty.to_string(),
+            pending_qty: ack.pending_qty.to_string(),
+        }))
+    }
+
+    async fn cancel_order(
+        &self,
+        request: Request<proto::CancelOrderRequest>,
+    ) -> Result<Response<proto::CancelOrderResponse>, Status> {
+        let target = request.into_inner().target
+            .ok_or_else(|| Status::invalid_argument("falta id o client_order_id"))?;
+
+        let mut book = self.lock()?;
+        let cancelled = match target {
+            proto::cancel_order_request::Target::Id(id) => book.cancel_order(id),
+            proto::cancel_order_request::Target::ClientOrderId(client_id) => book.cancel_by_client_id(&client_id),
+        };
+        self.publish(&mut book);
+
+        Ok(Response::new(proto::CancelOrderResponse {
+            cancelled: cancelled.is_some(),
+            remaining: cancelled.map(|o| o.amount).unwrap_or_default().to_string(),
+        }))
+    }
+
+    type StreamMarketDataStream = MarketDataStream;
+
+    async fn stream_market_data(
+        &self,
+        request: Request<proto::StreamMarketDataRequest>,
+    ) -> Result<Response<Self::StreamMarketDataStream>, Status> {
+        let depth = match request.into_inner().depth {
+            0 => usize::MAX,
+            n => n as usize,
+        };
+
+        // Snapshot y suscripción bajo el mismo lock: ningún delta queda entre medio
+        let (snapshot, updates) = {
+            let book = self.lock()?;
+            (snapshot_to_proto(&book, depth), self.updates.subscribe())
+        };
+
+        let deltas = BroadcastStream::new(updates).filter_map(|item| match item {
+            Ok(event) => delta_to_proto(event).map(Ok),
+            Err(BroadcastStreamRecvError::Lagged(missed)) => {
+                Some(Err(Status::data_loss(format!("suscriptor atrasado: se perdieron {missed} eventos"))))
+            }
+        });
+
+        let stream: MarketDataStream = Box::pin(tokio_stream::once(Ok(snapshot)).chain(deltas));
+        Ok(Response::new(stream))
+    }
+}
@@ -0,0 +1,61 @@
+pub mod balances;
+mod binary; // Sólo agrega `OrderBook::to_bytes` y `OrderBook::from_bytes`
+pub mod clock;
+pub mod config;
+pub mod delta;
+pub mod error;
+pub mod events;
+pub mod exchange;
+pub mod latency;
+pub mod logging;
+pub mod matcher;
+pub mod orderbook;
+pub mod persist;
+pub mod positions;
+pub mod recorder;
+pub mod replay;
+pub mod settlement;
+pub mod side_map;
+pub mod sim;
+pub mod snapshot;
+pub mod spread;
+pub mod tape;
+pub mod warmup;
+
+// Engine asíncrono (actores sobre canales de tokio). Sin esta feature queda el
+// núcleo síncrono: `OrderBook`, `Exchange`, persistencia y replay.
+#[cfg(feature = "async")]
+pub mod engine;
+#[cfg(feature = "async")]
+pub mod metrics;
+
+#[cfg(feature = "parquet")]
+pub mod export;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+
+pub use balances::{Asset, Balance, Balances};
+pub use clock::{Clock, SystemClock, VirtualClock};
+pub use config::{BookConfig, CircuitBreaker, DepthCapPolicy, FeeSchedule, HiddenPriority, MarketNoLiquidity, MatchingMode, MinRestingPolicy, OrderBookBuilder, PriceImprovement, PriorityTieBreak, RequotePriority, RoundingMode, SelfTradePrevention, StpScope};
+#[cfg(feature = "async")]
+pub use engine::{Connection, Engine, EngineBuilder, EngineError, FillEvent, SnapshotPolicy, StreamedTrade, TradeIdScope};
+pub use delta::{DeltaApplier, ResyncNeeded};
+pub use error::OrderError;
+pub use events::{BookEvent, SequencedEvent};
+pub use exchange::{CrossTrigger, Exchange, ExchangeError, MassQuote, TriggerCondition};
+pub use latency::LatencyHistogram;
+pub use matcher::{Allocation, Matcher, ProRata};
+#[cfg(feature = "async")]
+pub use metrics::EngineMetrics;
+pub use orderbook::{ExecSummary, MatchReason, MatchStep, Order, OrderAck, OrderBuilder, OrderBook, OrderType, PendingTrade, PriceLevel, RejectedOrder, Side, TimeInForce, Trade, TradeIdCounter, TradingState, Visibility};
+pub use persist::{Compression, load_snapshot, recover_book, save_snapshot, save_snapshot_atomic};
+pub use positions::Positions;
+pub use recorder::{EventRecorder, FlushPolicy};
+pub use replay::{DeterministicClock, ReplayHarness, ReplayOutcome};
+pub use settlement::SettlementHandler;
+pub use side_map::SideMap;
+pub use sim::{OrderGenerator, SimConfig, SimRng};
+pub use snapshot::{BookView, DepthSnapshot, Level, Snapshot, SnapshotLevel, SnapshotMode, Touch};
+pub use spread::{RealizedSpread, SpreadStats};
+pub use tape::{AggTrade, SymbolTrade, TradeAggregator};
+pub use warmup::L2Snapshot;
@@ -0,0 +1,42 @@
+// --- LOGS DE CONSOLA ---
+//
+// El libro narra lo que hace por stdout (📥, ⚡, 📌...). Para benchmarks o
+// flujos grandes de órdenes ese ruido se puede apagar en todo el proceso.
+
+use std::cell::Cell;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static ENABLED: AtomicBool = AtomicBool::new(true);
+
+thread_local! {
+    // Apagado sólo en este hilo mientras corre `silenced` (simulaciones, etc.)
+    static SILENCED: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Enciende o apaga los mensajes de consola del libro.
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed) && !SILENCED.with(Cell::get)
+}
+
+// Corre `f` sin logs en el hilo actual; los demás hilos siguen logueando.
+pub(crate) fn silenced<R>(f: impl FnOnce() -> R) -> R {
+    let previous = SILENCED.with(|silenced| silenced.replace(true));
+    let result = f();
+    SILENCED.with(|silenced| silenced.set(previous));
+    result
+}
+
+// `println!` que respeta `set_enabled`.
+macro_rules! book_log {
+    ($($arg:tt)*) => {
+        if $crate::logging::enabled() {
+            println!($($arg)*);
+        }
+    };
+}
+
+pub(crate) use book_log;
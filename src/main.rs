@@ -1,129 +1,5 @@
-use std::collections::BTreeMap;
-use std::cmp::Ordering;
+use hft_orderbook::{Engine, Order, Side, TimeInForce, VirtualClock};
 use rust_decimal::prelude::*; // Necesario para manejar precios financieros
-use tokio::sync::mpsc;        // Canales para comunicación asíncrona
-use tokio::time::{sleep, Duration};
-
-// --- ESTRUCTURAS DE DATOS ---
-
-#[derive(Debug, Clone, PartialEq, Eq, Copy)]
-pub enum Side {
-    Buy,
-    Sell,
-}
-
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub struct Order {
-    pub id: u64,
-    pub price: Decimal,
-    pub amount: Decimal,
-    pub side: Side,
-    pub timestamp: u64,
-}
-
-// --- LÓGICA DE ORDENAMIENTO (EL MOTOR MATEMÁTICO) ---
-
-impl Ord for Order {
-    fn cmp(&self, other: &Self) -> Ordering {
-        match self.side {
-            // Compras: Prioridad al precio MÁS ALTO. Desempate por tiempo (FIFO).
-            Side::Buy => other.price.cmp(&self.price)
-                .then_with(|| self.id.cmp(&other.id)), // Usamos ID como proxy de tiempo simple
-            
-            // Ventas: Prioridad al precio MÁS BAJO. Desempate por tiempo (FIFO).
-            Side::Sell => self.price.cmp(&other.price)
-                .then_with(|| self.id.cmp(&other.id)),
-        }
-    }
-}
-
-impl PartialOrd for Order {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.cmp(other))
-    }
-}
-
-// --- EL LIBRO DE ÓRDENES ---
-
-pub struct OrderBook {
-    bids: BTreeMap<Order, Decimal>, // Compras
-    asks: BTreeMap<Order, Decimal>, // Ventas
-}
-
-impl OrderBook {
-    pub fn new() -> Self {
-        Self {
-            bids: BTreeMap::new(),
-            asks: BTreeMap::new(),
-        }
-    }
-
-    pub fn add_order(&mut self, mut order: Order) {
-        println!("--> 📥 Recibida Orden #{}: {:?} {} @ {}", order.id, order.side, order.amount, order.price);
-
-        // Lógica de Matching (Cruce)
-        loop {
-            if order.amount <= Decimal::zero() { break; } // Orden completada
-
-            let match_found = match order.side {
-                Side::Buy => {
-                    // Si compro, busco la venta más barata (asks)
-                    if let Some((best_ask, ask_amount)) = self.asks.iter_mut().next() {
-                        if best_ask.price <= order.price {
-                            // ¡MATCH!
-                            let trade_amount = order.amount.min(*ask_amount);
-                            println!("   ⚡ MATCH EJECUTADO: Compra #{} vs Venta #{} :: Cantidad {}", order.id, best_ask.id, trade_amount);
-                            
-                            // Actualizar cantidades (lógica simplificada)
-                            order.amount -= trade_amount;
-                            *ask_amount -= trade_amount;
-                            
-                            // Si la orden del libro se agotó, habría que eliminarla (aquí omitido por brevedad)
-                            true
-                        } else {
-                            false
-                        }
-                    } else {
-                        false
-                    }
-                },
-                Side::Sell => {
-                    // Si vendo, busco la compra más cara (bids)
-                    if let Some((best_bid, bid_amount)) = self.bids.iter_mut().next() {
-                        if best_bid.price >= order.price {
-                            // ¡MATCH!
-                            let trade_amount = order.amount.min(*bid_amount);
-                            println!("   ⚡ MATCH EJECUTADO: Venta #{} vs Compra #{} :: Cantidad {}", order.id, best_bid.id, trade_amount);
-                            
-                            order.amount -= trade_amount;
-                            *bid_amount -= trade_amount;
-                            true
-                        } else {
-                            false
-                        }
-                    } else {
-                        false
-                    }
-                }
-            };
-
-            if !match_found {
-                break; // No hay más matches posibles, salir del loop
-            }
-            // Si hubo match, el loop continúa para intentar llenar el resto de la orden
-            break; // BREAK TEMPORAL: Para evitar loops infinitos si no borramos las órdenes en 0.
-        }
-
-        // Si sobra cantidad, guardar en el libro
-        if order.amount > Decimal::zero() {
-            println!("   📌 Guardando resto en el libro: {} @ {}", order.amount, order.price);
-            match order.side {
-                Side::Buy => { self.bids.insert(order.clone(), order.amount); },
-                Side::Sell => { self.asks.insert(order.clone(), order.amount); },
-            };
-        }
-    }
-}
 
 // --- ARQUITECTURA ASÍNCRONA (SYSTEMS ENGINEERING) ---
 
@@ -131,33 +7,44 @@ impl OrderBook {
 async fn main() {
     println!("🚀 Iniciando HFT Engine v1.0...");
 
-    // 1. Canal de comunicación: Gateway -> Engine
-    let (tx, mut rx) = mpsc::channel(100);
-
-    // 2. Spawn del Motor (Consumer) en su propio hilo verde
-    let engine_handle = tokio::spawn(async move {
-        let mut book = OrderBook::new();
-        while let Some(order) = rx.recv().await {
-            book.add_order(order);
-        }
-    });
-
-    // 3. Simulación de Tráfico (Producer)
-    let orders = vec![
+    // 1. Motor shardeado: un libro, una tarea y un inbox por símbolo.
+    //    Reloj virtual: la simulación avanza el tiempo a mano, sin dormir.
+    let clock = VirtualClock::new(1_000);
+    let engine = Engine::builder().symbol("BTC-USD").symbol("ETH-USD").clock(clock.clone()).build();
+
+    // 2. Simulación de Tráfico: una línea de tiempo virtual (ms, símbolo, orden).
+    //    El timestamp de cada orden lo pone el reloj del motor al recibirla.
+    let order = |id, price: i64, amount: i64, side, account, time_in_force| {
+        Order::builder(id, side, Decimal::from(price), Decimal::from(amount))
+            .account(account)
+            .time_in_force(time_in_force)
+            .build()
+            .expect("orden de la simulación válida")
+    };
+    let timeline = [
         // Vendedor pone 1 BTC a 50,000
-        Order { id: 1, price: Decimal::from(50000), amount: Decimal::from(1), side: Side::Sell, timestamp: 100 },
+        (1_000, "BTC-USD", order(1, 50000, 1, Side::Sell, 1, TimeInForce::Gtc)),
+        // Compra de ETH que vence a los 3 segundos
+        (1_000, "ETH-USD", order(1, 3000, 10, Side::Buy, 1, TimeInForce::Gtt { expire_at: 3_000 })),
         // Comprador pone orden baja a 49,000 (No match)
-        Order { id: 2, price: Decimal::from(49000), amount: Decimal::from(1), side: Side::Buy, timestamp: 101 },
+        (1_500, "BTC-USD", order(2, 49000, 1, Side::Buy, 2, TimeInForce::Gtc)),
         // Comprador agresivo a 51,000 (Debería matchear con la venta #1)
-        Order { id: 3, price: Decimal::from(51000), amount: Decimal::from(2), side: Side::Buy, timestamp: 102 },
+        (2_000, "BTC-USD", order(3, 51000, 2, Side::Buy, 3, TimeInForce::Gtc)),
+        // Llega después del vencimiento de la compra #1: no cruza y queda en el libro
+        (3_500, "ETH-USD", order(2, 2990, 4, Side::Sell, 2, TimeInForce::Gtc)),
     ];
 
-    for order in orders {
-        tx.send(order).await.unwrap();
-        sleep(Duration::from_millis(500)).await; // Pequeña pausa para ver el efecto
+    for (at, symbol, order) in timeline {
+        clock.set(at); // Sin sleeps: el tiempo salta directo al próximo evento
+        engine.submit(symbol, order).await.unwrap();
     }
 
-    println!("✅ Todas las órdenes enviadas. Cerrando canal...");
-    drop(tx); // Cierra el canal
-    engine_handle.await.unwrap(); // Espera a que el motor termine de procesar
-}
\ No newline at end of file
+    // 3. Estado final de cada libro
+    for symbol in ["BTC-USD", "ETH-USD"] {
+        let (bid, ask) = engine.with_book(symbol, |book| (book.best_bid(), book.best_ask())).await.unwrap();
+        println!("📊 {symbol}: best bid {bid:?} / best ask {ask:?}");
+    }
+
+    println!("✅ Todas las órdenes enviadas. Cerrando motor...");
+    engine.shutdown().await; // Espera a que cada libro termine de procesar
+}
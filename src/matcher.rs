@@ -0,0 +1,176 @@
+// --- MATCHERS A MEDIDA ---
+//
+// Por defecto el libro reparte cada nivel en FIFO (precio-tiempo). Un `Matcher`
+// permite cambiar ese reparto (pro-rata, prioridad por tamaño, ...). El matcher
+// sólo propone; el libro valida cada asignación antes de ejecutarla, así que un
+// matcher con bugs no puede dejar cantidades negativas ni ejecutar peor que el
+// mejor precio contrario (trade-through).
+
+use std::fmt;
+
+use rust_decimal::Decimal;
+
+use crate::orderbook::{Order, PriceLevel};
+
+/// Fill propuesto por un matcher: contra qué orden del libro, a qué precio y cuánto.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Allocation {
+    pub maker_id: u64,
+    /// Debe ser el precio del nivel; uno peor para el taker es un trade-through.
+    pub price: Decimal,
+    pub amount: Decimal,
+}
+
+pub trait Matcher: fmt::Debug + Send + Sync {
+    /// Reparte hasta `remaining` de la orden entrante entre las órdenes de `level`,
+    /// que es siempre el mejor nivel contrario. Devolver una lista vacía (o sólo
+    /// asignaciones inválidas) corta el matching de la orden.
+    fn allocate(&self, incoming: &Order, remaining: Decimal, level: &PriceLevel) -> Vec<Allocation>;
+}
+
+/// Pro-rata por lotes: cada orden del nivel recibe una parte proporcional a su
+/// cantidad ejecutable, redondeada hacia abajo a `lot`. Lo que sobra del
+/// redondeo se reparte de a un lote por el método del mayor resto (a igual
+/// resto, por orden en la cola), así que lo asignado suma exactamente lo que la
+/// orden entrante puede tomar del nivel. Si esa cantidad no es múltiplo del
+/// lote, el pico final va a la primera orden según el mismo criterio.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProRata {
+    lot: Decimal,
+}
+
+impl ProRata {
+    /// Conviene usar el mismo lote que `BookConfig::lot_size`.
+    pub fn new(lot: Decimal) -> Self {
+        assert!(lot > Decimal::ZERO, "el lote debe ser positivo");
+        Self { lot }
+    }
+}
+
+impl Matcher for ProRata {
+    fn allocate(&self, _incoming: &Order, remaining: Decimal, level: &PriceLevel) -> Vec<Allocation> {
+        let makers: Vec<(u64, Decimal)> = level.executable_orders()
+            .filter(|(_, executable)| *executable > Decimal::ZERO)
+            .map(|(order, executable)| (order.id, executable))
+            .collect();
+        let available: Decimal = makers.iter().map(|(_, executable)| *executable).sum();
+        let target = remaining.min(available);
+        if target <= Decimal::ZERO {
+            return Vec::new();
+        }
+
+        // Parte proporcional redondeada hacia abajo al lote, y el resto que perdió cada una
+        let mut shares: Vec<(Decimal, Decimal)> = makers.iter()
+            .map(|(_, executable)| {
+                let exact = target * *executable / available;
+                let rounded = ((exact / self.lot).floor() * self.lot).min(*executable);
+                (rounded, exact - rounded)
+            })
+            .collect();
+        let mut by_remainder: Vec<usize> = (0..shares.len()).collect();
+        by_remainder.sort_by(|a, b| shares[*b].1.cmp(&shares[*a].1)); // Estable: a igual resto, la cola manda
+
+        let mut leftover = target - shares.iter().map(|(rounded, _)| *rounded).sum::<Decimal>();
+        while leftover > Decimal::ZERO {
+            let before = leftover;
+            for &i in &by_remainder {
+                let step = self.lot.min(leftover).min(makers[i].1 - shares[i].0);
+                if step > Decimal::ZERO {
+                    shares[i].0 += step;
+                    leftover -= step;
+                }
+                if leftover.is_zero() {
+                    break;
+                }
+            }
+            if leftover == before {
+                break; // No queda lugar en ninguna orden (no debería pasar: target <= available)
+            }
+        }
+
+        makers.iter().zip(shares)
+            .filter(|(_, (amount, _))| *amount > Decimal::ZERO)
+            .map(|((maker_id, _), (amount, _))| Allocation { maker_id: *maker_id, price: level.price, amount })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal_macros::dec;
+
+    use super::*;
+    use crate::orderbook::tests::order;
+    use crate::orderbook::{OrderBook, Side};
+
+    // Matcher roto: asigna todo al primero de la cola un tick peor que el nivel.
+    #[derive(Debug)]
+    struct TradeThrough;
+
+    impl Matcher for TradeThrough {
+        fn allocate(&self, incoming: &Order, remaining: Decimal, level: &PriceLevel) -> Vec<Allocation> {
+            let worse = match incoming.side {
+                Side::Buy => level.price + Decimal::ONE,
+                Side::Sell => level.price - Decimal::ONE,
+            };
+            let (maker, executable) = level.executable_orders().next().expect("el nivel tiene órdenes");
+            vec![Allocation { maker_id: maker.id, price: worse, amount: remaining.min(executable) }]
+        }
+    }
+
+    fn book(protection: bool) -> OrderBook {
+        let mut book = OrderBook::builder().matcher(TradeThrough).trade_through_protection(protection).build();
+        book.add_order(order(1, Side::Sell, dec!(100), dec!(5))).unwrap();
+        book
+    }
+
+    #[test]
+    fn trade_through_allocations_are_discarded() {
+        let mut book = book(true);
+        let trades = book.add_order(order(2, Side::Buy, dec!(105), dec!(2))).unwrap();
+        assert!(trades.is_empty());
+        assert_eq!(book.order(1).map(|o| o.amount), Some(dec!(5)));
+    }
+
+    #[test]
+    fn without_protection_the_matcher_price_stands() {
+        let mut book = book(false);
+        let trades = book.add_order(order(2, Side::Buy, dec!(105), dec!(2))).unwrap();
+        assert_eq!((trades[0].price, trades[0].amount), (dec!(101), dec!(2)));
+    }
+
+    #[test]
+    fn pro_rata_splits_a_level_by_size() {
+        let mut book = OrderBook::builder().matcher(ProRata::new(dec!(1))).build();
+        book.add_order(order(1, Side::Sell, dec!(100), dec!(6))).unwrap();
+        book.add_order(order(2, Side::Sell, dec!(100), dec!(2))).unwrap();
+        let trades = book.add_order(order(3, Side::Buy, dec!(100), dec!(4))).unwrap();
+        assert_eq!(trades.iter().map(|t| (t.maker_id, t.amount)).collect::<Vec<_>>(), [(1, dec!(3)), (2, dec!(1))]);
+    }
+
+    // Makers con estas cantidades en un nivel de 100 y una compra de `incoming`: lo que toca a cada uno.
+    fn pro_rata_fills(lot: Decimal, makers: &[Decimal], incoming: Decimal) -> Vec<(u64, Decimal)> {
+        let mut book = OrderBook::builder().matcher(ProRata::new(lot)).build();
+        for (id, amount) in (1..).zip(makers) {
+            book.add_order(order(id, Side::Sell, dec!(100), *amount)).unwrap();
+        }
+        let trades = book.add_order(order(99, Side::Buy, dec!(100), incoming)).unwrap();
+        trades.iter().map(|t| (t.maker_id, t.amount)).collect()
+    }
+
+    #[test]
+    fn pro_rata_rounds_to_lots_and_hands_out_the_largest_remainders() {
+        // 3.5 / 2.1 / 1.4 -> 3 / 2 / 1, y el lote que sobra va al mayor resto
+        let fills = pro_rata_fills(dec!(1), &[dec!(5), dec!(3), dec!(2)], dec!(7));
+        assert_eq!(fills, [(1, dec!(4)), (2, dec!(2)), (3, dec!(1))]);
+        assert_eq!(fills.iter().map(|(_, amount)| *amount).sum::<Decimal>(), dec!(7));
+
+        // A igual resto manda la cola
+        assert_eq!(pro_rata_fills(dec!(1), &[dec!(1), dec!(1), dec!(1)], dec!(2)), [(1, dec!(1)), (2, dec!(1))]);
+
+        // Lotes de 0.5 con un pico que no es múltiplo del lote
+        let fills = pro_rata_fills(dec!(0.5), &[dec!(3), dec!(3)], dec!(2.2));
+        assert_eq!(fills.iter().map(|(_, amount)| *amount).sum::<Decimal>(), dec!(2.2));
+        assert!(fills.iter().all(|(_, amount)| *amount <= dec!(3)));
+    }
+}
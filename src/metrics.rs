@@ -0,0 +1,74 @@
+// --- MÉTRICAS DEL ENGINE ---
+//
+// Contadores planos para observar el engine sin traer un exporter de
+// Prometheus: cada tarea de símbolo los actualiza con atómicos y
+// `Engine::metrics` toma una foto.
+
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+use crate::error::OrderError;
+
+/// Foto de los contadores del engine.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EngineMetrics {
+    /// Órdenes que llegaron a un libro, aceptadas o rechazadas.
+    pub orders_processed: u64,
+    pub trades: u64,
+    /// Rechazos por variante de `OrderError` (ver `OrderError::kind`).
+    pub rejects: BTreeMap<&'static str, u64>,
+    /// Órdenes en reposo por símbolo.
+    pub resting_orders: BTreeMap<String, usize>,
+    /// Comandos esperando en el inbox de cada símbolo. Si llega a la capacidad
+    /// del inbox, los clientes de ese símbolo quedan esperando (backpressure).
+    pub queue_depth: BTreeMap<String, usize>,
+}
+
+impl EngineMetrics {
+    pub fn total_rejects(&self) -> u64 {
+        self.rejects.values().sum()
+    }
+}
+
+// Contadores compartidos por todas las tareas del engine.
+#[derive(Debug, Default)]
+pub(crate) struct EngineCounters {
+    orders_processed: AtomicU64,
+    trades: AtomicU64,
+    rejects: Mutex<BTreeMap<&'static str, u64>>, // Sólo se toca al rechazar
+}
+
+impl EngineCounters {
+    pub(crate) fn record_accepted(&self, trades: usize) {
+        self.orders_processed.fetch_add(1, Ordering::Relaxed);
+        self.trades.fetch_add(trades as u64, Ordering::Relaxed);
+    }
+
+    // Trades que no vienen de una orden procesada (last look vencido).
+    pub(crate) fn record_trades(&self, trades: usize) {
+        self.trades.fetch_add(trades as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_rejected(&self, error: &OrderError) {
+        self.orders_processed.fetch_add(1, Ordering::Relaxed);
+        let mut rejects = self.rejects.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        *rejects.entry(error.kind()).or_default() += 1;
+    }
+
+    // `shards` da (símbolo, órdenes en reposo, comandos en cola) de cada símbolo.
+    pub(crate) fn snapshot<'a>(&self, shards: impl Iterator<Item = (&'a str, &'a AtomicUsize, usize)>) -> EngineMetrics {
+        let (mut resting_orders, mut queue_depth) = (BTreeMap::new(), BTreeMap::new());
+        for (symbol, resting, queued) in shards {
+            resting_orders.insert(symbol.to_string(), resting.load(Ordering::Relaxed));
+            queue_depth.insert(symbol.to_string(), queued);
+        }
+        EngineMetrics {
+            orders_processed: self.orders_processed.load(Ordering::Relaxed),
+            trades: self.trades.load(Ordering::Relaxed),
+            rejects: self.rejects.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).clone(),
+            resting_orders,
+            queue_depth,
+        }
+    }
+}
@@ -0,0 +1,2212 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::cmp::Ordering;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering as AtomicOrdering};
+use rust_decimal::prelude::*; // Necesario para manejar precios financieros
+use serde::{Deserialize, Serialize};
+
+use crate::clock::Clock;
+use crate::config::{BookConfig, DepthCapPolicy, HiddenPriority, MarketNoLiquidity, MatchingMode, MinRestingPolicy, OrderBookBuilder, PriceImprovement, PriorityTieBreak, SelfTradePrevention};
+use crate::error::OrderError;
+use crate::events::{BookEvent, SequencedEvent};
+use crate::logging::book_log;
+use crate::matcher::Matcher;
+use crate::balances::Balances;
+use crate::positions::Positions;
+use crate::settlement::SettlementHandler;
+use crate::side_map::SideMap;
+
+mod auction;
+mod breaker;
+mod builder;
+mod fill_ratio;
+mod last_look;
+mod peg;
+mod quote;
+mod reserve;
+mod settlement;
+mod simulate;
+mod triggers;
+mod undo;
+
+#[cfg(test)]
+pub(crate) mod tests;
+
+pub use builder::OrderBuilder;
+pub use last_look::PendingTrade;
+pub use simulate::{MatchReason, MatchStep};
+use undo::{Inverse, UndoLog};
+
+// --- ESTRUCTURAS DE DATOS ---
+
+#[derive(Debug, Clone, PartialEq, Eq, Copy, Serialize, Deserialize)]
+pub enum Side {
+    Buy,
+    Sell,
+}
+
+impl Side {
+    pub fn opposite(self) -> Side {
+        match self {
+            Side::Buy => Side::Sell,
+            Side::Sell => Side::Buy,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum OrderType {
+    /// Cruza hasta su precio límite; el resto queda en el libro.
+    #[default]
+    Limit,
+    /// Cruza a cualquier precio (se ignora `price`); el resto se cancela.
+    Market,
+    /// Mercado protegido: deja de barrer cuando el próximo nivel se aleja del
+    /// precio del primer fill más que `max_slippage`. El resto se cancela.
+    MarketWithSlippage { max_slippage: Decimal },
+    /// Mercado por monto: ejecuta hasta que el nocional (`price * amount`) llega
+    /// a `budget`, en moneda cotizada. `amount` sigue siendo el tope en cantidad.
+    /// Cada fill se achica (redondeando al lote) para no pasarse del presupuesto.
+    MarketQuote { budget: Decimal },
+    /// Límite que sólo puede agregar liquidez: si al llegar cruzaría contra el
+    /// mejor precio contrario (incluido un precio igual) se rechaza.
+    PostOnly,
+    /// Market-if-touched: espera fuera del libro hasta que el último precio toque
+    /// `trigger` (una compra cuando baja hasta ahí, una venta cuando sube) y
+    /// entonces sale como `Market`. Sólo `Gtc`.
+    MarketIfTouched { trigger: Decimal },
+    /// All-or-none: límite que sólo descansa en el libro y se ejecuta entera,
+    /// contra una única orden que la complete. Las órdenes que no alcanzan la
+    /// saltean y siguen con las de atrás; si en un nivel que cruza sólo quedan
+    /// AON que no pueden completar, su resto se cancela. Si al llegar cruzaría
+    /// se rechaza, como una post-only.
+    AllOrNone,
+    /// Pegged al mejor precio contrario: una compra descansa `offset` debajo
+    /// del mejor ask y una venta `offset` encima del mejor bid (se ignora
+    /// `price`). Sigue al precio contrario en cada cambio, pero nunca pasa de
+    /// `limit`; sin precio contrario descansa en `limit`. Cada vez que se
+    /// mueve pierde la prioridad.
+    PegToOpposite { offset: Decimal, limit: Decimal },
+}
+
+impl OrderType {
+    pub fn is_market(self) -> bool {
+        matches!(self, OrderType::Market | OrderType::MarketWithSlippage { .. } | OrderType::MarketQuote { .. })
+    }
+}
+
+/// Estado de negociación del libro.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum TradingState {
+    /// Acepta órdenes y cancelaciones.
+    #[default]
+    Open,
+    /// Sólo se aceptan cancelaciones y reducciones de cantidad (parada de
+    /// emergencia, kill switch).
+    CancelOnly,
+    /// No se acepta nada que cambie el libro.
+    Halted,
+}
+
+/// Cuánto de una orden en reposo se publica en el libro.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Visibility {
+    #[default]
+    Visible,
+    /// No aparece en la profundidad pública, pero matchea en su turno como cualquier otra.
+    Hidden,
+    /// Muestra sólo `peak`; al agotarse el pico se repone desde la reserva y la
+    /// orden pasa al final de la cola de su nivel.
+    Iceberg { peak: Decimal },
+}
+
+/// Cuánto vive una orden en el libro.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum TimeInForce {
+    /// Hasta que se ejecute o se cancele.
+    #[default]
+    Gtc,
+    /// Se cancela sola cuando el reloj del libro llega a `expire_at`.
+    Gtt { expire_at: u64 },
+    /// Se cancela al cierre de la sesión (`BookConfig::session_end`).
+    Day,
+    /// Immediate-or-cancel: ejecuta lo que pueda al llegar y cancela el resto.
+    Ioc,
+    /// O se ejecuta entera al llegar, o queda entera en reposo como `Gtc`:
+    /// nunca un fill parcial seguido de reposo. Si cruza pero no alcanza para
+    /// completarse, se cancela entera (en reposo dejaría el libro cruzado).
+    FokOrRest,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Order {
+    pub id: u64,
+    pub price: Decimal,
+    pub amount: Decimal,
+    pub side: Side,
+    pub timestamp: u64,
+    pub account: u64, // Cuenta dueña de la orden (para self-trade prevention)
+    #[serde(default)]
+    pub subaccount: Option<u64>, // Con `StpScope::Subaccount`, afina el self-trade prevention
+    #[serde(default)]
+    pub min_fill: Option<Decimal>, // Mientras es taker, no ejecuta fills más chicos (salvo para completarse)
+    #[serde(default)]
+    pub price_cap: Option<Decimal>, // Tope absoluto de un barrido: no ejecuta más caro (compra) o más barato (venta)
+    #[serde(default)]
+    pub cancel_at_fill_ratio: Option<Decimal>, // Fracción del tamaño original (0.8 = 80%) a partir de la cual se cancela el resto
+    pub client_order_id: Option<String>, // Id propio del cliente: se devuelve tal cual, no afecta el matching
+    #[serde(default)]
+    pub strategy_id: Option<String>, // Estrategia que la envió: viaja en sus trades para atribuir PnL
+    #[serde(default)]
+    pub venue_tag: Option<String>, // Etiqueta de ruteo del cliente: viaja en sus trades, no afecta el matching
+    pub order_type: OrderType,
+    pub visibility: Visibility,
+    #[serde(default)] // Las grabaciones viejas no lo tienen
+    pub time_in_force: TimeInForce,
+}
+
+/// Ejecución entre una orden entrante (taker) y una orden del libro (maker).
+/// El precio es el de la orden que estaba descansando en el libro, salvo con
+/// `PriceImprovement::Midpoint`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Trade {
+    /// Id único y creciente del fill (ver `TradeIdCounter`).
+    #[serde(default)] // Las grabaciones viejas no lo tienen
+    pub trade_id: u64,
+    pub maker_id: u64,
+    pub taker_id: u64,
+    pub price: Decimal,
+    pub amount: Decimal,
+    pub timestamp: u64,
+    /// Lado del taker: `Buy` si la orden entrante compraba.
+    pub aggressor: Side,
+    /// Mid del libro justo antes del fill (ver `OrderBook::mid_price`), para
+    /// medir el spread efectivo de cada ejecución.
+    #[serde(default)]
+    pub prevailing_mid: Option<Decimal>,
+    /// `strategy_id` y `venue_tag` de la orden taker y de la maker, tal como
+    /// llegaron, para agrupar el PnL por estrategia.
+    #[serde(default)]
+    pub taker_strategy_id: Option<String>,
+    #[serde(default)]
+    pub maker_strategy_id: Option<String>,
+    #[serde(default)]
+    pub taker_venue_tag: Option<String>,
+    #[serde(default)]
+    pub maker_venue_tag: Option<String>,
+}
+
+impl Trade {
+    /// Valor del trade (`price * amount`). Con precios y cantidades extremos el
+    /// producto no entra en un `Decimal` (96 bits de mantisa) y en vez de entrar
+    /// en pánico devuelve `Overflow`.
+    pub fn notional(&self) -> Result<Decimal, OrderError> {
+        self.price.checked_mul(self.amount).ok_or(OrderError::Overflow("nocional del trade"))
+    }
+
+    /// Comisión sobre el nocional, con `rate` como fracción (0.001 = 10 bps).
+    pub fn fee(&self, rate: Decimal) -> Result<Decimal, OrderError> {
+        self.notional()?.checked_mul(rate).ok_or(OrderError::Overflow("comisión del trade"))
+    }
+}
+
+/// Respuesta a una orden enviada: cuánto se ejecutó, cuánto quedó en el libro
+/// (o encolado, en modo batch), cuánto se canceló y cuánto cruzó pero espera
+/// el last look del maker. Las cuatro cantidades suman siempre la cantidad
+/// enviada.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OrderAck {
+    pub order_id: u64,
+    pub client_order_id: Option<String>,
+    pub filled_qty: Decimal,
+    pub resting_qty: Decimal,
+    pub cancelled_qty: Decimal,
+    /// Fills pendientes de last look: no están en `trades` ni en `filled_qty`
+    /// hasta que el maker los acepta (ver `OrderBook::pending_trades`).
+    pub pending_qty: Decimal,
+    /// Trades de la orden, en orden de ejecución.
+    pub trades: Vec<Trade>,
+}
+
+impl OrderAck {
+    /// Calidad de ejecución de la orden, a partir de sus trades.
+    pub fn summary(&self) -> ExecSummary {
+        ExecSummary::from_trades(self.order_id, &self.trades)
+    }
+}
+
+/// Resumen de cómo se ejecutó una orden agresiva.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct ExecSummary {
+    /// Niveles de precio distintos contra los que ejecutó.
+    pub levels: usize,
+    /// Makers distintos contra los que ejecutó.
+    pub makers: usize,
+    pub filled_qty: Decimal,
+    /// Precio promedio ponderado por cantidad (`None` sin fills).
+    pub avg_price: Option<Decimal>,
+}
+
+impl ExecSummary {
+    /// Resumen de los trades en los que `taker_id` fue el agresor; el resto se ignora.
+    pub fn from_trades<'a>(taker_id: u64, trades: impl IntoIterator<Item = &'a Trade>) -> Self {
+        let (mut prices, mut makers) = (BTreeSet::new(), BTreeSet::new());
+        let (mut filled_qty, mut notional) = (Decimal::zero(), Decimal::zero());
+        for trade in trades.into_iter().filter(|t| t.taker_id == taker_id) {
+            prices.insert(trade.price);
+            makers.insert(trade.maker_id);
+            filled_qty += trade.amount;
+            notional += trade.price * trade.amount;
+        }
+        Self {
+            levels: prices.len(),
+            makers: makers.len(),
+            filled_qty,
+            avg_price: (!filled_qty.is_zero()).then(|| notional / filled_qty),
+        }
+    }
+}
+
+/// Orden que el libro rechazó al llegar, con el motivo (ver `OrderBook::recent_rejects`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RejectedOrder {
+    pub order: Order,
+    pub reason: OrderError,
+    /// Hora del libro al rechazarla (la del reloj o, sin reloj, el mayor timestamp recibido).
+    pub timestamp: u64,
+}
+
+/// Contador de ids de trade. Cada libro tiene el suyo (ids por símbolo); si
+/// varios libros comparten un clon del mismo contador, los ids son crecientes
+/// entre todos ellos.
+#[derive(Debug, Clone, Default)]
+pub struct TradeIdCounter(Arc<AtomicU64>);
+
+impl TradeIdCounter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reserva el próximo id (el primero es 1).
+    fn next(&self) -> u64 {
+        self.0.fetch_add(1, AtomicOrdering::Relaxed) + 1
+    }
+
+    /// Contador que sigue numerando después de `last` (p. ej. al restaurar).
+    pub fn starting_after(last: u64) -> Self {
+        Self(Arc::new(AtomicU64::new(last)))
+    }
+
+    /// Último id entregado (0 si todavía no hubo trades).
+    pub fn last(&self) -> u64 {
+        self.0.load(AtomicOrdering::Relaxed)
+    }
+}
+
+const DAY_MILLIS: u64 = 24 * 60 * 60 * 1000;
+
+// Primer cierre de sesión estrictamente posterior a `now`. `session_end` es la
+// hora del cierre dentro del día, en ms desde la medianoche (UTC del reloj).
+fn next_session_end(now: u64, session_end: u64) -> u64 {
+    let close = now - now % DAY_MILLIS + session_end % DAY_MILLIS;
+    if close > now { close } else { close + DAY_MILLIS }
+}
+
+// `value` con exactamente `scale` decimales, o `None` si eso cambia su valor.
+fn rescaled(value: Decimal, scale: u32) -> Option<Decimal> {
+    let mut normalized = value;
+    normalized.rescale(scale);
+    (normalized == value).then_some(normalized)
+}
+
+// Precio de un cruce con mejora `Midpoint`: el medio entre el límite del taker
+// y el nivel. Con tick, se redondea hacia el nivel para no salirse de la
+// grilla: la mejora del taker nunca pasa de la mitad.
+fn midpoint_price(limit: Decimal, level_price: Decimal, side: Side, tick: Option<Decimal>) -> Decimal {
+    let mid = (limit + level_price) / Decimal::TWO;
+    match tick {
+        Some(tick) if tick > Decimal::zero() => match side {
+            Side::Buy => (mid / tick).floor() * tick,
+            Side::Sell => (mid / tick).ceil() * tick,
+        },
+        _ => mid,
+    }
+}
+
+// --- LÓGICA DE ORDENAMIENTO (EL MOTOR MATEMÁTICO) ---
+
+// Orden total de prioridad: precio, después timestamp y, a igual timestamp,
+// id. Como los ids son únicos, dos órdenes distintas nunca dan `Equal` y se
+// pueden usar como clave de un `BTreeMap`/`BTreeSet` sin pisarse.
+impl Ord for Order {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let by_price = match self.side {
+            // Compras: Prioridad al precio MÁS ALTO
+            Side::Buy => other.price.cmp(&self.price),
+            // Ventas: Prioridad al precio MÁS BAJO
+            Side::Sell => self.price.cmp(&other.price),
+        };
+        // Desempate por tiempo (FIFO) y, a igual timestamp, por id
+        by_price
+            .then_with(|| self.timestamp.cmp(&other.timestamp))
+            .then_with(|| self.id.cmp(&other.id))
+    }
+}
+
+impl PartialOrd for Order {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// --- NIVELES DE PRECIO ---
+
+/// Una orden en reposo junto con la porción que muestra en el libro.
+#[derive(Debug, Clone)]
+pub(crate) struct RestingOrder {
+    pub(crate) order: Order,
+    pub(crate) shown: Decimal, // Pico vigente de un iceberg; en el resto no se usa
+    pub(crate) arrival_seq: u64, // Orden de llegada al libro, asignado por el libro
+}
+
+// La secuencia de llegada no cuenta: ya está reflejada en la posición en la cola.
+impl PartialEq for RestingOrder {
+    fn eq(&self, other: &Self) -> bool {
+        self.order == other.order && self.shown == other.shown
+    }
+}
+
+impl Eq for RestingOrder {}
+
+impl RestingOrder {
+    fn new(order: Order, arrival_seq: u64) -> Self {
+        let shown = match order.visibility {
+            Visibility::Iceberg { peak } => peak.min(order.amount),
+            _ => order.amount,
+        };
+        Self { order, shown, arrival_seq }
+    }
+
+    // Clave de prioridad dentro del nivel: la menor va primero.
+    fn priority(&self, tie_break: PriorityTieBreak) -> (u64, u64) {
+        match tie_break {
+            PriorityTieBreak::ArrivalSequence => (self.arrival_seq, 0),
+            PriorityTieBreak::Timestamp => (self.order.timestamp, self.arrival_seq),
+        }
+    }
+
+    /// Cantidad publicada en la profundidad pública.
+    pub(crate) fn visible(&self) -> Decimal {
+        match self.order.visibility {
+            Visibility::Visible => self.order.amount,
+            Visibility::Hidden => Decimal::zero(),
+            Visibility::Iceberg { .. } => self.shown,
+        }
+    }
+
+    // Cantidad que se puede ejecutar contra esta orden antes de reponer el pico
+    fn executable(&self) -> Decimal {
+        match self.order.visibility {
+            Visibility::Iceberg { .. } => self.shown,
+            _ => self.order.amount,
+        }
+    }
+
+    // Descuenta un fill. Un fill mayor que lo ejecutable dejaría la orden con
+    // cantidad negativa: se rechaza sin tocar nada.
+    fn fill(&mut self, amount: Decimal) -> Result<(), OrderError> {
+        let available = self.executable();
+        debug_assert!(amount <= available, "fill de {amount} sobre #{} con {available} ejecutable", self.order.id);
+        if amount > available {
+            return Err(OrderError::Internal(format!("fill de {amount} sobre #{} con {available} ejecutable", self.order.id)));
+        }
+        self.order.amount -= amount;
+        self.shown = (self.shown - amount).max(Decimal::zero());
+        Ok(())
+    }
+
+    // Achica la orden sin ejecutarla (modificación o decremento por self-trade)
+    fn reduce_to(&mut self, amount: Decimal) {
+        self.order.amount = amount;
+        self.shown = self.shown.min(amount);
+    }
+
+    // Un iceberg con el pico agotado y reserva pendiente debe reponerse
+    fn needs_refill(&self) -> bool {
+        matches!(self.order.visibility, Visibility::Iceberg { .. })
+            && self.shown.is_zero()
+            && self.order.amount > Decimal::zero()
+    }
+
+    fn refill(&mut self) {
+        if let Visibility::Iceberg { peak } = self.order.visibility {
+            self.shown = peak.min(self.order.amount);
+        }
+    }
+}
+
+/// Todas las órdenes en reposo a un mismo precio, en orden de llegada (FIFO).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PriceLevel {
+    pub price: Decimal,
+    pub(crate) orders: VecDeque<RestingOrder>,
+    pub(crate) total: Decimal,
+}
+
+impl PriceLevel {
+    fn new(price: Decimal) -> Self {
+        Self { price, orders: VecDeque::new(), total: Decimal::zero() }
+    }
+
+    /// Cantidad total del nivel, incluyendo órdenes ocultas y reservas de icebergs.
+    pub fn total(&self) -> Decimal {
+        self.total
+    }
+
+    /// Cantidad que el nivel muestra en la profundidad pública.
+    pub fn visible_total(&self) -> Decimal {
+        self.orders.iter().map(RestingOrder::visible).sum()
+    }
+
+    pub fn orders(&self) -> impl Iterator<Item = &Order> {
+        self.orders.iter().map(|o| &o.order)
+    }
+
+    // Tramo de la cola [inicio, fin) en el que compite `resting`. Con
+    // `BehindVisible` la cola queda partida: visibles (e icebergs) y detrás las ocultas.
+    fn segment(&self, resting: &RestingOrder, hidden_priority: HiddenPriority) -> (usize, usize) {
+        let len = self.orders.len();
+        match hidden_priority {
+            HiddenPriority::TimeOrder => (0, len),
+            HiddenPriority::BehindVisible => {
+                // Las ocultas suelen ser pocas: se busca el corte desde atrás
+                let first_hidden = self.orders.iter().rposition(|o| o.order.visibility != Visibility::Hidden).map_or(0, |pos| pos + 1);
+                if resting.order.visibility == Visibility::Hidden { (first_hidden, len) } else { (0, first_hidden) }
+            }
+        }
+    }
+
+    // Vuelve a encolar una orden al final de su tramo (un iceberg que repone el pico).
+    fn requeue(&mut self, resting: RestingOrder, hidden_priority: HiddenPriority) {
+        let (_, end) = self.segment(&resting, hidden_priority);
+        self.orders.insert(end, resting);
+    }
+
+    /// Órdenes del nivel en FIFO con la cantidad que se les puede ejecutar
+    /// ahora (en un iceberg, sólo el pico vigente).
+    pub fn executable_orders(&self) -> impl Iterator<Item = (&Order, Decimal)> {
+        self.orders.iter().map(|o| (&o.order, o.executable()))
+    }
+}
+
+/// Un lado del libro. Los niveles se indexan por una "clave de prioridad"
+/// (precio en asks, -precio en bids) para que el primer nivel del mapa sea
+/// siempre el mejor precio, sin importar el lado.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct BookSide {
+    side: Side,
+    levels: BTreeMap<Decimal, PriceLevel>,
+    volume: Decimal, // Suma de los totales de los niveles, mantenida al vuelo
+}
+
+impl BookSide {
+    fn new(side: Side) -> Self {
+        Self { side, levels: BTreeMap::new(), volume: Decimal::zero() }
+    }
+
+    fn key(&self, price: Decimal) -> Decimal {
+        match self.side {
+            Side::Buy => -price,
+            Side::Sell => price,
+        }
+    }
+
+    pub(crate) fn best(&self) -> Option<&PriceLevel> {
+        self.levels.values().next()
+    }
+
+    pub(crate) fn best_mut(&mut self) -> Option<&mut PriceLevel> {
+        self.levels.values_mut().next()
+    }
+
+    pub(crate) fn level(&self, price: Decimal) -> Option<&PriceLevel> {
+        self.levels.get(&self.key(price))
+    }
+
+    /// Niveles en orden de prioridad (mejor precio primero).
+    pub(crate) fn levels(&self) -> impl Iterator<Item = &PriceLevel> {
+        self.levels.values()
+    }
+
+    /// Encola la orden al final de su nivel (o de su tramo, según `hidden_priority`)
+    /// y devuelve el nuevo total visible del nivel.
+    fn push(&mut self, order: Order, arrival_seq: u64, tie_break: PriorityTieBreak, hidden_priority: HiddenPriority) -> Decimal {
+        let key = self.key(order.price);
+        let level = self.levels.entry(key).or_insert_with(|| PriceLevel::new(order.price));
+        level.total += order.amount;
+        self.volume += order.amount;
+        let resting = RestingOrder::new(order, arrival_seq);
+        let (start, end) = level.segment(&resting, hidden_priority);
+        let pos = match tie_break {
+            PriorityTieBreak::ArrivalSequence => end,
+            PriorityTieBreak::Timestamp => {
+                // Detrás de todas las del tramo con timestamp menor o igual (a igual
+                // timestamp, por llegada). Casi siempre es el final del tramo.
+                let timestamp = resting.order.timestamp;
+                level.orders.range(start..end).rposition(|o| o.order.timestamp <= timestamp).map_or(start, |pos| start + pos + 1)
+            }
+        };
+        level.orders.insert(pos, resting);
+        level.visible_total()
+    }
+
+    // Encola una orden ya armada (con su pico vigente), tal como estaba guardada.
+    fn push_resting(&mut self, resting: RestingOrder) {
+        let key = self.key(resting.order.price);
+        let level = self.levels.entry(key).or_insert_with(|| PriceLevel::new(resting.order.price));
+        level.total += resting.order.amount;
+        self.volume += resting.order.amount;
+        level.orders.push_back(resting);
+    }
+
+    /// Quita una orden de su nivel. Devuelve la orden, el lugar que ocupaba en
+    /// la cola y el total visible restante del nivel.
+    fn remove(&mut self, id: u64, price: Decimal) -> Option<(RestingOrder, usize, Decimal)> {
+        let key = self.key(price);
+        let level = self.levels.get_mut(&key)?;
+        let pos = level.orders.iter().position(|o| o.order.id == id)?;
+        let resting = level.orders.remove(pos)?;
+        level.total -= resting.order.amount;
+        self.volume -= resting.order.amount;
+        let remaining = level.visible_total();
+        if level.orders.is_empty() {
+            self.levels.remove(&key);
+        }
+        Some((resting, pos, remaining))
+    }
+
+    // Devuelve una orden al lugar `pos` de la cola de su nivel. Devuelve el total visible del nivel.
+    fn insert_at(&mut self, resting: RestingOrder, pos: usize) -> Decimal {
+        let key = self.key(resting.order.price);
+        let level = self.levels.entry(key).or_insert_with(|| PriceLevel::new(resting.order.price));
+        level.total += resting.order.amount;
+        self.volume += resting.order.amount;
+        level.orders.insert(pos.min(level.orders.len()), resting);
+        level.visible_total()
+    }
+
+    /// Cantidad de niveles de precio del lado.
+    fn level_count(&self) -> usize {
+        self.levels.len()
+    }
+
+    /// `true` si un nivel a `price` quedaría detrás del peor nivel actual
+    /// (o si el lado está vacío).
+    fn is_beyond_worst(&self, price: Decimal) -> bool {
+        self.levels.last_key_value().is_none_or(|(worst, _)| self.key(price) > *worst)
+    }
+
+    fn pop_worst(&mut self) -> Option<PriceLevel> {
+        let (_, level) = self.levels.pop_last()?;
+        self.volume -= level.total;
+        Some(level)
+    }
+
+    /// Cantidad total en reposo del lado, incluyendo ocultas y reservas.
+    pub(crate) fn volume(&self) -> Decimal {
+        self.volume
+    }
+
+    fn remove_best_if_empty(&mut self) {
+        if let Some(entry) = self.levels.first_entry()
+            && entry.get().orders.is_empty()
+        {
+            entry.remove();
+        }
+    }
+}
+
+// --- ÍNDICES ---
+
+/// Índices de las órdenes en reposo, para cancelar/modificar en O(1).
+#[derive(Debug, Default)]
+struct OrderIndex {
+    by_id: HashMap<u64, (Side, Decimal)>, // id -> (lado, precio)
+    by_client_id: HashMap<String, u64>,
+    by_account: HashMap<u64, BTreeSet<u64>>, // cuenta -> ids en reposo
+}
+
+impl OrderIndex {
+    fn get(&self, id: u64) -> Option<(Side, Decimal)> {
+        self.by_id.get(&id).copied()
+    }
+
+    fn id_for_client(&self, client_order_id: &str) -> Option<u64> {
+        self.by_client_id.get(client_order_id).copied()
+    }
+
+    fn ids_for_account(&self, account: u64) -> impl Iterator<Item = u64> + '_ {
+        self.by_account.get(&account).into_iter().flatten().copied()
+    }
+
+    fn insert(&mut self, order: &Order) {
+        self.by_id.insert(order.id, (order.side, order.price));
+        self.by_account.entry(order.account).or_default().insert(order.id);
+        if let Some(client_id) = &order.client_order_id {
+            self.by_client_id.insert(client_id.clone(), order.id);
+        }
+    }
+
+    fn remove(&mut self, order: &Order) {
+        self.by_id.remove(&order.id);
+        if let Some(ids) = self.by_account.get_mut(&order.account) {
+            ids.remove(&order.id);
+            if ids.is_empty() {
+                self.by_account.remove(&order.account);
+            }
+        }
+        if let Some(client_id) = &order.client_order_id
+            && self.by_client_id.get(client_id) == Some(&order.id)
+        {
+            self.by_client_id.remove(client_id);
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.by_id.len()
+    }
+}
+
+// --- EL LIBRO DE ÓRDENES ---
+
+#[derive(Debug)]
+pub struct OrderBook {
+    pub(crate) sides: SideMap<BookSide>, // Compras y ventas
+    index: OrderIndex,
+    sequence: u64,
+    events: Option<Vec<SequencedEvent>>,
+    config: BookConfig,
+    reference_price: Option<Decimal>, // Respaldo para banda/mid cuando el libro no tiene dos puntas
+    trade_ids: TradeIdCounter,
+    last_price: Option<Decimal>, // Precio del último trade
+    matcher: Option<Arc<dyn Matcher>>, // `None` = FIFO precio-tiempo incorporado
+    trading_state: TradingState,
+    pending: Vec<Order>, // Órdenes esperando el próximo tick (modo batch)
+    triggers: Vec<Order>, // Órdenes MIT esperando su disparador, en orden de llegada
+    clock: Option<Arc<dyn Clock>>, // `None` = se respetan los timestamps de las órdenes
+    expiries: BTreeSet<(u64, u64)>, // (vencimiento, id) de las órdenes Gtt
+    day_orders: BTreeSet<u64>, // Ids de las órdenes Day (puede haber ya ejecutadas)
+    next_session_end: Option<u64>, // Próximo cierre según el reloj; se calcula al primer uso
+    recent_trades: VecDeque<Trade>, // Últimos `config.recent_trades` trades, el más viejo primero
+    recent_rejects: VecDeque<RejectedOrder>, // Últimos `config.recent_rejects` rechazos, el más viejo primero
+    arrival_seq: u64, // Última secuencia de llegada asignada a una orden en reposo
+    positions: Positions, // Posición neta de cada cuenta según sus trades en este libro
+    last_order_id: u64, // Mayor id de orden recibido; las cotizaciones numeran desde acá
+    last_timestamp: u64, // Mayor timestamp recibido, para sellar cotizaciones sin reloj
+    seen_orders: bool, // Alguna vez aceptó (o restauró) una orden
+    undo_log: UndoLog, // Inversos de los últimos comandos, el más reciente al final
+    fast_cancels: u64, // Cancelaciones anteriores a `min_resting_time` (con `MinRestingPolicy::Flag`)
+    pegs: BTreeSet<u64>, // Ids de las órdenes `PegToOpposite` (puede haber ya ejecutadas)
+    last_update: Option<u64>, // Hora del libro en el último evento emitido
+    balances: Balances, // Saldos y reservas por cuenta (con `enforce_balances`)
+    recent_prices: VecDeque<(u64, Decimal)>, // (timestamp, precio) de los trades en la ventana del circuit breaker
+    breaker_until: Option<u64>, // Fin del corte del circuit breaker en curso
+    settlement: Option<Arc<dyn SettlementHandler>>, // `None` = los trades no se liquidan afuera
+    unsettled: Vec<Trade>, // Trades publicados todavía sin entregar al handler, en orden
+    unsettled_since: Option<u64>, // Hora del libro al entrar el primero de `unsettled`
+    pending_trades: Vec<PendingTrade>, // Fills esperando el last look del maker, el más viejo primero
+    fill_progress: BTreeMap<u64, (Decimal, Decimal)>, // (tamaño original, ejecutado) de las órdenes con `cancel_at_fill_ratio`
+}
+
+// Dos libros son iguales si tienen las mismas órdenes en reposo, en el mismo
+// orden de prioridad. La secuencia y el buffer de eventos no cuentan.
+impl PartialEq for OrderBook {
+    fn eq(&self, other: &Self) -> bool {
+        self.sides == other.sides
+    }
+}
+
+impl Eq for OrderBook {}
+
+impl Default for OrderBook {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl OrderBook {
+    pub fn new() -> Self {
+        Self::with_config(BookConfig::default())
+    }
+
+    pub fn with_config(config: BookConfig) -> Self {
+        Self {
+            sides: SideMap::from_fn(BookSide::new),
+            index: OrderIndex::default(),
+            sequence: 0,
+            events: None,
+            config,
+            reference_price: None,
+            trade_ids: TradeIdCounter::new(),
+            last_price: None,
+            matcher: None,
+            trading_state: TradingState::Open,
+            pending: Vec::new(),
+            triggers: Vec::new(),
+            clock: None,
+            expiries: BTreeSet::new(),
+            day_orders: BTreeSet::new(),
+            next_session_end: None,
+            recent_trades: VecDeque::new(),
+            recent_rejects: VecDeque::new(),
+            arrival_seq: 0,
+            positions: Positions::new(),
+            last_order_id: 0,
+            last_timestamp: 0,
+            seen_orders: false,
+            undo_log: UndoLog::new(),
+            fast_cancels: 0,
+            pegs: BTreeSet::new(),
+            last_update: None,
+            balances: Balances::new(),
+            recent_prices: VecDeque::new(),
+            breaker_until: None,
+            settlement: None,
+            unsettled: Vec::new(),
+            unsettled_since: None,
+            pending_trades: Vec::new(),
+            fill_progress: BTreeMap::new(),
+        }
+    }
+
+    pub fn builder() -> OrderBookBuilder {
+        OrderBookBuilder::default()
+    }
+
+    pub fn config(&self) -> &BookConfig {
+        &self.config
+    }
+
+    pub fn trading_state(&self) -> TradingState {
+        self.trading_state
+    }
+
+    /// Cambia el estado de negociación. Si cambia, queda registrado como evento.
+    pub fn set_trading_state(&mut self, state: TradingState) {
+        if self.trading_state == state {
+            return;
+        }
+        book_log!("   🚦 Estado de negociación: {:?} -> {:?}", self.trading_state, state);
+        self.trading_state = state;
+        self.emit(BookEvent::TradingStateChanged { state });
+    }
+
+    /// Reemplaza el reparto FIFO dentro de cada nivel por un matcher a medida.
+    pub fn set_matcher(&mut self, matcher: Arc<dyn Matcher>) {
+        self.matcher = Some(matcher);
+    }
+
+    /// Reloj del libro: sella los timestamps de las órdenes entrantes y decide
+    /// cuándo vence una orden `Gtt`.
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.clock = Some(clock);
+    }
+
+    fn now(&self) -> Option<u64> {
+        self.clock.as_ref().map(|clock| clock.now())
+    }
+
+    // Hora del libro: la del reloj o, sin reloj, el mayor timestamp recibido.
+    pub(crate) fn current_time(&self) -> u64 {
+        self.now().unwrap_or(self.last_timestamp)
+    }
+
+    // `true` si el libro aceptó alguna orden desde que se creó (o restauró).
+    pub(crate) fn has_seen_orders(&self) -> bool {
+        self.seen_orders
+    }
+
+    /// Reemplaza el contador de ids de trade, p. ej. por uno compartido entre símbolos.
+    pub fn set_trade_id_counter(&mut self, counter: TradeIdCounter) {
+        self.trade_ids = counter;
+    }
+
+    pub fn trade_id_counter(&self) -> &TradeIdCounter {
+        &self.trade_ids
+    }
+
+    /// Reconstruye un libro a partir de su estado guardado. Las órdenes entran
+    /// directo a su nivel, en el orden dado, sin pasar por el matching ni emitir eventos.
+    pub(crate) fn restore(
+        config: BookConfig,
+        sequence: u64,
+        reference_price: Option<Decimal>,
+        trade_ids: TradeIdCounter,
+        orders: impl IntoIterator<Item = RestingOrder>,
+    ) -> Self {
+        let mut book = Self::with_config(config);
+        for resting in orders {
+            book.index.insert(&resting.order);
+            book.track_time_in_force(&resting.order);
+            book.track_fill_ratio(&resting.order);
+            book.arrival_seq = book.arrival_seq.max(resting.arrival_seq);
+            book.last_order_id = book.last_order_id.max(resting.order.id);
+            book.last_timestamp = book.last_timestamp.max(resting.order.timestamp);
+            book.seen_orders = true;
+            if let OrderType::PegToOpposite { .. } = resting.order.order_type {
+                book.pegs.insert(resting.order.id);
+            }
+            book.side_mut(resting.order.side).push_resting(resting);
+        }
+        book.sequence = sequence;
+        book.reference_price = reference_price;
+        book.trade_ids = trade_ids;
+        book
+    }
+
+    // Estado de negociación guardado; a diferencia de `set_trading_state` no emite evento.
+    pub(crate) fn restore_trading_state(&mut self, state: TradingState) {
+        self.trading_state = state;
+    }
+
+    // Precio del último trade guardado: lo usan el touch y los disparadores MIT.
+    // Un libro que operó publica su touch aunque hoy esté vacío.
+    pub(crate) fn restore_last_price(&mut self, last_price: Option<Decimal>) {
+        self.last_price = last_price;
+        self.seen_orders |= last_price.is_some();
+    }
+
+    // Posiciones guardadas; se cargan tal cual, sin tocar el libro.
+    pub(crate) fn restore_positions(&mut self, positions: impl IntoIterator<Item = (u64, Decimal)>) {
+        for (account, position) in positions {
+            let side = if position > Decimal::zero() { Side::Buy } else { Side::Sell };
+            self.positions.record(account, side, position.abs());
+        }
+    }
+
+    // Saldos y reservas guardados.
+    pub(crate) fn restore_balances(&mut self, balances: Balances) {
+        self.balances = balances;
+    }
+
+    // Progreso de las órdenes con `cancel_at_fill_ratio`. Las que no lo traen
+    // (snapshot binario o viejo) cuentan desde lo que les quedaba.
+    pub(crate) fn restore_fill_progress(&mut self, progress: BTreeMap<u64, (Decimal, Decimal)>) {
+        self.fill_progress.extend(progress);
+    }
+
+    pub(crate) fn fill_progress(&self) -> &BTreeMap<u64, (Decimal, Decimal)> {
+        &self.fill_progress
+    }
+
+    // Órdenes MIT guardadas, en su orden de llegada.
+    pub(crate) fn restore_triggers(&mut self, triggers: Vec<Order>) {
+        for order in &triggers {
+            self.last_order_id = self.last_order_id.max(order.id);
+        }
+        self.triggers = triggers;
+    }
+
+    // Órdenes en reposo con su pico vigente: bids y luego asks, en prioridad.
+    pub(crate) fn resting_orders(&self) -> impl Iterator<Item = &RestingOrder> {
+        self.sides.iter().flat_map(|(_, side)| side.levels()).flat_map(|level| level.orders.iter())
+    }
+
+    // --- EVENTOS ---
+
+    /// Activa el buffer de eventos. Quien lo active debe vaciarlo con `drain_events`.
+    pub fn enable_events(&mut self) {
+        self.events.get_or_insert_with(Vec::new);
+    }
+
+    pub fn drain_events(&mut self) -> Vec<SequencedEvent> {
+        self.events.as_mut().map(std::mem::take).unwrap_or_default()
+    }
+
+    /// Número de secuencia del último evento generado por el libro.
+    pub fn sequence(&self) -> u64 {
+        self.sequence
+    }
+
+    /// Hora del libro en la última modificación (alta, cancelación,
+    /// modificación, trade o cambio de estado), o `None` si nunca cambió.
+    pub fn last_update_ts(&self) -> Option<u64> {
+        self.last_update
+    }
+
+    /// `true` si pasaron más de `max_age` desde la última modificación (o si
+    /// nunca la hubo). Sin reloj, la hora del libro sólo avanza con los
+    /// timestamps de las órdenes, así que un libro que no recibe nada no envejece.
+    pub fn is_stale(&self, max_age: u64) -> bool {
+        self.last_update.is_none_or(|ts| self.current_time().saturating_sub(ts) > max_age)
+    }
+
+    fn emit(&mut self, event: BookEvent) {
+        self.sequence += 1;
+        self.last_update = Some(self.current_time());
+        self.settle_event(&event);
+        // Todos los trades pasan por acá, vengan del matching continuo o de una subasta
+        if let BookEvent::Trade { trade } = &event
+            && self.config.recent_trades > 0
+        {
+            if self.recent_trades.len() == self.config.recent_trades {
+                self.recent_trades.pop_front();
+            }
+            self.recent_trades.push_back(trade.clone());
+        }
+        if let BookEvent::Trade { trade } = &event {
+            self.buffer_settlement(trade);
+        }
+        if let Some(events) = self.events.as_mut() {
+            events.push(SequencedEvent { seq: self.sequence, event });
+        }
+    }
+
+    // Publica el nuevo total visible de un nivel, con el precio en formato de display.
+    fn emit_level(&mut self, side: Side, price: Decimal, amount: Decimal) {
+        let price = self.display_price(price);
+        self.emit(BookEvent::LevelDelta { side, price, amount });
+    }
+
+    /// Reaplica un evento de comando (alta, cancelación, modificación) y devuelve
+    /// los trades que generó. Los eventos derivados (trades, deltas) se ignoran:
+    /// se regeneran solos.
+    pub fn apply(&mut self, event: &BookEvent) -> Vec<Trade> {
+        match event {
+            BookEvent::OrderAdded { order } => self.add_order(order.clone()).unwrap_or_default(),
+            // En el journal sólo quedan las cancelaciones que pasaron el control de tiempo mínimo.
+            // Las del libro se repiten como tales, sin entrada de undo, igual que en vivo
+            BookEvent::OrderCancelled { order, by_book: false } => { self.cancel_unchecked(order.id); Vec::new() },
+            BookEvent::OrderCancelled { order, by_book: true } => { self.cancel_by_book(order.id); Vec::new() },
+            BookEvent::OrderModified { id, price, amount } => self.modify_order(*id, *price, *amount).unwrap_or_default(),
+            BookEvent::TradingStateChanged { state } => { self.set_trading_state(*state); Vec::new() },
+            BookEvent::AuctionUncross => self.uncross(),
+            BookEvent::Undone => { let _ = self.undo_last(); Vec::new() },
+            BookEvent::Deposit { account, asset, amount } => { let _ = self.deposit(*account, *asset, *amount); Vec::new() },
+            BookEvent::LastLookDecision { trade_id, accepted: true } => self.accept_trade(*trade_id).into_iter().collect(),
+            BookEvent::LastLookDecision { trade_id, accepted: false } => { self.reject_trade(*trade_id); Vec::new() },
+            BookEvent::Trade { .. } | BookEvent::TradePending { .. } | BookEvent::LevelDelta { .. } => Vec::new(),
+        }
+    }
+
+    // --- CONSULTAS ---
+
+    fn side(&self, side: Side) -> &BookSide {
+        self.sides.get(side)
+    }
+
+    fn side_mut(&mut self, side: Side) -> &mut BookSide {
+        self.sides.get_mut(side)
+    }
+
+    // Cantidad total en reposo, de los dos lados.
+    fn resting_volume(&self) -> Decimal {
+        self.sides.iter().map(|(_, side)| side.volume).sum()
+    }
+
+    pub fn best_bid(&self) -> Option<Decimal> {
+        self.side(Side::Buy).best().map(|l| l.price)
+    }
+
+    pub fn best_ask(&self) -> Option<Decimal> {
+        self.side(Side::Sell).best().map(|l| l.price)
+    }
+
+    /// Punto medio entre best bid y best ask. Sin mercado de dos puntas se usa
+    /// el precio de referencia (ver `set_reference_price`).
+    pub fn mid_price(&self) -> Option<Decimal> {
+        match (self.best_bid(), self.best_ask()) {
+            (Some(bid), Some(ask)) => Some((bid + ask) / Decimal::TWO),
+            _ => self.reference_price,
+        }
+    }
+
+    /// Precio del último trade ejecutado en este libro.
+    pub fn last_price(&self) -> Option<Decimal> {
+        self.last_price
+    }
+
+    pub fn reference_price(&self) -> Option<Decimal> {
+        self.reference_price
+    }
+
+    /// Fija el precio de referencia para un libro vacío o de una sola punta.
+    /// En cuanto el libro tiene dos puntas, el mid lo va pisando.
+    pub fn set_reference_price(&mut self, price: Decimal) {
+        self.reference_price = Some(price);
+    }
+
+    // Mantiene el precio de referencia en el último mid conocido
+    fn refresh_reference_price(&mut self) {
+        if let (Some(bid), Some(ask)) = (self.best_bid(), self.best_ask()) {
+            self.reference_price = Some((bid + ask) / Decimal::TWO);
+        }
+    }
+
+    /// Cantidad total en reposo en un lado, incluyendo órdenes ocultas y
+    /// reservas de icebergs. Se mantiene al vuelo, así que es O(1).
+    pub fn total_volume(&self, side: Side) -> Decimal {
+        let book_side = self.side(side);
+        debug_assert_eq!(book_side.volume(), book_side.levels().map(PriceLevel::total).sum::<Decimal>());
+        book_side.volume()
+    }
+
+    /// Los primeros `levels` niveles de un lado como (precio, cantidad visible).
+    /// Los niveles formados sólo por órdenes ocultas no aparecen.
+    pub fn depth(&self, side: Side, levels: usize) -> Vec<(Decimal, Decimal)> {
+        self.side(side).levels()
+            .map(|l| (l.price, l.visible_total()))
+            .filter(|(_, visible)| *visible > Decimal::zero())
+            .take(levels)
+            .collect()
+    }
+
+    /// Como `depth`, con la cantidad de órdenes de cada nivel: (precio, cantidad
+    /// visible, órdenes). Las órdenes ocultas no se cuentan.
+    pub fn depth_with_counts(&self, side: Side, levels: usize) -> Vec<(Decimal, Decimal, usize)> {
+        self.side(side).levels()
+            .map(|l| (l.price, l.visible_total(), l.orders.iter().filter(|o| o.visible() > Decimal::zero()).count()))
+            .filter(|(_, visible, _)| *visible > Decimal::zero())
+            .take(levels)
+            .collect()
+    }
+
+    /// Precio redondeado a `price_precision` decimales (con ceros a la derecha),
+    /// tal como sale en snapshots, deltas y checksums. Sin precisión configurada
+    /// se devuelve tal cual.
+    pub fn display_price(&self, price: Decimal) -> Decimal {
+        match self.config.price_precision {
+            Some(decimals) => {
+                let mut display = price;
+                display.rescale(decimals);
+                display
+            }
+            None => price,
+        }
+    }
+
+    /// Comisión de `trade` a la tasa `rate`, redondeada según la configuración.
+    pub fn fee(&self, trade: &Trade, rate: Decimal) -> Result<Decimal, OrderError> {
+        Ok(self.round_money(trade.fee(rate)?))
+    }
+
+    /// Comisiones (maker, taker) de `trade` según el `FeeSchedule` del libro.
+    pub fn trade_fees(&self, trade: &Trade) -> Result<(Decimal, Decimal), OrderError> {
+        let fees = self.config.fees;
+        Ok((self.fee(trade, fees.maker)?, self.fee(trade, fees.taker)?))
+    }
+
+    /// PnL de cerrar `amount` abierto en `side` a `entry` saliendo a `exit`
+    /// (positivo = ganancia), redondeado según la configuración.
+    pub fn pnl(&self, side: Side, entry: Decimal, exit: Decimal, amount: Decimal) -> Result<Decimal, OrderError> {
+        let per_unit = match side {
+            Side::Buy => exit.checked_sub(entry),
+            Side::Sell => entry.checked_sub(exit),
+        };
+        let pnl = per_unit.and_then(|p| p.checked_mul(amount)).ok_or(OrderError::Overflow("PnL"))?;
+        Ok(self.round_money(pnl))
+    }
+
+    // Cantidad redondeada hacia abajo al lote. Sin lote configurado se trunca a
+    // 8 decimales, que alcanza para no pasarse por el redondeo de una división.
+    fn round_lot_down(&self, amount: Decimal) -> Decimal {
+        match self.config.lot_size {
+            Some(lot) if lot > Decimal::zero() => (amount / lot).floor() * lot,
+            _ => amount.round_dp_with_strategy(8, RoundingStrategy::ToZero),
+        }
+    }
+
+    fn round_money(&self, value: Decimal) -> Decimal {
+        self.config.rounding.round(value, self.config.money_precision)
+    }
+
+    /// Órdenes en reposo de un lado en orden de prioridad: mejor precio primero
+    /// y, dentro de cada nivel, orden de llegada.
+    pub fn iter_orders(&self, side: Side) -> impl Iterator<Item = &Order> {
+        self.side(side).levels().flat_map(PriceLevel::orders)
+    }
+
+    /// Los últimos `n` trades (como mucho los que guarda `BookConfig::recent_trades`),
+    /// del más viejo al más reciente.
+    pub fn recent_trades(&self, n: usize) -> Vec<Trade> {
+        let skip = self.recent_trades.len().saturating_sub(n);
+        self.recent_trades.iter().skip(skip).cloned().collect()
+    }
+
+    /// Las últimas `n` órdenes rechazadas (como mucho las que guarda
+    /// `BookConfig::recent_rejects`), de la más vieja a la más reciente.
+    pub fn recent_rejects(&self, n: usize) -> Vec<RejectedOrder> {
+        let skip = self.recent_rejects.len().saturating_sub(n);
+        self.recent_rejects.iter().skip(skip).cloned().collect()
+    }
+
+    // Anota un rechazo en el registro de auditoría, si está activado.
+    fn record_reject(&mut self, order: &Order, reason: &OrderError) {
+        if self.config.recent_rejects == 0 {
+            return;
+        }
+        if self.recent_rejects.len() == self.config.recent_rejects {
+            self.recent_rejects.pop_front();
+        }
+        let timestamp = self.current_time();
+        self.recent_rejects.push_back(RejectedOrder { order: order.clone(), reason: reason.clone(), timestamp });
+    }
+
+    /// Busca una orden en reposo por id.
+    pub fn order(&self, id: u64) -> Option<&Order> {
+        let (side, price) = self.index.get(id)?;
+        self.side(side).level(price)?.orders().find(|o| o.id == id)
+    }
+
+    /// Posición en la cola de una orden en reposo: (volumen por delante, total
+    /// del nivel). Por delante cuenta lo que se ejecutaría antes que ella: de un
+    /// iceberg, sólo su pico (la reserva se repone al final de la cola).
+    pub fn queue_position(&self, id: u64) -> Option<(Decimal, Decimal)> {
+        let (side, price) = self.index.get(id)?;
+        let level = self.side(side).level(price)?;
+        let ahead = level.executable_orders()
+            .take_while(|(order, _)| order.id != id)
+            .map(|(_, executable)| executable)
+            .sum();
+        Some((ahead, level.total))
+    }
+
+    /// Posiciones netas por cuenta según los trades de este libro.
+    pub fn positions(&self) -> &Positions {
+        &self.positions
+    }
+
+    /// Órdenes en reposo de una cuenta, por id (orden de llegada). No incluye
+    /// las encoladas para el próximo tick en modo batch.
+    pub fn open_orders(&self, account: u64) -> Vec<Order> {
+        self.index.ids_for_account(account).filter_map(|id| self.order(id)).cloned().collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.index.len() == 0
+    }
+
+    // --- COMPARACIÓN ---
+
+    /// Describe en qué difieren dos libros (niveles y colas de órdenes).
+    /// Vacío si son iguales; pensado para mensajes de error en replays y restores.
+    pub fn diff(&self, other: &OrderBook) -> Vec<String> {
+        let mut mismatches = Vec::new();
+        for side in [Side::Buy, Side::Sell] {
+            let (ours, theirs) = (self.side(side), other.side(side));
+
+            for level in ours.levels() {
+                let Some(their_level) = theirs.level(level.price) else {
+                    mismatches.push(format!("{side:?} @ {}: sólo en self ({} en {} órdenes)", level.price, level.total, level.orders.len()));
+                    continue;
+                };
+                if level.total != their_level.total {
+                    mismatches.push(format!("{side:?} @ {}: total {} != {}", level.price, level.total, their_level.total));
+                }
+                let len = level.orders.len().max(their_level.orders.len());
+                for pos in 0..len {
+                    match (level.orders.get(pos), their_level.orders.get(pos)) {
+                        (Some(a), Some(b)) if a == b => {},
+                        (a, b) => mismatches.push(format!("{side:?} @ {} posición {pos}: {:?} != {:?}", level.price, a.map(|o| &o.order), b.map(|o| &o.order))),
+                    }
+                }
+            }
+
+            for level in theirs.levels().filter(|l| ours.level(l.price).is_none()) {
+                mismatches.push(format!("{side:?} @ {}: sólo en other ({} en {} órdenes)", level.price, level.total, level.orders.len()));
+            }
+        }
+        mismatches
+    }
+
+    // --- COMANDOS ---
+
+    /// Procesa una orden entrante y devuelve los trades generados, en orden de ejecución.
+    /// Hay un `Trade` por cada fill contra una orden del libro, con el id y el precio
+    /// de esa orden (un barrido de tres niveles da tres trades; un iceberg que se
+    /// repone da uno por pico), y sus cantidades suman lo ejecutado.
+    /// Una orden rechazada no toca el libro ni genera eventos.
+    /// Con reloj, antes se vencen las órdenes `Gtt` cumplidas y la orden recibe la hora del libro.
+    /// Los fills de last look vencidos se aceptan primero y encabezan la lista; si la
+    /// orden es rechazada sólo quedan en los eventos, así que quien publique fills
+    /// debe llamar antes a `expire_last_look` (como hace el engine).
+    pub fn add_order(&mut self, mut order: Order) -> Result<Vec<Trade>, OrderError> {
+        book_log!("--> 📥 Recibida Orden #{}: {:?} {} @ {}", order.id, order.side, order.amount, order.price);
+        if let Some(now) = self.now() {
+            self.expire_orders();
+            order.timestamp = now;
+        }
+        self.last_order_id = self.last_order_id.max(order.id);
+        self.last_timestamp = self.last_timestamp.max(order.timestamp);
+        let accepted = self.expire_last_look();
+        self.resume_breaker();
+        order.price = self.peg_price(&order);
+        if let Err(e) = self.normalize_scale(&mut order).and_then(|_| self.validate(&order)).and_then(|_| self.reserve(&order)) {
+            book_log!("   ❌ Orden #{} rechazada: {}", order.id, e);
+            self.record_reject(&order, &e);
+            return Err(e);
+        }
+        self.emit(BookEvent::OrderAdded { order: order.clone() });
+        self.seen_orders = true;
+        self.track_time_in_force(&order);
+        self.track_fill_ratio(&order);
+        if let OrderType::PegToOpposite { .. } = order.order_type {
+            self.pegs.insert(order.id);
+        }
+        let (id, volume_before) = (order.id, self.resting_volume());
+        let trades = if self.config.matching_mode == MatchingMode::Batch {
+            book_log!("   ⏳ Orden #{} encolada para el próximo tick", order.id);
+            self.pending.push(order);
+            Ok(Vec::new())
+        } else if let OrderType::MarketIfTouched { .. } = order.order_type {
+            Ok(self.park_trigger(order))
+        } else if order.time_in_force == TimeInForce::FokOrRest && self.crosses(&order) && !self.fills_completely(&order) {
+            book_log!("   ✂️  Orden FokOrRest #{} cancelada: cruza pero no se completa ({})", order.id, order.amount);
+            self.emit(BookEvent::OrderCancelled { order, by_book: true });
+            Ok(Vec::new())
+        } else {
+            let mut trades = self.execute(order);
+            self.refresh_reference_price();
+            if let Ok(trades) = trades.as_mut() {
+                trades.extend(self.fire_triggers());
+            }
+            trades.map(|trades| self.without_pending(trades))
+        };
+        // Sin fills, el libro sólo cambió en lo que la orden dejó en reposo
+        let rested = self.order(id).map_or(Decimal::zero(), |o| o.amount);
+        let untouched = trades.as_ref().is_ok_and(Vec::is_empty) && self.resting_volume() == volume_before + rested;
+        self.record_undo(if untouched { Inverse::Remove { id } } else { Inverse::Irreversible });
+        if let Ok(trades) = &trades {
+            self.check_fill_ratios(trades);
+        }
+        self.sync_hold(id);
+        if let Ok(trades) = &trades {
+            self.sync_trade_holds(trades);
+            self.check_breaker(trades);
+        }
+        self.repeg();
+        // Los fills que se aceptaron solos al llegar esta orden van primero
+        trades.map(|trades| accepted.into_iter().chain(trades).collect())
+    }
+
+    /// Envía una orden que cierra `percent`% de la posición de `order.account`
+    /// (50 = la mitad). El lado y la cantidad de `order` se reemplazan por los
+    /// del cierre, redondeando la cantidad hacia abajo al lote. Falla si la
+    /// cuenta está plana.
+    pub fn submit_percent_of_position(&mut self, mut order: Order, percent: Decimal) -> Result<OrderAck, OrderError> {
+        let (side, amount) = self.positions.closing_order(order.account, percent)?;
+        let amount = self.round_lot_down(amount);
+        if amount <= Decimal::zero() {
+            return Err(OrderError::InvalidAmount(amount));
+        }
+        book_log!("   🎯 Cierre del {}% de la cuenta {}: {:?} {}", percent, order.account, side, amount);
+        order.side = side;
+        order.amount = amount;
+        self.submit(order)
+    }
+
+    /// Como `add_order`, pero devuelve el destino completo de la orden.
+    pub fn submit(&mut self, order: Order) -> Result<OrderAck, OrderError> {
+        let (order_id, client_order_id, amount) = (order.id, order.client_order_id.clone(), order.amount);
+        let trades = self.add_order(order)?;
+        let filled_qty: Decimal = trades.iter().filter(|t| t.taker_id == order_id).map(|t| t.amount).sum();
+        let resting_qty = self.order(order_id)
+            .or_else(|| self.pending.iter().chain(&self.triggers).find(|o| o.id == order_id))
+            .map_or(Decimal::zero(), |o| o.amount);
+        let pending_qty: Decimal = self.pending_trades.iter().filter(|p| p.trade.taker_id == order_id).map(|p| p.trade.amount).sum();
+        Ok(OrderAck {
+            order_id,
+            client_order_id,
+            filled_qty,
+            resting_qty,
+            cancelled_qty: amount - filled_qty - resting_qty - pending_qty,
+            pending_qty,
+            trades,
+        })
+    }
+
+    // Lleva precio y cantidad a la escala configurada. Sólo agrega o quita ceros:
+    // si hubiera que redondear, la orden se rechaza.
+    fn normalize_scale(&self, order: &mut Order) -> Result<(), OrderError> {
+        if let Some(scale) = self.config.amount_scale {
+            order.amount = rescaled(order.amount, scale).ok_or(OrderError::InvalidAmount(order.amount))?;
+        }
+        if let Some(scale) = self.config.price_scale
+            // El precio de una orden de mercado (o MIT) no se usa
+            && !matches!(order.order_type, OrderType::MarketIfTouched { .. })
+            && !order.order_type.is_market()
+        {
+            order.price = rescaled(order.price, scale).ok_or(OrderError::InvalidPrice(order.price))?;
+        }
+        Ok(())
+    }
+
+    fn validate(&self, order: &Order) -> Result<(), OrderError> {
+        if self.trading_state != TradingState::Open {
+            return Err(OrderError::MarketHalted(self.trading_state));
+        }
+        if order.amount <= Decimal::zero() {
+            return Err(OrderError::InvalidAmount(order.amount));
+        }
+        if let Some(lot) = self.config.lot_size
+            && lot > Decimal::zero()
+            && !(order.amount % lot).is_zero()
+        {
+            let lower = (order.amount / lot).floor() * lot;
+            return Err(OrderError::InvalidLot {
+                amount: order.amount,
+                lot,
+                lower: Some(lower).filter(|l| *l > Decimal::zero()),
+                upper: lower + lot,
+            });
+        }
+        if let Visibility::Iceberg { peak } = order.visibility
+            && peak <= Decimal::zero()
+        {
+            return Err(OrderError::InvalidPeak(peak));
+        }
+        if let Some(min_fill) = order.min_fill
+            && min_fill <= Decimal::zero()
+        {
+            return Err(OrderError::InvalidAmount(min_fill));
+        }
+        if let Some(cap) = order.price_cap
+            && cap <= Decimal::zero()
+            && !self.config.allow_negative_prices
+        {
+            return Err(OrderError::InvalidPrice(cap));
+        }
+        if let Some(ratio) = order.cancel_at_fill_ratio
+            && (ratio <= Decimal::zero() || ratio > Decimal::one())
+        {
+            return Err(OrderError::InvalidFillRatio(ratio));
+        }
+        if let TimeInForce::Gtt { expire_at } = order.time_in_force
+            && let Some(now) = self.now()
+            && expire_at <= now
+        {
+            return Err(OrderError::AlreadyExpired { expire_at, now });
+        }
+        if order.time_in_force == TimeInForce::Ioc && self.config.matching_mode == MatchingMode::Batch {
+            return Err(OrderError::NotSupported("órdenes IOC en modo batch"));
+        }
+        if order.time_in_force == TimeInForce::FokOrRest {
+            if self.config.matching_mode == MatchingMode::Batch {
+                return Err(OrderError::NotSupported("órdenes FokOrRest en modo batch"));
+            }
+            if order.order_type.is_market() || matches!(order.order_type, OrderType::MarketIfTouched { .. }) {
+                return Err(OrderError::NotSupported("órdenes de mercado FokOrRest"));
+            }
+        }
+        if order.order_type == OrderType::PostOnly && self.config.matching_mode == MatchingMode::Batch {
+            return Err(OrderError::NotSupported("órdenes post-only en modo batch"));
+        }
+        if order.order_type == OrderType::AllOrNone {
+            if self.config.matching_mode == MatchingMode::Batch {
+                return Err(OrderError::NotSupported("órdenes all-or-none en modo batch"));
+            }
+            if let Visibility::Iceberg { .. } = order.visibility {
+                return Err(OrderError::NotSupported("órdenes all-or-none iceberg"));
+            }
+        }
+        if let OrderType::MarketQuote { budget } = order.order_type
+            && budget <= Decimal::zero()
+        {
+            return Err(OrderError::InvalidAmount(budget));
+        }
+        if let OrderType::MarketIfTouched { trigger } = order.order_type {
+            if self.config.matching_mode == MatchingMode::Batch {
+                return Err(OrderError::NotSupported("órdenes MIT en modo batch"));
+            }
+            if order.time_in_force != TimeInForce::Gtc {
+                return Err(OrderError::NotSupported("órdenes MIT con vencimiento o IOC"));
+            }
+            if trigger <= Decimal::zero() && !self.config.allow_negative_prices {
+                return Err(OrderError::InvalidPrice(trigger));
+            }
+            return Ok(()); // El precio límite no se usa: se dispara como orden de mercado
+        }
+        if let OrderType::PegToOpposite { offset, limit } = order.order_type {
+            if self.config.matching_mode == MatchingMode::Batch {
+                return Err(OrderError::NotSupported("órdenes pegged en modo batch"));
+            }
+            if offset <= Decimal::zero() {
+                return Err(OrderError::InvalidPrice(offset));
+            }
+            if limit <= Decimal::zero() && !self.config.allow_negative_prices {
+                return Err(OrderError::InvalidPrice(limit));
+            }
+        }
+        if order.order_type.is_market() {
+            if self.config.matching_mode == MatchingMode::Batch {
+                return Err(OrderError::NotSupported("órdenes de mercado en modo batch"));
+            }
+            let opposite = order.side.opposite();
+            let zero_fill = self.config.market_no_liquidity == MarketNoLiquidity::ZeroFill && self.reference_price.is_some();
+            if self.side(opposite).best().is_none() && !zero_fill {
+                return Err(OrderError::NoLiquidity { side: opposite });
+            }
+            return Ok(()); // El precio de una orden de mercado no se usa
+        }
+        if order.price <= Decimal::zero() && !self.config.allow_negative_prices {
+            return Err(OrderError::InvalidPrice(order.price));
+        }
+        if let Some(tick) = self.config.tick_size
+            && tick > Decimal::zero()
+            && !(order.price % tick).is_zero()
+        {
+            return Err(OrderError::InvalidTick { price: order.price, tick });
+        }
+        if let Some(min) = self.config.min_notional {
+            let notional = order.price.checked_mul(order.amount).ok_or(OrderError::Overflow("nocional de la orden"))?.abs();
+            if notional < min {
+                return Err(OrderError::BelowMinNotional { notional, min });
+            }
+        }
+        if let (Some(band), Some(reference)) = (self.config.price_band, self.mid_price()) {
+            // Ancho sobre el valor absoluto: con referencia negativa la banda no se invierte
+            let width = reference.abs() * band;
+            let (low, high) = (reference - width, reference + width);
+            if order.price < low || order.price > high {
+                return Err(OrderError::PriceOutOfBand { price: order.price, reference, band });
+            }
+        }
+        if order.order_type == OrderType::PostOnly
+            && let Some(best) = self.crossing_best(order.side, order.price)
+        {
+            return Err(OrderError::PostOnlyWouldCross { price: order.price, best });
+        }
+        if order.order_type == OrderType::AllOrNone
+            && let Some(best) = self.crossing_best(order.side, order.price)
+        {
+            return Err(OrderError::AllOrNoneWouldCross { price: order.price, best });
+        }
+        // Una orden que cruza puede terminar sin resto; eso se resuelve al guardarla
+        if let Some(max_levels) = self.opens_level_over_cap(order.side, order.price)
+            && !self.crosses(order)
+            && (self.config.depth_cap_policy == DepthCapPolicy::Reject || self.side(order.side).is_beyond_worst(order.price))
+        {
+            return Err(OrderError::DepthExceeded { side: order.side, max_levels });
+        }
+        Ok(())
+    }
+
+    fn track_time_in_force(&mut self, order: &Order) {
+        match order.time_in_force {
+            TimeInForce::Gtc | TimeInForce::Ioc | TimeInForce::FokOrRest => {}
+            TimeInForce::Gtt { expire_at } => {
+                self.expiries.insert((expire_at, order.id));
+            }
+            TimeInForce::Day => {
+                self.day_orders.insert(order.id);
+            }
+        }
+    }
+
+    /// Cancela las órdenes `Gtt` vencidas según el reloj del libro y, si pasó el
+    /// cierre de sesión, las `Day`. Devuelve las canceladas.
+    /// Sin reloj, o con el libro en `Halted`, no hace nada.
+    pub fn expire_orders(&mut self) -> Vec<Order> {
+        let Some(now) = self.now() else { return Vec::new() };
+        if self.trading_state == TradingState::Halted {
+            return Vec::new();
+        }
+        let mut expired = Vec::new();
+        if let Some(session_end) = self.config.session_end {
+            let next = *self.next_session_end.get_or_insert_with(|| next_session_end(now, session_end));
+            if now >= next {
+                expired.extend(self.end_session());
+                self.next_session_end = Some(next_session_end(now, session_end));
+            }
+        }
+        while let Some(&(expire_at, id)) = self.expiries.first()
+            && expire_at <= now
+        {
+            self.expiries.pop_first();
+            // La entrada puede ser de una orden que ya se ejecutó o se canceló
+            if let Some(order) = self.cancel_by_book(id) {
+                book_log!("   ⌛ Orden #{} vencida ({} <= {})", id, expire_at, now);
+                expired.push(order);
+            }
+        }
+        expired
+    }
+
+    /// Cierre de sesión: cancela todas las órdenes `Day` (en reposo o encoladas)
+    /// y las devuelve. Con `session_end` configurado y un reloj, el libro lo hace
+    /// solo al pasar la hora de cierre; sin eso hay que llamarlo a mano.
+    pub fn end_session(&mut self) -> Vec<Order> {
+        if self.trading_state == TradingState::Halted {
+            return Vec::new();
+        }
+        book_log!("   🌙 Fin de sesión: barriendo {} órdenes Day", self.day_orders.len());
+        // Las que ya no están (ejecutadas o canceladas) simplemente no devuelven nada
+        std::mem::take(&mut self.day_orders).into_iter()
+            .filter_map(|id| self.cancel_by_book(id))
+            .collect()
+    }
+
+    // `true` si la orden límite ejecutaría algo contra el mejor precio contrario.
+    fn crosses(&self, order: &Order) -> bool {
+        self.crossing_best(order.side, order.price).is_some()
+    }
+
+    // `true` si `order` se ejecutaría entera al llegar (simulándola sobre una copia).
+    fn fills_completely(&self, order: &Order) -> bool {
+        self.simulate(order).iter().map(|trade| trade.amount).sum::<Decimal>() == order.amount
+    }
+
+    // El mejor precio contrario si un límite de `side` a `price` lo alcanza. Se
+    // compara sólo contra el mejor: si no lo alcanza, menos alcanza a los de atrás,
+    // y si lo alcanza da igual cuántos niveles más atravesaría.
+    fn crossing_best(&self, side: Side, price: Decimal) -> Option<Decimal> {
+        match side {
+            Side::Buy => self.best_ask().filter(|ask| price >= *ask),
+            Side::Sell => self.best_bid().filter(|bid| price <= *bid),
+        }
+    }
+
+    // Devuelve el tope si una orden a `price` abriría un nivel nuevo en un lado lleno.
+    fn opens_level_over_cap(&self, side: Side, price: Decimal) -> Option<usize> {
+        let max_levels = self.config.max_levels_per_side?;
+        let book_side = self.side(side);
+        (book_side.level(price).is_none() && book_side.level_count() >= max_levels).then_some(max_levels)
+    }
+
+    // Cancela todas las órdenes del peor nivel de un lado.
+    fn evict_worst_level(&mut self, side: Side) {
+        let Some(level) = self.side_mut(side).pop_worst() else { return };
+        book_log!("   🧹 Nivel {:?} @ {} desalojado por tope de profundidad ({} órdenes)", side, level.price, level.orders.len());
+        for resting in level.orders {
+            self.index.remove(&resting.order);
+            self.emit(BookEvent::OrderCancelled { order: resting.order, by_book: true });
+        }
+        self.emit_level(side, level.price, Decimal::zero());
+    }
+
+    /// Cancela una orden en reposo. Devuelve la orden con su cantidad pendiente.
+    /// En `Halted` no se cancela nada. Con `min_resting_time`, una orden que
+    /// descansó menos que el mínimo se cuenta en `fast_cancels` o no se cancela
+    /// (devuelve `None`), según `min_resting_policy`.
+    pub fn cancel_order(&mut self, id: u64) -> Option<Order> {
+        if self.trading_state == TradingState::Halted || !self.check_resting_time(id) {
+            return None;
+        }
+        self.cancel_unchecked(id)
+    }
+
+    /// Cancel-on-disconnect: como `cancel_order`, pero sin el control de
+    /// `min_resting_time`. Una conexión caída tiene que retirar sus órdenes
+    /// siempre; nadie va a reintentar la cancelación. En `Halted` tampoco cancela.
+    pub fn cancel_on_disconnect(&mut self, id: u64) -> Option<Order> {
+        if self.trading_state == TradingState::Halted {
+            return None;
+        }
+        self.cancel_unchecked(id)
+    }
+
+    /// Cancelaciones que llegaron antes de `min_resting_time` y se dejaron pasar.
+    pub fn fast_cancels(&self) -> u64 {
+        self.fast_cancels
+    }
+
+    // Anti-spoofing: `false` si la cancelación de `id` se rechaza por haber
+    // descansado menos que el mínimo. Las órdenes que no están en reposo pasan.
+    fn check_resting_time(&mut self, id: u64) -> bool {
+        let Some((rested, min)) = self.resting_shortfall(id) else { return true };
+        match self.config.min_resting_policy {
+            MinRestingPolicy::Flag => {
+                book_log!("   🚩 Cancelación rápida de #{}: descansó {} ms (mínimo {})", id, rested, min);
+                self.fast_cancels += 1;
+                true
+            }
+            MinRestingPolicy::Reject => {
+                book_log!("   ❌ Cancelación de #{} rechazada: descansó {} ms (mínimo {})", id, rested, min);
+                false
+            }
+        }
+    }
+
+    // (descansado, mínimo) si la orden `id` está en reposo desde hace menos
+    // que `min_resting_time`.
+    fn resting_shortfall(&self, id: u64) -> Option<(u64, u64)> {
+        let min = self.config.min_resting_time?;
+        let rested = self.current_time().saturating_sub(self.order(id)?.timestamp);
+        (rested < min).then_some((rested, min))
+    }
+
+    // Con `MinRestingPolicy::Reject`, el error de una orden que todavía no se
+    // puede retirar. No cuenta nada: sirve para revisar antes de tocar el libro.
+    pub(super) fn check_can_pull(&self, id: u64) -> Result<(), OrderError> {
+        match self.resting_shortfall(id) {
+            Some((rested, min)) if self.config.min_resting_policy == MinRestingPolicy::Reject => {
+                Err(OrderError::CancelTooSoon { id, rested, min })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // Cancela sin el control de tiempo mínimo: replay, rollback de una
+    // cotización y cancel-on-disconnect.
+    fn cancel_unchecked(&mut self, id: u64) -> Option<Order> {
+        let (order, inverse) = self.withdraw(id, false)?;
+        self.record_undo(inverse);
+        Some(order)
+    }
+
+    // Cancelación que decide el libro (vencimientos, cierre de sesión, fracción
+    // ejecutada): no es un comando del cliente, así que no se puede deshacer.
+    fn cancel_by_book(&mut self, id: u64) -> Option<Order> {
+        self.withdraw(id, true).map(|(order, _)| order)
+    }
+
+    // Saca la orden de donde esté y publica la cancelación. Devuelve también
+    // la operación que la devolvería a su lugar.
+    fn withdraw(&mut self, id: u64, by_book: bool) -> Option<(Order, Inverse)> {
+        if self.trading_state == TradingState::Halted {
+            return None;
+        }
+        if let Some(pos) = self.pending.iter().position(|o| o.id == id) {
+            let order = self.pending.remove(pos);
+            book_log!("   🗑️  Cancelada Orden #{} antes del tick", id);
+            self.emit(BookEvent::OrderCancelled { order: order.clone(), by_book });
+            return Some((order.clone(), Inverse::Pending { order, pos }));
+        }
+        if let Some(pos) = self.triggers.iter().position(|o| o.id == id) {
+            let order = self.triggers.remove(pos);
+            book_log!("   🗑️  Cancelada MIT #{} antes de dispararse", id);
+            self.emit(BookEvent::OrderCancelled { order: order.clone(), by_book });
+            return Some((order.clone(), Inverse::Trigger { order, pos }));
+        }
+        let (side, price) = self.index.get(id)?;
+        let (resting, pos, level_total) = self.side_mut(side).remove(id, price)?;
+        let order = resting.order.clone();
+        self.index.remove(&order);
+        book_log!("   🗑️  Cancelada Orden #{}: resto {} @ {}", id, order.amount, order.price);
+
+        self.emit(BookEvent::OrderCancelled { order: order.clone(), by_book });
+        self.emit_level(side, price, level_total);
+        self.refresh_reference_price();
+        self.repeg();
+        Some((order, Inverse::Resting { resting, pos }))
+    }
+
+    /// Cancela varias órdenes de una vez. El resultado va en el mismo orden que
+    /// `ids`: la orden cancelada, o `None` si no estaba (o ya se canceló antes
+    /// en el mismo lote, o el tiempo mínimo en reposo la rechazó). Cancelar no
+    /// puede fallar a mitad de camino, así que el lote nunca queda a medias; en
+    /// `Halted` no se cancela ninguna.
+    pub fn cancel_batch(&mut self, ids: &[u64]) -> Vec<Option<Order>> {
+        book_log!("   🗑️  Cancelación en lote de {} órdenes", ids.len());
+        ids.iter().map(|&id| self.cancel_order(id)).collect()
+    }
+
+    /// Cancela una orden en reposo usando el id asignado por el cliente.
+    pub fn cancel_by_client_id(&mut self, client_order_id: &str) -> Option<Order> {
+        let pending = self.pending.iter().chain(&self.triggers).find(|o| o.client_order_id.as_deref() == Some(client_order_id));
+        let id = pending.map(|o| o.id).or_else(|| self.index.id_for_client(client_order_id))?;
+        self.cancel_order(id)
+    }
+
+    /// Modifica precio y/o cantidad de una orden en reposo.
+    /// Reducir la cantidad al mismo precio conserva la prioridad: la orden no
+    /// sale de su lugar en la cola, sólo bajan el total del nivel y el volumen
+    /// del lado, y se publica como un único `OrderModified` (más el delta del
+    /// nivel). Como es una cancelación parcial, también se acepta en
+    /// `CancelOnly`. Cualquier otro cambio la pierde: se publica como
+    /// cancelación más alta (`OrderCancelled` + `OrderAdded`) y la orden vuelve
+    /// a pasar por el matching y por las validaciones de una nueva.
+    /// Con `BookConfig::amend_to_zero_cancels`, llevar la cantidad a cero es
+    /// una cancelación (con sus mismos controles) y no una modificación.
+    /// Devuelve `None` si la orden no existe, la nueva cantidad no es positiva, el
+    /// libro no está abierto, la orden modificada se rechazaría (una post-only
+    /// que quedaría cruzando, un precio fuera de banda...) o el matching se cortó
+    /// por un error interno.
+    pub fn modify_order(&mut self, id: u64, price: Decimal, amount: Decimal) -> Option<Vec<Trade>> {
+        if amount.is_zero() && self.config.amend_to_zero_cancels {
+            book_log!("   ✏️  Modificación de #{} a cantidad cero: se cancela", id);
+            return self.cancel_order(id).map(|_| Vec::new());
+        }
+        if amount <= Decimal::zero() || self.trading_state == TradingState::Halted {
+            return None;
+        }
+        let price = self.config.price_scale.map_or(Some(price), |scale| rescaled(price, scale))?;
+        let amount = self.config.amount_scale.map_or(Some(amount), |scale| rescaled(amount, scale))?;
+        let (side, old_price) = self.index.get(id)?;
+        let open = self.trading_state == TradingState::Open;
+
+        let book_side = self.side_mut(side);
+        let key = book_side.key(old_price);
+        let level = book_side.levels.get_mut(&key)?;
+        let resting = level.orders.iter_mut().find(|o| o.order.id == id)?;
+
+        if price == old_price && amount <= resting.order.amount {
+            if amount == resting.order.amount {
+                // Nada que cambiar: ni evento ni delta
+                return Some(Vec::new());
+            }
+            // Modificación en el lugar: se mantiene la posición en la cola
+            let inverse = Inverse::Resize { id, amount: resting.order.amount, shown: resting.shown };
+            let reduction = resting.order.amount - amount;
+            level.total -= reduction;
+            book_side.volume -= reduction;
+            resting.reduce_to(amount);
+            let level_total = level.visible_total();
+            book_log!("   ✏️  Modificada Orden #{} (mantiene prioridad): {} @ {}", id, amount, price);
+
+            self.emit(BookEvent::OrderModified { id, price, amount });
+            self.emit_level(side, old_price, level_total);
+            self.record_undo(inverse);
+            self.sync_hold(id);
+            return Some(Vec::new());
+        }
+
+        if !open {
+            return None;
+        }
+        // Se valida antes de cancelar: una modificación rechazada deja la orden como estaba
+        let mut order = resting.order.clone();
+        order.price = price;
+        order.amount = amount;
+        if let Err(e) = self.validate(&order).and_then(|_| self.check_rehold(&order)) {
+            book_log!("   ❌ Modificación de #{} rechazada: {}", id, e);
+            return None;
+        }
+        book_log!("   ✏️  Modificada Orden #{} (pierde prioridad): {} @ {}", id, amount, price);
+        self.cancel_order(id)?;
+        self.add_order(order).ok()
+    }
+
+    // --- MATCHING ---
+
+    // Aplica las asignaciones de un matcher a medida sobre el mejor nivel contrario.
+    // Cada una se valida por separado y las inválidas se descartan con un log:
+    // maker inexistente, cantidad fuera de rango, self-trade o trade-through.
+    // No ejecuta más de `limit` en el nivel. Devuelve `true` si se ejecutó al menos un fill.
+    fn execute_allocations(&mut self, matcher: &dyn Matcher, order: &mut Order, limit: Decimal, trades: &mut Vec<Trade>) -> bool {
+        let opposite_side = order.side.opposite();
+        let prevailing_mid = self.mid_price();
+        let last_look_deadline = self.config.last_look.map(|window| self.current_time() + window);
+        // El nivel sale del mapa mientras se trabaja sobre él y vuelve si le quedan órdenes
+        let Some((key, mut level)) = self.side_mut(opposite_side).levels.pop_first() else { return false };
+        let (best_price, total_before) = (level.price, level.total);
+        let mut limit = limit;
+        let allocations = matcher.allocate(order, limit, &level);
+
+        let mut events = Vec::new();
+        let mut progressed = false;
+        for allocation in allocations {
+            let Some(pos) = level.orders.iter().position(|o| o.order.id == allocation.maker_id) else {
+                book_log!("   ⛔ Asignación descartada: #{} no está en el nivel {}", allocation.maker_id, best_price);
+                continue;
+            };
+            let maker = &mut level.orders[pos];
+
+            // Trade-through: ejecutar peor que el mejor precio contrario disponible
+            let worse = match order.side {
+                Side::Buy => allocation.price > best_price,
+                Side::Sell => allocation.price < best_price,
+            };
+            if worse && self.config.trade_through_protection {
+                book_log!("   ⛔ Trade-through evitado: #{} a {} con mejor precio contrario {}", order.id, allocation.price, best_price);
+                continue;
+            }
+            if !worse && allocation.price != best_price {
+                book_log!("   ⛔ Asignación descartada: {} mejora el precio del maker #{} ({})", allocation.price, maker.order.id, best_price);
+                continue;
+            }
+            if allocation.amount <= Decimal::zero() || allocation.amount > limit {
+                book_log!("   ⛔ Asignación descartada: cantidad {} para #{} con {} pendiente", allocation.amount, order.id, limit);
+                continue;
+            }
+            if let Some(min_fill) = order.min_fill
+                && allocation.amount < min_fill.min(order.amount)
+            {
+                book_log!("   ⛔ Asignación descartada: {} no llega al fill mínimo {} de #{}", allocation.amount, min_fill, order.id);
+                continue;
+            }
+            if maker.order.order_type == OrderType::AllOrNone && allocation.amount != maker.order.amount {
+                book_log!("   ⛔ Asignación descartada: {} no completa la AON #{} ({})", allocation.amount, maker.order.id, maker.order.amount);
+                continue;
+            }
+            if let Some(position_limit) = self.config.position_limit
+                && allocation.amount > self.positions.room(maker.order.account, maker.order.side, position_limit)
+            {
+                book_log!("   🧱 Asignación descartada: #{} pasaría la cuenta {} de su límite de posición", maker.order.id, maker.order.account);
+                continue;
+            }
+            if self.config.self_trade_prevention.is_some() && self.config.stp_scope.same_owner(&maker.order, order) {
+                book_log!("   🚫 Self-trade evitado: asignación #{} vs #{} descartada (cuenta {})", order.id, maker.order.id, order.account);
+                continue;
+            }
+            if let Err(e) = maker.fill(allocation.amount) {
+                book_log!("   ⛔ Asignación descartada: {}", e);
+                continue;
+            }
+
+            book_log!("   ⚡ MATCH EJECUTADO ({:?}): #{} vs #{} :: Cantidad {} @ {}", matcher, order.id, maker.order.id, allocation.amount, allocation.price);
+            order.amount -= allocation.amount;
+            limit -= allocation.amount;
+            self.positions.record(order.account, order.side, allocation.amount);
+            self.positions.record(maker.order.account, maker.order.side, allocation.amount);
+            level.total -= allocation.amount;
+            let trade = Trade {
+                trade_id: self.trade_ids.next(),
+                maker_id: maker.order.id,
+                taker_id: order.id,
+                price: allocation.price,
+                amount: allocation.amount,
+                timestamp: order.timestamp,
+                aggressor: order.side,
+                prevailing_mid,
+                taker_strategy_id: order.strategy_id.clone(),
+                maker_strategy_id: maker.order.strategy_id.clone(),
+                taker_venue_tag: order.venue_tag.clone(),
+                maker_venue_tag: maker.order.venue_tag.clone(),
+            };
+            events.push(last_look::trade_event(&mut self.pending_trades, last_look_deadline, &trade, order.account, maker.order.account));
+            trades.push(trade);
+            self.last_price = Some(allocation.price);
+            progressed = true;
+
+            if maker.needs_refill() {
+                let mut maker = level.orders.remove(pos).expect("maker en el nivel");
+                maker.refill();
+                level.requeue(maker, self.config.hidden_priority);
+            } else if maker.order.amount.is_zero() {
+                let maker = level.orders.remove(pos).expect("maker en el nivel").order;
+                self.index.remove(&maker);
+            }
+        }
+
+        let level_total = level.visible_total();
+        let book_side = self.side_mut(opposite_side);
+        book_side.volume -= total_before - level.total;
+        if !level.orders.is_empty() {
+            book_side.levels.insert(key, level);
+        }
+        for event in events {
+            self.emit(event);
+        }
+        if progressed {
+            self.emit_level(opposite_side, best_price, level_total);
+        }
+        progressed
+    }
+
+    // Cruza la orden contra el lado contrario y guarda el resto en el libro.
+    // Ante un error interno el libro queda consistente con lo ejecutado hasta ahí
+    // (eventos emitidos, nada en negativo) y la orden no pasa a reposo.
+    fn execute(&mut self, mut order: Order) -> Result<Vec<Trade>, OrderError> {
+        let mut trades = Vec::new();
+        let (stp, stp_scope) = (self.config.self_trade_prevention, self.config.stp_scope);
+        let (hidden_priority, price_improvement, tick_size) = (self.config.hidden_priority, self.config.price_improvement, self.config.tick_size);
+        let mut first_fill_price: Option<Decimal> = None;
+        let mut stalled = false; // El matcher (o los makers salteados) dejó de ejecutar contra un nivel que cruza
+        // Nocional ejecutado (para `MarketQuote`). Satura en vez de entrar en pánico:
+        // un presupuesto nunca llega a `Decimal::MAX`, así que saturado es agotado
+        let mut spent = Decimal::zero();
+        let position_limit = self.config.position_limit;
+        let mut capped = false; // El taker llegó a su límite de posición (o a su tope de precio)
+        let last_look_deadline = self.config.last_look.map(|window| self.current_time() + window);
+
+        // Lógica de Matching (Cruce): seguimos consumiendo el mejor nivel contrario
+        // mientras la orden tenga cantidad y el precio cruce.
+        while order.amount > Decimal::zero() {
+            // Si compro, busco la venta más barata; si vendo, la compra más cara
+            let Some(best_price) = self.side(order.side.opposite()).best().map(|l| l.price) else { break };
+
+            let crosses = match (order.order_type, order.side) {
+                // Una MIT llega acá ya convertida en `Market`
+                (OrderType::Market | OrderType::MarketWithSlippage { .. } | OrderType::MarketQuote { .. } | OrderType::MarketIfTouched { .. }, _) => true,
+                (OrderType::Limit | OrderType::PostOnly | OrderType::AllOrNone | OrderType::PegToOpposite { .. }, Side::Buy) => best_price <= order.price,
+                (OrderType::Limit | OrderType::PostOnly | OrderType::AllOrNone | OrderType::PegToOpposite { .. }, Side::Sell) => best_price >= order.price,
+            };
+            if !crosses {
+                break; // No hay más matches posibles
+            }
+
+            // Tope absoluto de precio: vale para cualquier tipo, pegged o de mercado
+            if let Some(cap) = order.price_cap
+                && match order.side {
+                    Side::Buy => best_price > cap,
+                    Side::Sell => best_price < cap,
+                }
+            {
+                book_log!("   🛑 Tope de precio alcanzado: #{} no ejecuta a {} (tope {})", order.id, best_price, cap);
+                capped = true;
+                break;
+            }
+
+            // Compra/venta por monto: a este precio no se puede pasar del presupuesto
+            let level_cap = match order.order_type {
+                OrderType::MarketQuote { budget } if best_price > Decimal::zero() => {
+                    let cap = self.round_lot_down((budget - spent) / best_price).min(order.amount);
+                    if cap <= Decimal::zero() {
+                        book_log!("   💰 Presupuesto agotado: gastado {} de {}", spent, budget);
+                        break;
+                    }
+                    cap
+                }
+                _ => order.amount,
+            };
+            // Límite de posición: el taker no ejecuta más de lo que le queda a su cuenta
+            let level_cap = match position_limit {
+                Some(limit) => {
+                    let room = self.positions.room(order.account, order.side, limit);
+                    if room.is_zero() {
+                        book_log!("   🧱 Orden #{} frenada: la cuenta {} llegó al límite de posición {}", order.id, order.account, limit);
+                        capped = true;
+                        break;
+                    }
+                    level_cap.min(room)
+                }
+                None => level_cap,
+            };
+            let trades_before = trades.len();
+
+            // Tope de slippage: relativo al precio del primer fill
+            if let (OrderType::MarketWithSlippage { max_slippage }, Some(first)) = (order.order_type, first_fill_price)
+                && (best_price - first).abs() > max_slippage
+            {
+                book_log!("   🛑 Slippage máximo alcanzado: {} está a más de {} de {}", best_price, max_slippage, first);
+                break;
+            }
+
+            // Un matcher a medida propone el reparto; el libro lo valida y ejecuta
+            if let Some(matcher) = self.matcher.clone() {
+                if !self.execute_allocations(matcher.as_ref(), &mut order, level_cap, &mut trades) {
+                    stalled = true; // Sin asignaciones válidas no hay progreso posible
+                    break;
+                }
+                first_fill_price = first_fill_price.or(self.last_price);
+                spent = trades[trades_before..].iter().fold(spent, |spent, t| spent.saturating_add(t.price.saturating_mul(t.amount)));
+                continue;
+            }
+
+            // Consumimos el nivel en orden FIFO. Los eventos se juntan y se emiten
+            // al terminar el nivel, en el mismo orden en que ocurrieron. Mientras
+            // el nivel sea el mejor, el mid no cambia.
+            let prevailing_mid = self.mid_price();
+            let opposite = self.sides.get_mut(order.side.opposite());
+            let level = opposite.best_mut().expect("el mejor nivel existe");
+            let (level_price, total_before) = (level.price, level.total);
+            // Con mejora de precio, un límite que cruza ejecuta al medio entre su límite y el nivel
+            let trade_price = match (price_improvement, order.order_type) {
+                (PriceImprovement::Midpoint, OrderType::Limit) => midpoint_price(order.price, level_price, order.side, tick_size),
+                _ => level_price,
+            };
+            let mut level_events = Vec::new();
+            let mut failure = None;
+            let mut level_left = level_cap;
+            let mut pos = 0; // Las AON que no se pueden completar quedan adelante, salteadas
+            while order.amount > Decimal::zero() && level_left > Decimal::zero() {
+                let Some(maker) = level.orders.get_mut(pos) else { break };
+
+                // All-or-none: sólo se ejecuta entera; si no alcanza, se pasa a la de atrás
+                if maker.order.order_type == OrderType::AllOrNone && order.amount.min(level_left) < maker.order.amount {
+                    book_log!("   ⏭️  AON #{} salteada: #{} no la completa ({} de {})", maker.order.id, order.id, order.amount.min(level_left), maker.order.amount);
+                    pos += 1;
+                    continue;
+                }
+
+                // Self-trade: la política decide quién se cancela, no hay trade
+                if let Some(policy) = stp
+                    && stp_scope.same_owner(&maker.order, &order)
+                {
+                    book_log!("   🚫 Self-trade evitado ({:?}): #{} vs #{} (cuenta {})", policy, order.id, maker.order.id, order.account);
+                    let decrement = order.amount.min(maker.order.amount);
+                    let (cancel_maker, cancel_taker) = match policy {
+                        SelfTradePrevention::CancelNewest => (false, true),
+                        SelfTradePrevention::CancelOldest => (true, false),
+                        SelfTradePrevention::CancelBoth => (true, true),
+                        // La más chica desaparece, la más grande queda reducida
+                        SelfTradePrevention::DecrementAndCancel => (maker.order.amount == decrement, order.amount == decrement),
+                    };
+                    let decrementing = policy == SelfTradePrevention::DecrementAndCancel;
+
+                    if cancel_maker {
+                        let maker = level.orders.remove(pos).expect("maker en el nivel").order;
+                        level.total -= maker.amount;
+                        self.index.remove(&maker);
+                        level_events.push(BookEvent::OrderCancelled { order: maker, by_book: true });
+                    } else if decrementing {
+                        maker.reduce_to(maker.order.amount - decrement);
+                        level.total -= decrement;
+                    }
+
+                    if cancel_taker {
+                        level_events.push(BookEvent::OrderCancelled { order: order.clone(), by_book: true });
+                        order.amount = Decimal::zero();
+                    } else if decrementing {
+                        order.amount -= decrement;
+                    }
+                    continue;
+                }
+
+                // Un maker cuya cuenta ya no puede operar se cancela al llegarle el turno
+                let maker_room = position_limit.map_or(maker.executable(), |limit| self.positions.room(maker.order.account, maker.order.side, limit));
+                let maker_short = match maker.order.order_type {
+                    OrderType::AllOrNone => maker_room < maker.order.amount,
+                    _ => maker_room.is_zero(),
+                };
+                if maker_short {
+                    let maker = level.orders.remove(pos).expect("maker en el nivel").order;
+                    book_log!("   🧱 Maker #{} cancelado: la cuenta {} llegó al límite de posición", maker.id, maker.account);
+                    level.total -= maker.amount;
+                    self.index.remove(&maker);
+                    level_events.push(BookEvent::OrderCancelled { order: maker, by_book: true });
+                    continue;
+                }
+
+                let trade_amount = order.amount.min(maker.executable()).min(level_left).min(maker_room);
+                // Fill mínimo del taker: un maker que no lo alcanza se saltea, como una AON
+                if let Some(min_fill) = order.min_fill
+                    && trade_amount < min_fill.min(order.amount)
+                {
+                    book_log!("   ⏭️  #{} salteada: {} no llega al fill mínimo {} de #{}", maker.order.id, trade_amount, min_fill, order.id);
+                    pos += 1;
+                    continue;
+                }
+
+                // ¡MATCH!
+                if let Err(e) = maker.fill(trade_amount) {
+                    failure = Some(e);
+                    break;
+                }
+                match order.side {
+                    Side::Buy => book_log!("   ⚡ MATCH EJECUTADO: Compra #{} vs Venta #{} :: Cantidad {} @ {}", order.id, maker.order.id, trade_amount, trade_price),
+                    Side::Sell => book_log!("   ⚡ MATCH EJECUTADO: Venta #{} vs Compra #{} :: Cantidad {} @ {}", order.id, maker.order.id, trade_amount, trade_price),
+                }
+                order.amount -= trade_amount;
+                level_left -= trade_amount;
+                self.positions.record(order.account, order.side, trade_amount);
+                self.positions.record(maker.order.account, maker.order.side, trade_amount);
+                spent = spent.saturating_add(trade_price.saturating_mul(trade_amount));
+                level.total -= trade_amount;
+
+                let trade = Trade {
+                    trade_id: self.trade_ids.next(),
+                    maker_id: maker.order.id,
+                    taker_id: order.id,
+                    price: trade_price,
+                    amount: trade_amount,
+                    timestamp: order.timestamp,
+                    aggressor: order.side,
+                    prevailing_mid,
+                    taker_strategy_id: order.strategy_id.clone(),
+                    maker_strategy_id: maker.order.strategy_id.clone(),
+                    taker_venue_tag: order.venue_tag.clone(),
+                    maker_venue_tag: maker.order.venue_tag.clone(),
+                };
+                level_events.push(last_look::trade_event(&mut self.pending_trades, last_look_deadline, &trade, order.account, maker.order.account));
+                trades.push(trade);
+                first_fill_price.get_or_insert(trade_price);
+                self.last_price = Some(trade_price);
+
+                if maker.needs_refill() {
+                    // Iceberg con el pico agotado: se repone y va al final de la cola
+                    let mut maker = level.orders.remove(pos).expect("maker en el nivel");
+                    maker.refill();
+                    book_log!("   🧊 Iceberg #{} repone {} (reserva {})", maker.order.id, maker.shown, maker.order.amount);
+                    level.requeue(maker, hidden_priority);
+                } else if maker.order.amount.is_zero() {
+                    // La orden del libro se agotó: se elimina para seguir con la siguiente
+                    let maker = level.orders.remove(pos).expect("maker en el nivel").order;
+                    self.index.remove(&maker);
+                }
+            }
+            // Salió del bucle con cantidad pendiente y órdenes en el nivel: sólo quedan salteadas (AON o bajo el fill mínimo)
+            let blocked = failure.is_none() && !level.orders.is_empty() && order.amount > Decimal::zero() && level_left > Decimal::zero();
+            let (level_total, removed) = (level.visible_total(), total_before - level.total);
+            opposite.volume -= removed;
+            opposite.remove_best_if_empty();
+
+            for event in level_events {
+                self.emit(event);
+            }
+            self.emit_level(order.side.opposite(), level_price, level_total);
+            if let Some(e) = failure {
+                book_log!("   💥 Matching cortado para orden #{}: {}", order.id, e);
+                return Err(e);
+            }
+            if blocked {
+                stalled = true;
+                break;
+            }
+        }
+
+        // Las órdenes de mercado nunca quedan en el libro: el resto se cancela
+        if order.order_type.is_market() && order.amount > Decimal::zero() {
+            book_log!("   ✂️  Resto de orden de mercado #{} cancelado: {}", order.id, order.amount);
+            self.emit(BookEvent::OrderCancelled { order: order.clone(), by_book: true });
+            order.amount = Decimal::zero();
+        }
+
+        if order.time_in_force == TimeInForce::Ioc && order.amount > Decimal::zero() {
+            book_log!("   ✂️  Resto de orden IOC #{} cancelado: {}", order.id, order.amount);
+            self.emit(BookEvent::OrderCancelled { order: order.clone(), by_book: true });
+            order.amount = Decimal::zero();
+        }
+
+        // Frenada por el límite de posición o el tope de precio con precios que
+        // todavía cruzan: guardarla dejaría el libro cruzado
+        if capped && order.amount > Decimal::zero() && self.crosses(&order) {
+            book_log!("   ✂️  Resto de orden #{} cancelado: frenada por su límite de posición o tope de precio", order.id);
+            self.emit(BookEvent::OrderCancelled { order: order.clone(), by_book: true });
+            order.amount = Decimal::zero();
+        }
+
+        // Guardar el resto dejaría el libro cruzado
+        if stalled && order.amount > Decimal::zero() {
+            book_log!("   ✂️  Resto de orden #{} cancelado: no puede ejecutar contra un nivel que cruza", order.id);
+            self.emit(BookEvent::OrderCancelled { order: order.clone(), by_book: true });
+            order.amount = Decimal::zero();
+        }
+
+        // Tope de profundidad: el resto abriría un nivel de más
+        if order.amount > Decimal::zero()
+            && let Some(max_levels) = self.opens_level_over_cap(order.side, order.price)
+        {
+            if self.config.depth_cap_policy == DepthCapPolicy::EvictWorst && !self.side(order.side).is_beyond_worst(order.price) {
+                self.evict_worst_level(order.side);
+            } else {
+                book_log!("   ✂️  Resto de orden #{} cancelado: el lado {:?} ya tiene {} niveles", order.id, order.side, max_levels);
+                self.emit(BookEvent::OrderCancelled { order: order.clone(), by_book: true });
+                order.amount = Decimal::zero();
+            }
+        }
+
+        // El resto nunca puede quedar cruzado: si el lado contrario todavía cruza,
+        // el matching debió haberlo consumido. Se cancela antes que bloquear el libro.
+        if order.amount > Decimal::zero()
+            && let Some(best) = self.crossing_best(order.side, order.price)
+        {
+            book_log!("   💥 Resto de orden #{} cancelado: a {} quedaría cruzado contra {}", order.id, order.price, best);
+            self.emit(BookEvent::OrderCancelled { order: order.clone(), by_book: true });
+            return Err(OrderError::Internal(format!("el resto de #{} a {} cruzaría contra {best}", order.id, order.price)));
+        }
+
+        // Si sobra cantidad, guardar en el libro
+        if order.amount > Decimal::zero() {
+            book_log!("   📌 Guardando resto en el libro: {} @ {}", order.amount, order.price);
+            self.rest(order);
+        }
+
+        Ok(trades)
+    }
+
+    // Deja la orden en su nivel con la próxima secuencia de llegada.
+    fn rest(&mut self, order: Order) {
+        let (side, price) = (order.side, order.price);
+        self.arrival_seq += 1;
+        let (arrival_seq, tie_break, hidden_priority) = (self.arrival_seq, self.config.tie_break, self.config.hidden_priority);
+        self.index.insert(&order);
+        let level_total = self.side_mut(side).push(order, arrival_seq, tie_break, hidden_priority);
+        self.emit_level(side, price, level_total);
+    }
+}
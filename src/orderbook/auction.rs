@@ -0,0 +1,202 @@
+// --- SUBASTAS POR LOTES (MODO BATCH) ---
+//
+// En modo batch las órdenes no se cruzan al llegar: se encolan y en cada tick
+// pasan todas al libro y se cruzan a un único precio de equilibrio, el que
+// maximiza el volumen ejecutado. Así dos órdenes que llegan en el mismo tick
+// no compiten por velocidad.
+
+use rust_decimal::prelude::*;
+
+use super::{Inverse, Order, OrderBook, OrderIndex, PriceLevel, RestingOrder, Side, Trade};
+use crate::config::{HiddenPriority, PriorityTieBreak};
+use crate::events::BookEvent;
+use crate::logging::book_log;
+
+// Saca del frente del nivel una orden agotada, o repone el pico de un iceberg.
+fn settle_front(level: &mut PriceLevel, index: &mut OrderIndex, hidden_priority: HiddenPriority) {
+    let Some(front) = level.orders.front() else { return };
+    if front.needs_refill() {
+        let mut resting = level.orders.pop_front().expect("orden al frente del nivel");
+        resting.refill();
+        level.requeue(resting, hidden_priority);
+    } else if front.order.amount.is_zero() {
+        let resting = level.orders.pop_front().expect("orden al frente del nivel");
+        index.remove(&resting.order);
+    }
+}
+
+// La más reciente de dos órdenes, según el mismo criterio que la prioridad en la cola.
+fn is_newer(a: &RestingOrder, b: &RestingOrder, tie_break: PriorityTieBreak) -> bool {
+    a.priority(tie_break) > b.priority(tie_break)
+}
+
+impl OrderBook {
+    /// Órdenes encoladas para el próximo tick.
+    pub fn pending_orders(&self) -> &[Order] {
+        &self.pending
+    }
+
+    /// Tick del modo batch: pasa las órdenes encoladas al libro y cruza todo lo
+    /// que se pueda a un único precio. El precio maximiza el volumen; a igual
+    /// volumen se elige el de menor desbalance, después el más cercano al precio
+    /// de referencia y por último el más bajo. Del par que cruza, la orden más
+    /// reciente figura como taker. En self-trades se cancela la más reciente.
+    /// También se puede llamar en modo continuo (no hace nada si el libro no cruza).
+    pub fn uncross(&mut self) -> Vec<Trade> {
+        self.emit(BookEvent::AuctionUncross);
+        for order in std::mem::take(&mut self.pending) {
+            self.rest(order);
+        }
+
+        let Some(price) = self.clearing_price() else { return Vec::new() };
+        book_log!("   🔨 Subasta: precio de cruce {}", price);
+        // Lo que cruce en la subasta ya no se puede deshacer
+        self.record_undo(Inverse::Irreversible);
+
+        let (stp, stp_scope) = (self.config.self_trade_prevention, self.config.stp_scope);
+        let (tie_break, hidden_priority) = (self.config.tie_break, self.config.hidden_priority);
+        let mut trades = Vec::new();
+        loop {
+            let prevailing_mid = self.mid_price();
+            let mut events = Vec::new();
+            let mut levels = Vec::new();
+            let (mut bid_removed, mut ask_removed) = (Decimal::zero(), Decimal::zero());
+            {
+                let (bids, asks) = self.sides.both_mut();
+                let (Some(bid_level), Some(ask_level)) = (bids.best_mut(), asks.best_mut()) else { break };
+                if bid_level.price < price || ask_level.price > price {
+                    break;
+                }
+                let (bid_price, ask_price) = (bid_level.price, ask_level.price);
+                let (Some(bid), Some(ask)) = (bid_level.orders.front_mut(), ask_level.orders.front_mut()) else { break };
+
+                if stp.is_some() && stp_scope.same_owner(&bid.order, &ask.order) {
+                    let (level, side) = if is_newer(bid, ask, tie_break) { (bid_level, Side::Buy) } else { (ask_level, Side::Sell) };
+                    let cancelled = level.orders.pop_front().expect("orden al frente del nivel").order;
+                    book_log!("   🚫 Self-trade evitado en subasta: se cancela #{} (cuenta {})", cancelled.id, cancelled.account);
+                    level.total -= cancelled.amount;
+                    match side {
+                        Side::Buy => bid_removed += cancelled.amount,
+                        Side::Sell => ask_removed += cancelled.amount,
+                    }
+                    self.index.remove(&cancelled);
+                    levels.push((side, level.price, level.visible_total()));
+                    events.push(BookEvent::OrderCancelled { order: cancelled, by_book: true });
+                } else {
+                    let amount = bid.executable().min(ask.executable());
+                    let (taker, maker) = if is_newer(bid, ask, tie_break) { (&*bid, &*ask) } else { (&*ask, &*bid) };
+                    let trade = Trade {
+                        trade_id: self.trade_ids.next(),
+                        maker_id: maker.order.id,
+                        taker_id: taker.order.id,
+                        price,
+                        amount,
+                        timestamp: taker.order.timestamp,
+                        aggressor: taker.order.side,
+                        prevailing_mid,
+                        taker_strategy_id: taker.order.strategy_id.clone(),
+                        maker_strategy_id: maker.order.strategy_id.clone(),
+                        taker_venue_tag: taker.order.venue_tag.clone(),
+                        maker_venue_tag: maker.order.venue_tag.clone(),
+                    };
+                    if let Err(e) = bid.fill(amount).and_then(|_| ask.fill(amount)) {
+                        book_log!("   💥 Subasta cortada: {}", e);
+                        break;
+                    }
+                    book_log!("   ⚡ MATCH EN SUBASTA: Compra #{} vs Venta #{} :: Cantidad {} @ {}", bid.order.id, ask.order.id, amount, price);
+                    self.positions.record(bid.order.account, Side::Buy, amount);
+                    self.positions.record(ask.order.account, Side::Sell, amount);
+                    bid_level.total -= amount;
+                    ask_level.total -= amount;
+                    (bid_removed, ask_removed) = (amount, amount);
+                    settle_front(bid_level, &mut self.index, hidden_priority);
+                    settle_front(ask_level, &mut self.index, hidden_priority);
+
+                    events.push(BookEvent::Trade { trade: trade.clone() });
+                    levels.push((Side::Buy, bid_price, bid_level.visible_total()));
+                    levels.push((Side::Sell, ask_price, ask_level.visible_total()));
+                    trades.push(trade);
+                    self.last_price = Some(price);
+                }
+            }
+            let (bids, asks) = self.sides.both_mut();
+            bids.volume -= bid_removed;
+            asks.volume -= ask_removed;
+            bids.remove_best_if_empty();
+            asks.remove_best_if_empty();
+            for event in events {
+                self.emit(event);
+            }
+            for (side, level_price, level_total) in levels {
+                self.emit_level(side, level_price, level_total);
+            }
+        }
+        self.refresh_reference_price();
+        self.check_fill_ratios(&trades);
+        self.sync_trade_holds(&trades);
+        trades
+    }
+
+    // Precio que maximiza el volumen cruzado entre lo que hay en el libro.
+    fn clearing_price(&self) -> Option<Decimal> {
+        let (best_bid, best_ask) = (self.best_bid()?, self.best_ask()?);
+        if best_bid < best_ask {
+            return None; // No cruza nada
+        }
+
+        let candidates = self.side(Side::Buy).levels().chain(self.side(Side::Sell).levels())
+            .map(|level| level.price)
+            .filter(|price| *price >= best_ask && *price <= best_bid);
+
+        let mut best: Option<(Decimal, Decimal, Decimal)> = None; // (precio, volumen, desbalance)
+        for price in candidates {
+            let demand: Decimal = self.side(Side::Buy).levels().take_while(|l| l.price >= price).map(PriceLevel::total).sum();
+            let supply: Decimal = self.side(Side::Sell).levels().take_while(|l| l.price <= price).map(PriceLevel::total).sum();
+            let (volume, imbalance) = (demand.min(supply), (demand - supply).abs());
+
+            let better = match best {
+                None => true,
+                Some((best_price, best_volume, best_imbalance)) => {
+                    let distance = |p: Decimal| self.reference_price.map(|r| (p - r).abs()).unwrap_or_default();
+                    (volume, -imbalance, -distance(price), -price) > (best_volume, -best_imbalance, -distance(best_price), -best_price)
+                }
+            };
+            if better {
+                best = Some((price, volume, imbalance));
+            }
+        }
+        best.filter(|(_, volume, _)| *volume > Decimal::zero()).map(|(price, _, _)| price)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal_macros::dec;
+
+    use super::*;
+    use crate::config::MatchingMode;
+    use crate::orderbook::tests::order;
+
+    fn prices(trades: &[Trade]) -> Vec<Decimal> {
+        trades.iter().map(|t| t.price).collect()
+    }
+
+    #[test]
+    fn orders_in_the_same_tick_cross_at_one_price() {
+        let arrivals = [order(1, Side::Sell, dec!(100), dec!(2)), order(2, Side::Sell, dec!(101), dec!(2)), order(3, Side::Buy, dec!(102), dec!(4))];
+
+        let mut continuous = OrderBook::new();
+        let sequential: Vec<_> = arrivals.iter().flat_map(|o| continuous.add_order(o.clone()).unwrap()).collect();
+        assert_eq!(prices(&sequential), [dec!(100), dec!(101)]);
+
+        let mut batch = OrderBook::builder().matching_mode(MatchingMode::Batch).build();
+        for o in arrivals {
+            assert!(batch.add_order(o).unwrap().is_empty());
+        }
+        assert_eq!(batch.pending_orders().len(), 3);
+        let trades = batch.uncross();
+        assert_eq!(prices(&trades), [dec!(101), dec!(101)]);
+        assert_eq!(trades.iter().map(|t| t.amount).sum::<Decimal>(), dec!(4));
+        assert!(batch.pending_orders().is_empty());
+    }
+}
@@ -0,0 +1,114 @@
+// --- CIRCUIT BREAKER DE VOLATILIDAD ---
+//
+// Con `BookConfig::circuit_breaker`, el libro guarda los precios de sus trades
+// de la última ventana. Si entre el mínimo y el máximo de la ventana hay más
+// de `max_move` (como fracción del mínimo), el libro pasa solo a `CancelOnly`;
+// la primera orden que llega pasado el `cooldown` lo vuelve a abrir. Los dos
+// cambios se publican como `TradingStateChanged`, igual que uno manual.
+
+use super::{OrderBook, Trade, TradingState};
+use crate::logging::book_log;
+
+impl OrderBook {
+    /// Hora del libro hasta la que dura el corte del circuit breaker, si está cortado.
+    pub fn breaker_until(&self) -> Option<u64> {
+        self.breaker_until
+    }
+
+    // Suma los precios de `trades` a la ventana y corta si el movimiento se pasa.
+    pub(super) fn check_breaker(&mut self, trades: &[Trade]) {
+        let Some(breaker) = self.config.circuit_breaker else { return };
+        if trades.is_empty() || self.breaker_until.is_some() {
+            return;
+        }
+        for trade in trades {
+            self.recent_prices.push_back((trade.timestamp, trade.price));
+        }
+        let now = self.recent_prices.back().map_or(0, |&(timestamp, _)| timestamp);
+        while let Some(&(timestamp, _)) = self.recent_prices.front()
+            && timestamp + breaker.window < now
+        {
+            self.recent_prices.pop_front();
+        }
+
+        let prices = self.recent_prices.iter().map(|&(_, price)| price);
+        let (Some(low), Some(high)) = (prices.clone().min(), prices.max()) else { return };
+        if low.is_zero() || (high - low) / low.abs() <= breaker.max_move {
+            return;
+        }
+        let until = self.current_time().max(now) + breaker.cooldown;
+        book_log!("   🔌 Circuit breaker: el precio se movió de {} a {} en {} ms, CancelOnly hasta {}", low, high, breaker.window, until);
+        self.recent_prices.clear();
+        self.breaker_until = Some(until);
+        self.set_trading_state(TradingState::CancelOnly);
+    }
+
+    // Reabre el libro si el corte ya cumplió su cooldown. Si mientras tanto
+    // alguien cambió el estado a mano, el corte se da por terminado sin tocarlo.
+    pub(super) fn resume_breaker(&mut self) {
+        let Some(until) = self.breaker_until else { return };
+        if self.trading_state != TradingState::CancelOnly {
+            self.breaker_until = None;
+            return;
+        }
+        if self.current_time() >= until {
+            book_log!("   🔌 Circuit breaker: termina el cooldown, se reanuda la negociación");
+            self.breaker_until = None;
+            self.set_trading_state(TradingState::Open);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal::Decimal;
+    use rust_decimal_macros::dec;
+
+    use super::*;
+    use crate::clock::VirtualClock;
+    use crate::error::OrderError;
+    use crate::orderbook::Side;
+    use crate::orderbook::tests::order;
+
+    // 5% en 1 s, con 500 ms de cooldown.
+    fn breaker_book() -> (OrderBook, VirtualClock) {
+        let clock = VirtualClock::new(0);
+        let book = OrderBook::builder().clock(clock.clone()).circuit_breaker(dec!(0.05), 1_000, 500).build();
+        (book, clock)
+    }
+
+    fn trade_at(book: &mut OrderBook, id: u64, price: Decimal) {
+        book.add_order(order(id, Side::Sell, price, dec!(1))).unwrap();
+        book.add_order(order(id + 1, Side::Buy, price, dec!(1))).unwrap();
+    }
+
+    #[test]
+    fn a_fast_move_trips_the_breaker_until_the_cooldown_ends() {
+        let (mut book, clock) = breaker_book();
+        trade_at(&mut book, 1, dec!(100));
+        clock.set(200);
+        book.add_order(order(3, Side::Buy, dec!(90), dec!(1))).unwrap();
+        trade_at(&mut book, 4, dec!(106));
+        assert_eq!(book.trading_state(), TradingState::CancelOnly);
+        assert_eq!(book.breaker_until(), Some(700));
+
+        // Cortado: no entran órdenes, pero se puede cancelar
+        clock.set(699);
+        assert_eq!(book.add_order(order(6, Side::Buy, dec!(100), dec!(1))), Err(OrderError::MarketHalted(TradingState::CancelOnly)));
+        assert!(book.cancel_order(3).is_some());
+
+        // Pasado el cooldown la primera orden lo reabre
+        clock.set(700);
+        assert!(book.add_order(order(7, Side::Buy, dec!(100), dec!(1))).is_ok());
+        assert_eq!((book.trading_state(), book.breaker_until()), (TradingState::Open, None));
+    }
+
+    #[test]
+    fn a_move_spread_over_more_than_the_window_does_not_trip() {
+        let (mut book, clock) = breaker_book();
+        trade_at(&mut book, 1, dec!(100));
+        clock.set(1_500);
+        trade_at(&mut book, 3, dec!(106));
+        assert_eq!((book.trading_state(), book.breaker_until()), (TradingState::Open, None));
+    }
+}
@@ -0,0 +1,200 @@
+// --- CONSTRUCTOR DE ÓRDENES ---
+//
+// Armar un `Order` literal obliga a completar todos los campos, y cada campo
+// nuevo rompe todos los literales. `OrderBuilder` pide sólo lo indispensable
+// (id, lado, precio, cantidad) y deja el resto en sus valores por defecto: una
+// límite `Gtc` visible de la cuenta 0, sin timestamp (con reloj, lo pone el libro).
+
+use rust_decimal::prelude::*;
+
+use super::{Order, OrderType, Side, TimeInForce, Visibility};
+use crate::error::OrderError;
+
+/// Constructor de `Order` con valores por defecto para lo opcional.
+#[derive(Debug, Clone)]
+pub struct OrderBuilder {
+    order: Order,
+}
+
+impl Order {
+    pub fn builder(id: u64, side: Side, price: Decimal, amount: Decimal) -> OrderBuilder {
+        OrderBuilder::new(id, side, price, amount)
+    }
+}
+
+impl OrderBuilder {
+    pub fn new(id: u64, side: Side, price: Decimal, amount: Decimal) -> Self {
+        Self {
+            order: Order {
+                id,
+                price,
+                amount,
+                side,
+                timestamp: 0,
+                account: 0,
+                subaccount: None,
+                min_fill: None,
+                price_cap: None,
+                cancel_at_fill_ratio: None,
+                client_order_id: None,
+                strategy_id: None,
+                venue_tag: None,
+                order_type: OrderType::Limit,
+                visibility: Visibility::Visible,
+                time_in_force: TimeInForce::Gtc,
+            },
+        }
+    }
+
+    pub fn timestamp(mut self, timestamp: u64) -> Self {
+        self.order.timestamp = timestamp;
+        self
+    }
+
+    pub fn account(mut self, account: u64) -> Self {
+        self.order.account = account;
+        self
+    }
+
+    pub fn subaccount(mut self, subaccount: u64) -> Self {
+        self.order.subaccount = Some(subaccount);
+        self
+    }
+
+    pub fn min_fill(mut self, min_fill: Decimal) -> Self {
+        self.order.min_fill = Some(min_fill);
+        self
+    }
+
+    pub fn price_cap(mut self, cap: Decimal) -> Self {
+        self.order.price_cap = Some(cap);
+        self
+    }
+
+    /// Cancela el resto cuando se ejecutó `ratio` del tamaño original (0.8 = 80%).
+    pub fn cancel_at_fill_ratio(mut self, ratio: Decimal) -> Self {
+        self.order.cancel_at_fill_ratio = Some(ratio);
+        self
+    }
+
+    pub fn client_order_id(mut self, client_order_id: impl Into<String>) -> Self {
+        self.order.client_order_id = Some(client_order_id.into());
+        self
+    }
+
+    pub fn strategy_id(mut self, strategy_id: impl Into<String>) -> Self {
+        self.order.strategy_id = Some(strategy_id.into());
+        self
+    }
+
+    pub fn venue_tag(mut self, venue_tag: impl Into<String>) -> Self {
+        self.order.venue_tag = Some(venue_tag.into());
+        self
+    }
+
+    pub fn order_type(mut self, order_type: OrderType) -> Self {
+        self.order.order_type = order_type;
+        self
+    }
+
+    pub fn visibility(mut self, visibility: Visibility) -> Self {
+        self.order.visibility = visibility;
+        self
+    }
+
+    pub fn time_in_force(mut self, time_in_force: TimeInForce) -> Self {
+        self.order.time_in_force = time_in_force;
+        self
+    }
+
+    /// Devuelve la orden si es coherente por sí misma: cantidad, pico de
+    /// iceberg, fill mínimo, presupuesto y offset de pegged positivos. Lo que
+    /// depende del libro (precio, banda, lote, vencimiento) lo sigue validando
+    /// `add_order`.
+    pub fn build(self) -> Result<Order, OrderError> {
+        let order = self.order;
+        if order.amount <= Decimal::zero() {
+            return Err(OrderError::InvalidAmount(order.amount));
+        }
+        if let Visibility::Iceberg { peak } = order.visibility
+            && peak <= Decimal::zero()
+        {
+            return Err(OrderError::InvalidPeak(peak));
+        }
+        if let Some(min_fill) = order.min_fill
+            && min_fill <= Decimal::zero()
+        {
+            return Err(OrderError::InvalidAmount(min_fill));
+        }
+        match order.order_type {
+            OrderType::MarketQuote { budget } if budget <= Decimal::zero() => Err(OrderError::InvalidAmount(budget)),
+            OrderType::PegToOpposite { offset, .. } if offset <= Decimal::zero() => Err(OrderError::InvalidPrice(offset)),
+            _ => Ok(order),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal_macros::dec;
+
+    use super::*;
+
+    #[test]
+    fn a_minimal_order_gets_the_defaults() {
+        let order = Order::builder(1, Side::Buy, dec!(100), dec!(2)).build().unwrap();
+        assert_eq!(order, Order {
+            id: 1,
+            price: dec!(100),
+            amount: dec!(2),
+            side: Side::Buy,
+            timestamp: 0,
+            account: 0,
+            subaccount: None,
+            min_fill: None,
+            price_cap: None,
+            cancel_at_fill_ratio: None,
+            client_order_id: None,
+            strategy_id: None,
+            venue_tag: None,
+            order_type: OrderType::Limit,
+            visibility: Visibility::Visible,
+            time_in_force: TimeInForce::Gtc,
+        });
+    }
+
+    #[test]
+    fn every_field_can_be_set() {
+        let order = Order::builder(2, Side::Sell, dec!(101), dec!(10))
+            .timestamp(5)
+            .account(7)
+            .subaccount(3)
+            .min_fill(dec!(2))
+            .price_cap(dec!(99))
+            .cancel_at_fill_ratio(dec!(0.8))
+            .client_order_id("c-1")
+            .strategy_id("mm")
+            .venue_tag("dark")
+            .order_type(OrderType::AllOrNone)
+            .visibility(Visibility::Iceberg { peak: dec!(4) })
+            .time_in_force(TimeInForce::Day)
+            .build()
+            .unwrap();
+        assert_eq!((order.timestamp, order.account, order.subaccount), (5, 7, Some(3)));
+        assert_eq!((order.min_fill, order.price_cap, order.cancel_at_fill_ratio), (Some(dec!(2)), Some(dec!(99)), Some(dec!(0.8))));
+        assert_eq!(
+            (order.client_order_id.as_deref(), order.strategy_id.as_deref(), order.venue_tag.as_deref()),
+            (Some("c-1"), Some("mm"), Some("dark"))
+        );
+        assert_eq!((order.order_type, order.visibility, order.time_in_force), (OrderType::AllOrNone, Visibility::Iceberg { peak: dec!(4) }, TimeInForce::Day));
+    }
+
+    #[test]
+    fn build_rejects_an_incoherent_order() {
+        let builder = || Order::builder(3, Side::Buy, dec!(100), dec!(1));
+        assert_eq!(Order::builder(3, Side::Buy, dec!(100), dec!(0)).build(), Err(OrderError::InvalidAmount(dec!(0))));
+        assert_eq!(builder().visibility(Visibility::Iceberg { peak: dec!(0) }).build(), Err(OrderError::InvalidPeak(dec!(0))));
+        assert_eq!(builder().min_fill(dec!(-1)).build(), Err(OrderError::InvalidAmount(dec!(-1))));
+        assert_eq!(builder().order_type(OrderType::MarketQuote { budget: dec!(0) }).build(), Err(OrderError::InvalidAmount(dec!(0))));
+    }
+}
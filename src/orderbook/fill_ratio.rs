@@ -0,0 +1,103 @@
+// --- CANCELACIÓN POR FRACCIÓN EJECUTADA ---
+//
+// Una orden con `cancel_at_fill_ratio` cancela su resto en cuanto lo ejecutado
+// llega a esa fracción de su tamaño original (0.8 = cancelar pasado el 80%),
+// para los algoritmos que están cerrando una ejecución. El libro guarda el
+// tamaño con que entró y va sumando sus fills; la cancelación se publica como
+// `OrderCancelled`, igual que una del cliente, pero no entra en el log de
+// undo: deshacer el comando que la disparó sigue fallando por sus fills. Una
+// modificación que pierde prioridad entra como orden nueva y vuelve a contar
+// desde cero.
+
+use rust_decimal::prelude::*;
+
+use super::{Order, OrderBook, Trade};
+use crate::logging::book_log;
+
+impl OrderBook {
+    /// Fracción del tamaño original que lleva ejecutada la orden `id`, si
+    /// tiene `cancel_at_fill_ratio` y sigue viva.
+    pub fn filled_ratio(&self, id: u64) -> Option<Decimal> {
+        self.fill_progress.get(&id).map(|&(original, filled)| filled / original)
+    }
+
+    // Empieza a seguir los fills de una orden entrante con la opción.
+    pub(super) fn track_fill_ratio(&mut self, order: &Order) {
+        if order.cancel_at_fill_ratio.is_some() {
+            self.fill_progress.insert(order.id, (order.amount, Decimal::zero()));
+        }
+    }
+
+    // Suma los fills de `trades` y cancela el resto de las órdenes que pasaron
+    // su fracción. De paso olvida las que ya no están vivas.
+    pub(super) fn check_fill_ratios(&mut self, trades: &[Trade]) {
+        if self.fill_progress.is_empty() {
+            return;
+        }
+        for trade in trades {
+            for id in [trade.maker_id, trade.taker_id] {
+                if let Some((_, filled)) = self.fill_progress.get_mut(&id) {
+                    *filled += trade.amount;
+                }
+            }
+        }
+
+        let tracked: Vec<(u64, Decimal, Decimal)> = self.fill_progress.iter().map(|(&id, &(original, filled))| (id, original, filled)).collect();
+        for (id, original, filled) in tracked {
+            let Some(ratio) = self.order(id).and_then(|order| order.cancel_at_fill_ratio) else {
+                // Ejecutada, cancelada o todavía esperando el tick del modo batch
+                if !self.pending.iter().any(|o| o.id == id) {
+                    self.fill_progress.remove(&id);
+                }
+                continue;
+            };
+            if filled >= original * ratio {
+                book_log!("   🏁 Orden #{} ejecutó {} de {} (≥ {}): se cancela el resto", id, filled, original, ratio);
+                self.fill_progress.remove(&id);
+                self.cancel_by_book(id);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal_macros::dec;
+
+    use super::*;
+    use crate::error::OrderError;
+    use crate::events::BookEvent;
+    use crate::orderbook::Side;
+    use crate::orderbook::tests::order;
+
+    #[test]
+    fn the_remainder_is_cancelled_once_the_ratio_is_filled() {
+        let mut book = OrderBook::new();
+        book.enable_events();
+        book.add_order(Order { cancel_at_fill_ratio: Some(dec!(0.8)), ..order(1, Side::Sell, dec!(100), dec!(10)) }).unwrap();
+
+        book.add_order(order(2, Side::Buy, dec!(100), dec!(5))).unwrap();
+        assert_eq!(book.filled_ratio(1), Some(dec!(0.5)));
+        book.drain_events();
+
+        // 8 de 10: los 2 que quedan se cancelan solos
+        book.add_order(order(3, Side::Buy, dec!(100), dec!(3))).unwrap();
+        assert!(book.order(1).is_none());
+        assert_eq!(book.filled_ratio(1), None);
+        let events: Vec<_> = book.drain_events().into_iter().map(|e| e.event).collect();
+        assert!(events.iter().any(|e| matches!(e, BookEvent::OrderCancelled { order, .. } if order.id == 1 && order.amount == dec!(2))), "{events:?}");
+
+        // La cancelación automática no es un comando: deshacer sigue chocando con los fills
+        assert_eq!(book.undo_last(), Err(OrderError::NotSupported("deshacer un comando que generó fills")));
+        assert!(book.is_empty());
+    }
+
+    #[test]
+    fn a_taker_past_its_ratio_does_not_rest_the_remainder() {
+        let mut book = OrderBook::new();
+        book.add_order(order(1, Side::Sell, dec!(100), dec!(6))).unwrap();
+        let ack = book.submit(Order { cancel_at_fill_ratio: Some(dec!(0.5)), ..order(2, Side::Buy, dec!(100), dec!(10)) }).unwrap();
+        assert_eq!((ack.filled_qty, ack.resting_qty, ack.cancelled_qty), (dec!(6), dec!(0), dec!(4)));
+        assert!(book.is_empty());
+    }
+}
@@ -0,0 +1,208 @@
+// --- LAST LOOK ---
+//
+// Con `BookConfig::last_look`, un fill del matching continuo no es definitivo
+// al cruzar: queda como `PendingTrade` y se publica como `TradePending`. El
+// maker tiene `window` ms (hora del libro) para mirarlo y responder con
+// `accept_trade` o `reject_trade`; si no responde, el fill se acepta solo al
+// vencer la ventana (se revisa al llegar cada orden, o con `expire_last_look`).
+// Al aceptarse recién se publica el `Trade`: ahí se liquida, cuenta para el
+// circuit breaker y llega al `SettlementHandler`. Un rechazo anula el fill:
+// las posiciones vuelven atrás, pero ninguna de las dos órdenes recupera la
+// cantidad. Para el maker es el sentido del last look (retira la cotización);
+// para el taker es una decisión: cuando llega el rechazo su orden ya se
+// resolvió (el resto descansó o se canceló según su time-in-force), y volver
+// a meter esa cantidad tarde le daría una prioridad que no ganó contra un
+// libro que ya cambió. La cantidad rechazada se da por cancelada, como el
+// resto de un IOC; si quiere, el cliente la reenvía como orden nueva.
+//
+// Las respuestas explícitas se publican como `LastLookDecision`, así que el
+// journal las reproduce; los vencimientos se regeneran solos con la hora.
+
+use rust_decimal::prelude::*;
+
+use super::{OrderBook, Side, Trade};
+use crate::events::BookEvent;
+use crate::logging::book_log;
+
+/// Fill esperando la respuesta del maker.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PendingTrade {
+    pub trade: Trade,
+    /// Hora del libro a la que se acepta solo.
+    pub deadline: u64,
+    pub taker_account: u64,
+    pub maker_account: u64,
+}
+
+// Evento de un fill recién cruzado: con last look (`deadline`) queda pendiente
+// en `pending`, si no se publica como trade definitivo. Es una función suelta
+// para poder usarla mientras el matching tiene prestado el nivel.
+pub(super) fn trade_event(pending: &mut Vec<PendingTrade>, deadline: Option<u64>, trade: &Trade, taker_account: u64, maker_account: u64) -> BookEvent {
+    let Some(deadline) = deadline else { return BookEvent::Trade { trade: trade.clone() } };
+    pending.push(PendingTrade { trade: trade.clone(), deadline, taker_account, maker_account });
+    BookEvent::TradePending { trade: trade.clone() }
+}
+
+impl OrderBook {
+    /// Fills esperando la respuesta de su maker, el más viejo primero.
+    pub fn pending_trades(&self) -> &[PendingTrade] {
+        &self.pending_trades
+    }
+
+    /// El maker acepta el fill `trade_id`: se publica como `Trade` y se
+    /// devuelve. `None` si no está pendiente (ya se decidió o venció).
+    pub fn accept_trade(&mut self, trade_id: u64) -> Option<Trade> {
+        self.expire_last_look();
+        let pending = self.take_pending_trade(trade_id)?;
+        self.emit(BookEvent::LastLookDecision { trade_id, accepted: true });
+        Some(self.finalize_trade(pending.trade))
+    }
+
+    /// El maker rechaza el fill `trade_id` dentro de su ventana: el trade se
+    /// anula y se devuelve. La cantidad queda cancelada para las dos órdenes:
+    /// el taker no la recupera ni se reintenta contra el libro; para ejecutarla
+    /// tiene que enviar otra orden. `None` si no está pendiente: en particular,
+    /// si la ventana ya venció el fill quedó aceptado.
+    pub fn reject_trade(&mut self, trade_id: u64) -> Option<Trade> {
+        self.expire_last_look();
+        let pending = self.take_pending_trade(trade_id)?;
+        let trade = pending.trade;
+        book_log!("   🙅 Last look: el maker #{} rechaza el trade #{} ({} @ {})", trade.maker_id, trade_id, trade.amount, trade.price);
+        self.emit(BookEvent::LastLookDecision { trade_id, accepted: false });
+        book_log!("   🗑️  Orden #{}: los {} rechazados quedan cancelados", trade.taker_id, trade.amount);
+        self.positions.record(pending.taker_account, trade.aggressor.opposite(), trade.amount);
+        self.positions.record(pending.maker_account, trade.aggressor, trade.amount);
+        self.sync_hold(trade.maker_id);
+        self.sync_hold(trade.taker_id);
+        Some(trade)
+    }
+
+    /// Acepta los fills cuya ventana ya venció y los devuelve, en orden.
+    pub fn expire_last_look(&mut self) -> Vec<Trade> {
+        let now = self.current_time();
+        let (expired, waiting) = std::mem::take(&mut self.pending_trades).into_iter().partition::<Vec<_>, _>(|p| p.deadline <= now);
+        self.pending_trades = waiting;
+        expired
+            .into_iter()
+            .map(|pending| {
+                book_log!("   ⌛ Last look: vence la ventana del trade #{}, se acepta", pending.trade.trade_id);
+                self.finalize_trade(pending.trade)
+            })
+            .collect()
+    }
+
+    // Saca de la lista, sin publicar nada, los trades que quedaron pendientes:
+    // lo que devuelve un comando son sólo los fills definitivos.
+    pub(super) fn without_pending(&self, mut trades: Vec<Trade>) -> Vec<Trade> {
+        if !self.pending_trades.is_empty() {
+            trades.retain(|t| !self.pending_trades.iter().any(|p| p.trade.trade_id == t.trade_id));
+        }
+        trades
+    }
+
+    // Lo que la orden `id` todavía tiene que pagar por sus fills pendientes:
+    // nocional si compra, cantidad si vende. Su reserva no puede bajar de ahí.
+    pub(super) fn last_look_owed(&self, id: u64) -> Decimal {
+        self.pending_trades
+            .iter()
+            .map(|p| &p.trade)
+            .map(|t| {
+                let (buyer, seller) = match t.aggressor {
+                    Side::Buy => (t.taker_id, t.maker_id),
+                    Side::Sell => (t.maker_id, t.taker_id),
+                };
+                if id == buyer {
+                    t.price * t.amount
+                } else if id == seller {
+                    t.amount
+                } else {
+                    Decimal::zero()
+                }
+            })
+            .sum()
+    }
+
+    fn take_pending_trade(&mut self, trade_id: u64) -> Option<PendingTrade> {
+        let pos = self.pending_trades.iter().position(|p| p.trade.trade_id == trade_id)?;
+        Some(self.pending_trades.remove(pos))
+    }
+
+    // Publica un fill aceptado como trade definitivo.
+    fn finalize_trade(&mut self, trade: Trade) -> Trade {
+        self.emit(BookEvent::Trade { trade: trade.clone() });
+        self.sync_hold(trade.maker_id);
+        self.sync_hold(trade.taker_id);
+        self.check_breaker(std::slice::from_ref(&trade));
+        trade
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal_macros::dec;
+
+    use super::*;
+    use crate::clock::VirtualClock;
+    use crate::orderbook::tests::order;
+
+    // Libro con 50 ms de last look, un maker de 3 en 100 y un taker que lo cruza en t=0.
+    fn pending_fill() -> (OrderBook, VirtualClock, u64) {
+        let clock = VirtualClock::new(0);
+        let mut book = OrderBook::builder().clock(clock.clone()).last_look(50).build();
+        book.enable_events();
+        book.add_order(order(1, Side::Sell, dec!(100), dec!(3))).unwrap();
+        let ack = book.submit(order(2, Side::Buy, dec!(100), dec!(2))).unwrap();
+        assert_eq!((ack.filled_qty, ack.pending_qty), (dec!(0), dec!(2)));
+        let trade_id = book.pending_trades()[0].trade.trade_id;
+        assert_eq!(book.pending_trades()[0].deadline, 50);
+        (book, clock, trade_id)
+    }
+
+    fn events(book: &mut OrderBook) -> Vec<BookEvent> {
+        book.drain_events().into_iter().map(|e| e.event).collect()
+    }
+
+    #[test]
+    fn a_reject_within_the_window_voids_the_trade() {
+        let (mut book, clock, trade_id) = pending_fill();
+        assert_eq!((book.positions().get(1), book.positions().get(2)), (dec!(-2), dec!(2)));
+        book.drain_events();
+
+        clock.set(49);
+        let voided = book.reject_trade(trade_id).unwrap();
+        assert_eq!((voided.maker_id, voided.taker_id, voided.amount), (1, 2, dec!(2)));
+        assert!(book.pending_trades().is_empty());
+        assert_eq!(events(&mut book), [BookEvent::LastLookDecision { trade_id, accepted: false }]);
+        assert_eq!((book.positions().get(1), book.positions().get(2)), (dec!(0), dec!(0)));
+        // Ninguna de las dos recupera la cantidad rechazada
+        assert_eq!(book.order(1).map(|o| o.amount), Some(dec!(1)));
+        assert!(book.order(2).is_none());
+        // Ya decidido: ni aceptar ni rechazar otra vez
+        assert_eq!(book.accept_trade(trade_id), None);
+    }
+
+    #[test]
+    fn without_an_answer_the_fill_is_accepted_when_the_window_ends() {
+        let (mut book, clock, trade_id) = pending_fill();
+        book.drain_events();
+
+        clock.set(49);
+        assert!(book.expire_last_look().is_empty());
+        clock.set(50);
+        let accepted = book.expire_last_look();
+        assert_eq!(accepted.iter().map(|t| t.trade_id).collect::<Vec<_>>(), [trade_id]);
+        assert_eq!(events(&mut book), [BookEvent::Trade { trade: accepted[0].clone() }]);
+        assert_eq!((book.positions().get(1), book.positions().get(2)), (dec!(-2), dec!(2)));
+        // Vencida, un rechazo tardío no la anula
+        assert_eq!(book.reject_trade(trade_id), None);
+    }
+
+    #[test]
+    fn an_explicit_accept_publishes_the_trade() {
+        let (mut book, _clock, trade_id) = pending_fill();
+        book.drain_events();
+        let trade = book.accept_trade(trade_id).unwrap();
+        assert_eq!(events(&mut book), [BookEvent::LastLookDecision { trade_id, accepted: true }, BookEvent::Trade { trade }]);
+        assert!(book.pending_trades().is_empty());
+    }
+}
@@ -0,0 +1,100 @@
+// --- ÓRDENES PEGGED AL LADO CONTRARIO ---
+//
+// Una orden `PegToOpposite` no tiene precio propio: descansa a `offset` del
+// mejor precio contrario (una compra, debajo del mejor ask; una venta, encima
+// del mejor bid) y nunca pasa de su límite de protección. Después de cada
+// comando se recalcula el precio de las pegged; la que cambia de precio vuelve
+// a la cola de su nuevo nivel como una orden recién llegada.
+//
+// El recálculo es una sola pasada, por id: dos pegged enfrentadas no se
+// persiguen sin fin dentro de un mismo comando. Como el offset es positivo,
+// una pegged nunca queda cruzando.
+
+use rust_decimal::Decimal;
+
+use super::{Order, OrderBook, OrderType, Side};
+use crate::logging::book_log;
+
+impl OrderBook {
+    // Precio que le toca a una pegged según el mejor precio contrario, acotado
+    // por su límite. Sin precio contrario descansa en el límite.
+    pub(super) fn peg_price(&self, order: &Order) -> Decimal {
+        let OrderType::PegToOpposite { offset, limit } = order.order_type else { return order.price };
+        match order.side {
+            Side::Buy => self.best_ask().map_or(limit, |ask| (ask - offset).min(limit)),
+            Side::Sell => self.best_bid().map_or(limit, |bid| (bid + offset).max(limit)),
+        }
+    }
+
+    // Mueve las pegged cuyo precio cambió. Las que ya no están en el libro
+    // (ejecutadas o canceladas) se olvidan acá.
+    pub(super) fn repeg(&mut self) {
+        if self.pegs.is_empty() {
+            return;
+        }
+        let ids: Vec<u64> = self.pegs.iter().copied().collect();
+        for id in ids {
+            let Some((side, price)) = self.index.get(id) else {
+                self.pegs.remove(&id);
+                continue;
+            };
+            let target = match self.order(id) {
+                Some(order) => self.peg_price(order),
+                None => continue,
+            };
+            if target == price {
+                continue;
+            }
+            let Some((resting, _, level_total)) = self.side_mut(side).remove(id, price) else { continue };
+            self.index.remove(&resting.order);
+            self.emit_level(side, price, level_total);
+            book_log!("   📍 Pegged #{} se mueve de {} a {}", id, price, target);
+            let mut order = resting.order;
+            order.price = target;
+            self.rest(order);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal_macros::dec;
+
+    use super::*;
+    use crate::orderbook::tests::order;
+
+    #[test]
+    fn a_pegged_buy_follows_the_ask_up_to_its_limit() {
+        let mut book = OrderBook::new();
+        book.add_order(order(1, Side::Sell, dec!(100), dec!(1))).unwrap();
+        let peg = Order { order_type: OrderType::PegToOpposite { offset: dec!(1), limit: dec!(101) }, ..order(2, Side::Buy, dec!(0), dec!(1)) };
+        book.add_order(peg).unwrap();
+        assert_eq!(book.order(2).map(|o| o.price), Some(dec!(99)));
+
+        // El ask baja (sin llegar a la pegged): la pegged baja con él
+        book.add_order(order(3, Side::Sell, dec!(99.5), dec!(1))).unwrap();
+        assert_eq!(book.order(2).map(|o| o.price), Some(dec!(98.5)));
+
+        // El ask sube más allá del límite: la sigue hasta el límite y ahí se queda
+        book.add_order(order(4, Side::Sell, dec!(105), dec!(1))).unwrap();
+        book.cancel_order(3).unwrap();
+        assert_eq!(book.order(2).map(|o| o.price), Some(dec!(99)));
+        book.cancel_order(1).unwrap();
+        assert_eq!(book.order(2).map(|o| o.price), Some(dec!(101)));
+        assert_eq!(book.best_bid(), Some(dec!(101)));
+    }
+
+    #[test]
+    fn a_repegged_order_goes_to_the_back_of_its_new_level() {
+        let mut book = OrderBook::new();
+        book.add_order(order(1, Side::Buy, dec!(100), dec!(1))).unwrap();
+        let peg = Order { order_type: OrderType::PegToOpposite { offset: dec!(2), limit: dec!(90) }, ..order(2, Side::Sell, dec!(0), dec!(1)) };
+        book.add_order(peg).unwrap();
+        assert_eq!(book.order(2).map(|o| o.price), Some(dec!(102)));
+        book.add_order(order(3, Side::Sell, dec!(103), dec!(1))).unwrap();
+
+        book.add_order(order(4, Side::Buy, dec!(101), dec!(1))).unwrap();
+        assert_eq!(book.order(2).map(|o| o.price), Some(dec!(103)));
+        assert_eq!(book.iter_orders(Side::Sell).map(|o| o.id).collect::<Vec<_>>(), [3, 2]);
+    }
+}
@@ -0,0 +1,215 @@
+// --- COTIZACIÓN DE DOS PUNTAS (MARKET MAKING) ---
+//
+// `quote` pone un bid y un ask post-only de una sola vez y `requote` los
+// reemplaza por un par nuevo. Los ids los asigna el libro, siguiendo al mayor
+// id que recibió hasta ahora.
+//
+// Si una punta recotizada al mismo precio conserva la prioridad o vuelve al
+// final de la cola lo decide `BookConfig::requote_priority`.
+
+use rust_decimal::Decimal;
+
+use super::{Order, OrderBook, OrderType, Side, TimeInForce, TradingState, Visibility};
+use crate::config::RequotePriority;
+use crate::error::OrderError;
+use crate::logging::book_log;
+
+impl OrderBook {
+    /// Pone un bid a `bid_price` y un ask a `ask_price`, ambos post-only por
+    /// `size`, y devuelve sus ids (bid, ask). Es todo o nada: si alguna de las
+    /// dos puntas se rechaza, no queda ninguna en el libro.
+    pub fn quote(&mut self, bid_price: Decimal, ask_price: Decimal, size: Decimal, account: u64) -> Result<(u64, u64), OrderError> {
+        if bid_price >= ask_price {
+            return Err(OrderError::PostOnlyWouldCross { price: bid_price, best: ask_price });
+        }
+        let bid = self.quote_order(Side::Buy, bid_price, size, account);
+        let ask = self.quote_order(Side::Sell, ask_price, size, account);
+        // Se validan las dos antes de tocar el libro
+        for order in [&bid, &ask] {
+            if let Err(e) = self.validate(order) {
+                self.record_reject(order, &e);
+                return Err(e);
+            }
+        }
+
+        let (bid_id, ask_id) = (bid.id, ask.id);
+        book_log!("   💱 Cotización cuenta {}: {} @ {} / {} (#{} / #{})", account, size, bid_price, ask_price, bid_id, ask_id);
+        self.add_order(bid)?;
+        if let Err(e) = self.add_order(ask) {
+            // El bid puede mover el mid lo suficiente como para sacar al ask de la banda
+            self.cancel_unchecked(bid_id);
+            return Err(e);
+        }
+        Ok((bid_id, ask_id))
+    }
+
+    /// Cancela las dos puntas de `previous` (lo que les quede) y cotiza de nuevo.
+    /// Con `RequotePriority::KeepSamePrice`, una punta que repite su precio sin
+    /// agrandarse se achica en el lugar: conserva su id y su lugar en la cola.
+    /// Si la nueva cotización se rechaza, la cuenta queda sin cotizar. Con
+    /// `MinRestingPolicy::Reject`, si alguna punta a retirar todavía no cumplió
+    /// el tiempo mínimo se devuelve `CancelTooSoon` sin tocar nada: la
+    /// cotización anterior sigue viva.
+    pub fn requote(
+        &mut self,
+        previous: (u64, u64),
+        bid_price: Decimal,
+        ask_price: Decimal,
+        size: Decimal,
+        account: u64,
+    ) -> Result<(u64, u64), OrderError> {
+        let keep_bid = self.keeps_priority(previous.0, bid_price, size);
+        let keep_ask = self.keeps_priority(previous.1, ask_price, size);
+        for (id, keep) in [(previous.0, keep_bid), (previous.1, keep_ask)] {
+            if !keep || bid_price >= ask_price {
+                self.check_can_pull(id)?;
+            }
+        }
+        if !keep_bid && !keep_ask || bid_price >= ask_price {
+            self.cancel_order(previous.0);
+            self.cancel_order(previous.1);
+            return self.quote(bid_price, ask_price, size, account);
+        }
+
+        let bid_id = match self.requote_leg(previous.0, keep_bid, Side::Buy, bid_price, size, account) {
+            Ok(id) => id,
+            Err(e) => {
+                self.cancel_unchecked(previous.1);
+                return Err(e);
+            }
+        };
+        let ask_id = match self.requote_leg(previous.1, keep_ask, Side::Sell, ask_price, size, account) {
+            Ok(id) => id,
+            Err(e) => {
+                self.cancel_unchecked(bid_id);
+                return Err(e);
+            }
+        };
+        book_log!("   💱 Recotización cuenta {}: {} @ {} / {} (#{} / #{})", account, size, bid_price, ask_price, bid_id, ask_id);
+        Ok((bid_id, ask_id))
+    }
+
+    // Con `KeepSamePrice`, `true` si la punta `id` sigue en reposo a `price`
+    // con al menos `size`: se recotiza achicándola en el lugar.
+    fn keeps_priority(&self, id: u64, price: Decimal, size: Decimal) -> bool {
+        self.config.requote_priority == RequotePriority::KeepSamePrice
+            && self.trading_state == TradingState::Open
+            && self.order(id).is_some_and(|order| order.price == price && size <= order.amount)
+    }
+
+    // Recotiza una punta: la achica en el lugar si conserva la prioridad, o la
+    // cancela y pone una nueva post-only. Devuelve el id de la punta vigente.
+    fn requote_leg(&mut self, previous: u64, keep: bool, side: Side, price: Decimal, size: Decimal, account: u64) -> Result<u64, OrderError> {
+        if keep && self.modify_order(previous, price, size).is_some() {
+            return Ok(previous);
+        }
+        self.check_can_pull(previous)?;
+        self.cancel_order(previous);
+        let order = self.quote_order(side, price, size, account);
+        let id = order.id;
+        if let Err(e) = self.validate(&order) {
+            self.record_reject(&order, &e);
+            return Err(e);
+        }
+        self.add_order(order)?;
+        Ok(id)
+    }
+
+    // Una punta de la cotización con el próximo id libre.
+    fn quote_order(&mut self, side: Side, price: Decimal, amount: Decimal, account: u64) -> Order {
+        self.last_order_id += 1;
+        Order {
+            id: self.last_order_id,
+            price,
+            amount,
+            side,
+            timestamp: self.current_time(),
+            account,
+            subaccount: None,
+            min_fill: None,
+            price_cap: None,
+            cancel_at_fill_ratio: None,
+            client_order_id: None,
+            strategy_id: None,
+            venue_tag: None,
+            order_type: OrderType::PostOnly,
+            visibility: Visibility::Visible,
+            time_in_force: TimeInForce::Gtc,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal_macros::dec;
+
+    use super::*;
+    use crate::config::MinRestingPolicy;
+    use crate::orderbook::tests::order;
+
+    #[test]
+    fn requote_replaces_both_legs_with_new_ids() {
+        let mut book = OrderBook::new();
+        book.add_order(order(10, Side::Buy, dec!(95), dec!(1))).unwrap();
+
+        let (bid, ask) = book.quote(dec!(99), dec!(101), dec!(2), 7).unwrap();
+        assert_eq!((bid, ask), (11, 12));
+        assert_eq!((book.best_bid(), book.best_ask()), (Some(dec!(99)), Some(dec!(101))));
+
+        let (new_bid, new_ask) = book.requote((bid, ask), dec!(98), dec!(102), dec!(3), 7).unwrap();
+        assert_eq!((new_bid, new_ask), (13, 14));
+        assert!(book.order(bid).is_none() && book.order(ask).is_none());
+        let leg = |id| book.order(id).map(|o| (o.side, o.price, o.amount, o.order_type));
+        assert_eq!(leg(new_bid), Some((Side::Buy, dec!(98), dec!(3), OrderType::PostOnly)));
+        assert_eq!(leg(new_ask), Some((Side::Sell, dec!(102), dec!(3), OrderType::PostOnly)));
+    }
+
+    #[test]
+    fn a_rejected_leg_leaves_no_quote() {
+        let mut book = OrderBook::new();
+        book.add_order(order(1, Side::Buy, dec!(100), dec!(1))).unwrap();
+
+        // El ask cruzaría el bid de 100
+        assert!(matches!(book.quote(dec!(98), dec!(100), dec!(1), 7), Err(OrderError::PostOnlyWouldCross { .. })));
+        assert_eq!(book.len(), 1);
+        assert_eq!(book.best_bid(), Some(dec!(100)));
+    }
+
+    // Cotiza 99/101 por 2 con otra orden detrás del bid y recotiza el bid al
+    // mismo precio y el ask a 102. Devuelve (id del bid vigente, volumen por
+    // delante del bid, id del ask vigente).
+    fn same_price_requote(priority: RequotePriority) -> (u64, Decimal, u64) {
+        let mut book = OrderBook::builder().requote_priority(priority).build();
+        let quote = book.quote(dec!(99), dec!(101), dec!(2), 7).unwrap();
+        book.add_order(order(20, Side::Buy, dec!(99), dec!(5))).unwrap();
+        let (bid, ask) = book.requote(quote, dec!(99), dec!(102), dec!(2), 7).unwrap();
+        assert_eq!((book.best_bid(), book.best_ask()), (Some(dec!(99)), Some(dec!(102))));
+        (bid, book.queue_position(bid).unwrap().0, ask)
+    }
+
+    #[test]
+    fn a_same_price_requote_keeps_or_resets_priority_per_config() {
+        // Se achica en el lugar: mismo id y primera en la cola
+        assert_eq!(same_price_requote(RequotePriority::KeepSamePrice), (1, dec!(0), 21));
+        // Cancel-replace: id nuevo, detrás de la orden 20
+        assert_eq!(same_price_requote(RequotePriority::Reset), (21, dec!(5), 22));
+    }
+
+    #[test]
+    fn a_fast_requote_under_reject_keeps_the_previous_quote() {
+        let clock = crate::clock::VirtualClock::new(0);
+        let mut book = OrderBook::builder().clock(clock.clone()).min_resting_time(100, MinRestingPolicy::Reject).build();
+        let quote = book.quote(dec!(99), dec!(101), dec!(2), 7).unwrap();
+
+        clock.set(50);
+        let error = book.requote(quote, dec!(98), dec!(102), dec!(2), 7).unwrap_err();
+        assert_eq!(error, OrderError::CancelTooSoon { id: quote.0, rested: 50, min: 100 });
+        assert_eq!(book.len(), 2, "una sola cotización viva");
+        assert_eq!((book.best_bid(), book.best_ask()), (Some(dec!(99)), Some(dec!(101))));
+
+        clock.set(100);
+        let requoted = book.requote(quote, dec!(98), dec!(102), dec!(2), 7).unwrap();
+        assert_eq!(book.len(), 2);
+        assert_eq!((book.order(requoted.0).map(|o| o.price), book.order(requoted.1).map(|o| o.price)), (Some(dec!(98)), Some(dec!(102))));
+    }
+}
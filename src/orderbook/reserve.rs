@@ -0,0 +1,143 @@
+// --- RESERVA DE SALDOS (ESCROW) ---
+//
+// Con `BookConfig::enforce_balances`, cada orden que entra reserva de su cuenta
+// lo que puede llegar a pagar (ver `Balances`) y se rechaza si no le alcanza.
+// Los fills se liquidan al publicarse cada `Trade` y las cancelaciones liberan
+// la reserva al publicarse su `OrderCancelled`, vengan de donde vengan
+// (cliente, vencimiento, self-trade prevention...). Después de cada comando la
+// reserva de las órdenes que ejecutaron se ajusta a lo que les queda: una
+// compra que ejecutó mejor que su límite recupera la diferencia.
+
+use rust_decimal::prelude::*;
+
+use super::{Order, OrderBook, Side, Trade};
+use crate::balances::{Asset, Balances, required_hold};
+use crate::error::OrderError;
+use crate::events::BookEvent;
+use crate::logging::book_log;
+
+impl OrderBook {
+    /// Acredita `amount` de `asset` como disponible en la cuenta. Se publica
+    /// como `BookEvent::Deposit`, así que el journal lo reproduce.
+    pub fn deposit(&mut self, account: u64, asset: Asset, amount: Decimal) -> Result<(), OrderError> {
+        if amount <= Decimal::zero() {
+            return Err(OrderError::InvalidAmount(amount));
+        }
+        book_log!("   🏦 Depósito en la cuenta {}: {} {:?}", account, amount, asset);
+        self.emit(BookEvent::Deposit { account, asset, amount });
+        self.balances.deposit(account, asset, amount);
+        Ok(())
+    }
+
+    /// Saldos disponibles y reservados de cada cuenta.
+    pub fn balances(&self) -> &Balances {
+        &self.balances
+    }
+
+    // Reserva lo que necesita una orden entrante.
+    pub(super) fn reserve(&mut self, order: &Order) -> Result<(), OrderError> {
+        if !self.config.enforce_balances {
+            return Ok(());
+        }
+        let (asset, amount) = required_hold(order)?;
+        self.balances.hold(order.id, order.account, asset, amount)
+    }
+
+    // Antes de una modificación que pierde prioridad: la reserva actual más lo
+    // disponible tienen que cubrir lo que pide la orden nueva.
+    pub(super) fn check_rehold(&self, order: &Order) -> Result<(), OrderError> {
+        if !self.config.enforce_balances {
+            return Ok(());
+        }
+        let (asset, amount) = required_hold(order)?;
+        if self.balances.can_hold(order.id, order.account, asset, amount) {
+            return Ok(());
+        }
+        let available = self.balances.get(order.account, asset).available + self.balances.held(order.id);
+        Err(OrderError::InsufficientBalance { account: order.account, asset, required: amount, available })
+    }
+
+    // Ajusta la reserva de `id` a lo que le queda a la orden, o la libera si ya no está.
+    pub(super) fn sync_hold(&mut self, id: u64) {
+        if !self.config.enforce_balances {
+            return;
+        }
+        let order = self.order(id).or_else(|| self.pending.iter().chain(&self.triggers).find(|o| o.id == id));
+        let required = order.and_then(|o| required_hold(o).ok()).map_or(Decimal::zero(), |(_, amount)| amount);
+        self.balances.set_hold(id, required + self.last_look_owed(id));
+    }
+
+    // `sync_hold` de todas las órdenes que participaron en `trades`.
+    pub(super) fn sync_trade_holds(&mut self, trades: &[Trade]) {
+        if !self.config.enforce_balances {
+            return;
+        }
+        for trade in trades {
+            self.sync_hold(trade.maker_id);
+            self.sync_hold(trade.taker_id);
+        }
+    }
+
+    // Mueve los saldos de un evento recién publicado: fills y cancelaciones.
+    pub(super) fn settle_event(&mut self, event: &BookEvent) {
+        if !self.config.enforce_balances {
+            return;
+        }
+        match event {
+            BookEvent::Trade { trade } => {
+                let notional = trade.price * trade.amount;
+                let (buyer, seller) = match trade.aggressor {
+                    Side::Buy => (trade.taker_id, trade.maker_id),
+                    Side::Sell => (trade.maker_id, trade.taker_id),
+                };
+                self.balances.settle(buyer, notional, trade.amount);
+                self.balances.settle(seller, trade.amount, notional);
+            }
+            // Lo que deben sus fills en last look sigue reservado hasta la respuesta
+            BookEvent::OrderCancelled { order, .. } => self.balances.set_hold(order.id, self.last_look_owed(order.id)),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal_macros::dec;
+
+    use super::*;
+    use crate::balances::Balance;
+    use crate::orderbook::tests::order;
+
+    fn funded_book() -> OrderBook {
+        let mut book = OrderBook::builder().enforce_balances(true).build();
+        book.deposit(1, Asset::Quote, dec!(1000)).unwrap();
+        book.deposit(2, Asset::Base, dec!(5)).unwrap();
+        book
+    }
+
+    #[test]
+    fn a_resting_order_reserves_its_funds_until_cancelled() {
+        let mut book = funded_book();
+        book.add_order(order(1, Side::Buy, dec!(100), dec!(4))).unwrap();
+        assert_eq!(book.balances().get(1, Asset::Quote), Balance { available: dec!(600), reserved: dec!(400) });
+        assert_eq!(book.balances().held(1), dec!(400));
+
+        book.cancel_order(1).unwrap();
+        assert_eq!(book.balances().get(1, Asset::Quote), Balance { available: dec!(1000), reserved: dec!(0) });
+        assert_eq!(book.balances().held(1), dec!(0));
+    }
+
+    #[test]
+    fn fills_consume_the_reserve_and_short_funds_are_rejected() {
+        let mut book = funded_book();
+        book.add_order(order(1, Side::Buy, dec!(100), dec!(4))).unwrap();
+        book.add_order(order(2, Side::Sell, dec!(100), dec!(3))).unwrap();
+        assert_eq!(book.balances().get(1, Asset::Quote), Balance { available: dec!(600), reserved: dec!(100) });
+        assert_eq!(book.balances().get(1, Asset::Base).available, dec!(3));
+        assert_eq!(book.balances().get(2, Asset::Quote).available, dec!(300));
+        assert_eq!(book.balances().get(2, Asset::Base), Balance { available: dec!(2), reserved: dec!(0) });
+
+        let error = book.add_order(order(3, Side::Buy, dec!(100), dec!(1))).unwrap_err();
+        assert_eq!(error, OrderError::InsufficientBalance { account: 3, asset: Asset::Quote, required: dec!(100), available: dec!(0) });
+    }
+}
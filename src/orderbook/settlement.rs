@@ -0,0 +1,112 @@
+// --- BUFFER DE LIQUIDACIÓN ---
+//
+// Con un `SettlementHandler` instalado, cada `Trade` publicado se guarda en un
+// buffer que se vacía hacia el handler al llegar a `settlement_batch` trades o
+// al pasar `settlement_interval` desde el primero del buffer. El intervalo se
+// mira cuando el libro publica un trade; para no dejar trades colgados en un
+// libro quieto hay que llamar a `flush_settlement` (p. ej. al apagar).
+
+use std::sync::Arc;
+
+use super::{OrderBook, Trade};
+use crate::settlement::SettlementHandler;
+
+impl OrderBook {
+    /// Instala el handler que recibe los lotes de trades a liquidar.
+    pub fn set_settlement_handler(&mut self, handler: Arc<dyn SettlementHandler>) {
+        self.settlement = Some(handler);
+    }
+
+    /// Entrega al handler lo que haya en el buffer, aunque no complete un lote.
+    /// Devuelve cuántos trades entregó.
+    pub fn flush_settlement(&mut self) -> usize {
+        self.unsettled_since = None;
+        let Some(handler) = &self.settlement else { return 0 };
+        if self.unsettled.is_empty() {
+            return 0;
+        }
+        let batch = std::mem::take(&mut self.unsettled);
+        handler.settle(&batch);
+        batch.len()
+    }
+
+    /// Trades publicados que todavía no se entregaron al handler.
+    pub fn pending_settlement(&self) -> usize {
+        self.unsettled.len()
+    }
+
+    // Suma un trade recién publicado al buffer y lo vacía si completó el lote
+    // o se pasó del intervalo.
+    pub(super) fn buffer_settlement(&mut self, trade: &Trade) {
+        if self.settlement.is_none() {
+            return;
+        }
+        let now = self.current_time();
+        let since = *self.unsettled_since.get_or_insert(now);
+        self.unsettled.push(trade.clone());
+        let full = self.unsettled.len() >= self.config.settlement_batch.max(1);
+        let overdue = self.config.settlement_interval.is_some_and(|interval| now.saturating_sub(since) >= interval);
+        if full || overdue {
+            self.flush_settlement();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use rust_decimal_macros::dec;
+
+    use super::*;
+    use crate::clock::VirtualClock;
+    use crate::orderbook::Side;
+    use crate::orderbook::tests::order;
+
+    // Guarda los ids de trade de cada lote recibido.
+    #[derive(Debug, Default)]
+    struct Batches(Mutex<Vec<Vec<u64>>>);
+
+    impl SettlementHandler for Batches {
+        fn settle(&self, trades: &[Trade]) {
+            self.0.lock().unwrap().push(trades.iter().map(|t| t.trade_id).collect());
+        }
+    }
+
+    // Cinco makers de 1 en 100 y un barrido que los toma a todos.
+    fn sweep(book: &mut OrderBook) {
+        for id in 1..=5 {
+            book.add_order(order(id, Side::Sell, dec!(100), dec!(1))).unwrap();
+        }
+        book.add_order(order(6, Side::Buy, dec!(100), dec!(5))).unwrap();
+    }
+
+    #[test]
+    fn trades_reach_the_handler_in_order_and_in_batches() {
+        let handler = Arc::new(Batches::default());
+        let mut book = OrderBook::builder().settlement_batch(2, None).build();
+        book.set_settlement_handler(handler.clone());
+        sweep(&mut book);
+        assert_eq!(*handler.0.lock().unwrap(), [vec![1, 2], vec![3, 4]]);
+        assert_eq!(book.pending_settlement(), 1);
+
+        assert_eq!(book.flush_settlement(), 1);
+        assert_eq!(book.flush_settlement(), 0);
+        assert_eq!(*handler.0.lock().unwrap(), [vec![1, 2], vec![3, 4], vec![5]]);
+    }
+
+    #[test]
+    fn an_overdue_buffer_flushes_with_the_next_trade() {
+        let clock = VirtualClock::new(0);
+        let handler = Arc::new(Batches::default());
+        let mut book = OrderBook::builder().clock(clock.clone()).settlement_batch(10, Some(50)).build();
+        book.set_settlement_handler(handler.clone());
+        sweep(&mut book);
+        assert!(handler.0.lock().unwrap().is_empty());
+
+        clock.set(50);
+        book.add_order(order(7, Side::Sell, dec!(100), dec!(1))).unwrap();
+        book.add_order(order(8, Side::Buy, dec!(100), dec!(1))).unwrap();
+        assert_eq!(*handler.0.lock().unwrap(), [vec![1, 2, 3, 4, 5, 6]]);
+    }
+}
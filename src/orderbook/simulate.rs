@@ -0,0 +1,191 @@
+// --- SIMULACIÓN (WHAT-IF) ---
+//
+// `simulate` responde "¿qué pasaría si mando esta orden?" sin tocar el libro:
+// arma un libro descartable con lo que usa el matching (los dos lados, para
+// que el mid de cada trade dé lo mismo, los vencimientos pendientes y las
+// posiciones, que frenan al llegar al límite) y corre ahí el mismo `execute`
+// que una orden real. `match_trace` usa esa misma simulación para explicar,
+// fill por fill, qué maker eligió el matching.
+
+use rust_decimal::Decimal;
+
+use super::{Order, OrderBook, OrderIndex, OrderType, Trade, TradeIdCounter};
+use crate::config::MatchingMode;
+use crate::logging;
+
+/// Por qué el matching eligió al maker de un `MatchStep`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatchReason {
+    /// Precio-tiempo: el mejor nivel contrario y, dentro de él, el primero de
+    /// la cola que podía ejecutar.
+    PriceTime,
+    /// Lo asignó el matcher a medida del libro (ver `OrderBook::set_matcher`).
+    Matcher,
+}
+
+/// Una decisión del matching: contra qué maker, a qué precio y cuánto, y dónde
+/// estaba ese maker en el libro cuando llegó la orden.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MatchStep {
+    pub maker_id: u64,
+    pub price: Decimal,
+    pub amount: Decimal,
+    /// Nivel del maker contando desde el mejor precio contrario (0 = el mejor).
+    pub level: usize,
+    /// Lugar del maker en la cola de su nivel (0 = el primero).
+    pub queue_pos: usize,
+    pub reason: MatchReason,
+}
+
+impl OrderBook {
+    /// Los fills de `simulate(order)`, en orden, con la posición de cada maker
+    /// en el libro actual. Sirve para depurar disputas de prioridad: no toca
+    /// el libro. Un maker que ejecuta varias veces (un iceberg que se repone)
+    /// aparece una vez por fill.
+    pub fn match_trace(&self, order: &Order) -> Vec<MatchStep> {
+        let reason = if self.matcher.is_some() { MatchReason::Matcher } else { MatchReason::PriceTime };
+        let opposite = self.side(order.side.opposite());
+        self.simulate(order)
+            .into_iter()
+            .map(|trade| {
+                let (level, queue_pos) = opposite.levels()
+                    .enumerate()
+                    .find_map(|(level, price_level)| {
+                        let pos = price_level.orders.iter().position(|o| o.order.id == trade.maker_id)?;
+                        Some((level, pos))
+                    })
+                    .unwrap_or_default();
+                MatchStep { maker_id: trade.maker_id, price: trade.price, amount: trade.amount, level, queue_pos, reason }
+            })
+            .collect()
+    }
+
+    /// Trades que generaría `order` si se enviara ahora, sin modificar el libro.
+    /// Son los mismos (ids incluidos) que daría un `add_order` inmediato, sin
+    /// contar las MIT que esos trades dispararían. Una pegged se simula al
+    /// precio que le fijaría el libro. Una orden que el libro rechazaría, una
+    /// MIT o cualquier orden en modo batch no genera trades.
+    pub fn simulate(&self, order: &Order) -> Vec<Trade> {
+        if self.config.matching_mode == MatchingMode::Batch {
+            return Vec::new(); // La orden se encolaría y cruzaría recién en el tick
+        }
+        if let OrderType::MarketIfTouched { .. } = order.order_type {
+            return Vec::new(); // Espera su disparador fuera del libro
+        }
+        let mut order = order.clone();
+        if let Some(now) = self.now() {
+            order.timestamp = now;
+        }
+
+        let mut scratch = self.scratch();
+        scratch.last_timestamp = scratch.last_timestamp.max(order.timestamp);
+        logging::silenced(|| {
+            scratch.expire_orders();
+            // Igual que en `add_order`: se fija el precio pegged y la escala antes de validar
+            order.price = scratch.peg_price(&order);
+            if self.normalize_scale(&mut order).and_then(|_| self.validate(&order)).is_err() {
+                return Vec::new();
+            }
+            scratch.execute(order).unwrap_or_default()
+        })
+    }
+
+    // Copia del libro sin buffer de eventos.
+    fn scratch(&self) -> OrderBook {
+        let mut scratch = OrderBook::with_config(self.config.clone());
+        let mut index = OrderIndex::default();
+        for order in self.resting_orders().map(|resting| &resting.order) {
+            index.insert(order);
+        }
+        scratch.sides = self.sides.clone();
+        scratch.index = index;
+        scratch.reference_price = self.reference_price;
+        scratch.last_price = self.last_price;
+        scratch.trade_ids = TradeIdCounter::starting_after(self.trade_ids.last());
+        scratch.matcher = self.matcher.clone();
+        scratch.trading_state = self.trading_state;
+        scratch.clock = self.clock.clone();
+        scratch.expiries = self.expiries.clone();
+        scratch.day_orders = self.day_orders.clone();
+        scratch.next_session_end = self.next_session_end;
+        scratch.positions = self.positions.clone();
+        scratch.last_timestamp = self.last_timestamp;
+        scratch
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal_macros::dec;
+
+    use super::*;
+    use crate::orderbook::Side;
+    use crate::orderbook::tests::order;
+
+    // Simula `order` y después la envía de verdad: los trades tienen que coincidir.
+    fn assert_simulation_matches(book: &mut OrderBook, order: Order) -> Vec<Trade> {
+        let simulated = book.simulate(&order);
+        let real = book.add_order(order).unwrap();
+        assert_eq!(simulated, real);
+        real
+    }
+
+    #[test]
+    fn simulate_matches_a_real_sweep_and_leaves_the_book_alone() {
+        let mut book = OrderBook::new();
+        book.add_order(order(1, Side::Sell, dec!(100), dec!(2))).unwrap();
+        book.add_order(order(2, Side::Sell, dec!(101), dec!(2))).unwrap();
+        book.add_order(order(3, Side::Buy, dec!(98), dec!(1))).unwrap();
+
+        let sweep = order(4, Side::Buy, dec!(101), dec!(3));
+        let resting = |book: &OrderBook| book.iter_orders(Side::Sell).map(|o| (o.id, o.amount)).collect::<Vec<_>>();
+        let before = resting(&book);
+        assert_eq!(book.simulate(&sweep).len(), 2);
+        assert_eq!(resting(&book), before);
+        assert_simulation_matches(&mut book, sweep);
+    }
+
+    #[test]
+    fn simulate_stops_at_the_position_limit() {
+        let mut book = OrderBook::builder().position_limit(dec!(5)).build();
+        book.add_order(order(1, Side::Sell, dec!(100), dec!(3))).unwrap();
+        book.add_order(Order { account: 9, ..order(2, Side::Buy, dec!(100), dec!(3)) }).unwrap();
+        book.add_order(order(3, Side::Sell, dec!(100), dec!(5))).unwrap();
+
+        let trades = assert_simulation_matches(&mut book, Order { account: 9, ..order(4, Side::Buy, dec!(100), dec!(5)) });
+        assert_eq!(trades.iter().map(|t| t.amount).sum::<Decimal>(), dec!(2));
+    }
+
+    #[test]
+    fn simulate_prices_a_pegged_order_like_the_book() {
+        let mut book = OrderBook::new();
+        book.add_order(order(1, Side::Sell, dec!(100), dec!(2))).unwrap();
+
+        // Con su límite cruzaría; pegged descansa un tick debajo del ask
+        let peg = Order { order_type: OrderType::PegToOpposite { offset: dec!(1), limit: dec!(105) }, ..order(2, Side::Buy, dec!(105), dec!(2)) };
+        assert!(assert_simulation_matches(&mut book, peg).is_empty());
+        assert_eq!(book.best_bid(), Some(dec!(99)));
+    }
+
+    #[test]
+    fn match_trace_lists_makers_in_price_time_order() {
+        let mut book = OrderBook::new();
+        book.add_order(order(1, Side::Sell, dec!(100), dec!(1))).unwrap();
+        book.add_order(order(2, Side::Sell, dec!(101), dec!(2))).unwrap();
+        book.add_order(order(3, Side::Sell, dec!(100), dec!(1))).unwrap();
+        book.add_order(order(4, Side::Sell, dec!(100), dec!(1))).unwrap();
+
+        let step = |maker_id, price, amount, level, queue_pos| MatchStep { maker_id, price, amount, level, queue_pos, reason: MatchReason::PriceTime };
+        let sweep = order(5, Side::Buy, dec!(101), dec!(4));
+        assert_eq!(book.match_trace(&sweep), [
+            step(1, dec!(100), dec!(1), 0, 0),
+            step(3, dec!(100), dec!(1), 0, 1),
+            step(4, dec!(100), dec!(1), 0, 2),
+            step(2, dec!(101), dec!(1), 1, 0),
+        ]);
+
+        // No toca el libro: el mismo barrido real consume en ese orden
+        let trades = book.add_order(sweep).unwrap();
+        assert_eq!(trades.iter().map(|t| t.maker_id).collect::<Vec<_>>(), [1, 3, 4, 2]);
+    }
+}
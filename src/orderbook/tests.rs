@@ -0,0 +1,1065 @@
+// Tests del núcleo del libro: matching, cancelaciones y consultas. Las
+// extensiones con módulo propio (subastas, breaker, last look...) tienen los
+// suyos al final de su archivo.
+
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+
+use super::*;
+
+// Límite GTC de la cuenta `id` (cuentas distintas: no hay self-trade), con
+// timestamp `id` para que el orden de llegada sea el de los ids. La usan
+// también los tests de los otros módulos.
+pub(crate) fn order(id: u64, side: Side, price: Decimal, amount: Decimal) -> Order {
+    Order::builder(id, side, price, amount).account(id).timestamp(id).build().unwrap()
+}
+
+// --- IGUALDAD Y DIFF ---
+
+#[test]
+fn identical_books_compare_equal_and_changes_show_in_the_diff() {
+    let build = || {
+        let mut book = OrderBook::new();
+        book.add_order(order(1, Side::Buy, dec!(99), dec!(5))).unwrap();
+        book.add_order(order(2, Side::Buy, dec!(99), dec!(3))).unwrap();
+        book.add_order(order(3, Side::Sell, dec!(101), dec!(4))).unwrap();
+        book
+    };
+    let (a, mut b) = (build(), build());
+    assert_eq!(a, b);
+    assert!(a.diff(&b).is_empty());
+
+    b.cancel_order(2);
+    b.add_order(order(4, Side::Sell, dec!(102), dec!(1))).unwrap();
+    assert_ne!(a, b);
+    let diff = a.diff(&b);
+    assert_eq!(diff.len(), 3, "{diff:?}");
+    assert!(diff[0].starts_with("Buy @ 99: total 8 != 5"), "{diff:?}");
+    assert!(diff[1].starts_with("Buy @ 99 posición 1"), "{diff:?}");
+    assert!(diff[2].starts_with("Sell @ 102: sólo en other"), "{diff:?}");
+}
+
+// --- SELF-TRADE PREVENTION ---
+
+// Venta de 5 en el libro y compra de 3 de la misma cuenta, que la cruza.
+fn self_cross(policy: Option<SelfTradePrevention>) -> (OrderBook, Vec<Trade>) {
+    let builder = OrderBook::builder();
+    let mut book = match policy {
+        Some(policy) => builder.self_trade_prevention(policy),
+        None => builder.allow_self_trades(),
+    }
+    .build();
+    let account = |order: Order| Order { account: 7, ..order };
+    book.add_order(account(order(1, Side::Sell, dec!(100), dec!(5)))).unwrap();
+    let trades = book.add_order(account(order(2, Side::Buy, dec!(100), dec!(3)))).unwrap();
+    (book, trades)
+}
+
+#[test]
+fn cancel_newest_drops_the_incoming_order() {
+    let (book, trades) = self_cross(Some(SelfTradePrevention::CancelNewest));
+    assert!(trades.is_empty());
+    assert_eq!(book.order(1).map(|o| o.amount), Some(dec!(5)));
+    assert!(book.order(2).is_none());
+}
+
+#[test]
+fn cancel_oldest_drops_the_resting_order() {
+    let (book, trades) = self_cross(Some(SelfTradePrevention::CancelOldest));
+    assert!(trades.is_empty());
+    assert!(book.order(1).is_none());
+    assert_eq!(book.order(2).map(|o| o.amount), Some(dec!(3)));
+}
+
+#[test]
+fn cancel_both_drops_both_orders() {
+    let (book, trades) = self_cross(Some(SelfTradePrevention::CancelBoth));
+    assert!(trades.is_empty());
+    assert!(book.is_empty());
+}
+
+#[test]
+fn decrement_and_cancel_shrinks_the_larger_order() {
+    let (book, trades) = self_cross(Some(SelfTradePrevention::DecrementAndCancel));
+    assert!(trades.is_empty());
+    assert_eq!(book.order(1).map(|o| o.amount), Some(dec!(2)));
+    assert!(book.order(2).is_none());
+}
+
+#[test]
+fn allowed_self_trades_execute() {
+    let (book, trades) = self_cross(None);
+    assert_eq!(trades.len(), 1);
+    assert_eq!(book.order(1).map(|o| o.amount), Some(dec!(2)));
+}
+
+#[test]
+fn subaccount_scope_lets_two_desks_of_one_account_cross() {
+    use crate::config::StpScope;
+
+    let trades = |scope| {
+        let mut book = OrderBook::builder().stp_scope(scope).build();
+        let desk = |order: Order, subaccount| Order { account: 7, subaccount: Some(subaccount), ..order };
+        book.add_order(desk(order(1, Side::Sell, dec!(100), dec!(5)), 1)).unwrap();
+        let other_desk = book.add_order(desk(order(2, Side::Buy, dec!(100), dec!(2)), 2)).unwrap();
+        let same_desk = book.add_order(desk(order(3, Side::Buy, dec!(100), dec!(2)), 1)).unwrap();
+        (other_desk.len(), same_desk.len())
+    };
+    assert_eq!(trades(StpScope::Subaccount), (1, 0));
+    assert_eq!(trades(StpScope::Account), (0, 0));
+}
+
+// --- CLIENT ORDER ID ---
+
+#[test]
+fn orders_can_be_cancelled_by_client_order_id() {
+    let mut book = OrderBook::new();
+    let tagged = |id, tag: &str| Order { client_order_id: Some(tag.into()), ..order(id, Side::Buy, dec!(99), dec!(2)) };
+    book.add_order(tagged(1, "alpha")).unwrap();
+    book.add_order(tagged(2, "beta")).unwrap();
+
+    let cancelled = book.cancel_by_client_id("beta").unwrap();
+    assert_eq!((cancelled.id, cancelled.amount), (2, dec!(2)));
+    assert!(book.order(2).is_none());
+    assert!(book.order(1).is_some());
+    assert!(book.cancel_by_client_id("beta").is_none());
+    assert!(book.cancel_by_client_id("gamma").is_none());
+}
+
+// --- MERCADO CON TOPE DE SLIPPAGE ---
+
+#[test]
+fn market_buy_stops_where_the_next_ask_exceeds_the_slippage_cap() {
+    let mut book = OrderBook::new();
+    book.add_order(order(1, Side::Sell, dec!(100), dec!(2))).unwrap();
+    book.add_order(order(2, Side::Sell, dec!(101), dec!(2))).unwrap();
+    book.add_order(order(3, Side::Sell, dec!(103), dec!(2))).unwrap();
+
+    let market = Order { order_type: OrderType::MarketWithSlippage { max_slippage: dec!(1) }, ..order(4, Side::Buy, Decimal::ZERO, dec!(6)) };
+    let ack = book.submit(market).unwrap();
+    assert_eq!(ack.trades.iter().map(|t| t.price).collect::<Vec<_>>(), [dec!(100), dec!(101)]);
+    assert_eq!((ack.filled_qty, ack.resting_qty, ack.cancelled_qty), (dec!(4), dec!(0), dec!(2)));
+    assert_eq!(book.best_ask(), Some(dec!(103)));
+}
+
+// --- PRECIO DE REFERENCIA ---
+
+#[test]
+fn empty_book_bands_orders_around_the_reference_price() {
+    let mut book = OrderBook::builder().price_band(dec!(0.05)).build();
+    // Sin referencia, la banda no tiene contra qué medir
+    book.add_order(order(1, Side::Buy, dec!(10), dec!(1))).unwrap();
+    book.cancel_order(1);
+
+    book.set_reference_price(dec!(100));
+    assert_eq!(book.mid_price(), Some(dec!(100)));
+    let far = book.add_order(order(2, Side::Buy, dec!(90), dec!(1)));
+    assert_eq!(far, Err(OrderError::PriceOutOfBand { price: dec!(90), reference: dec!(100), band: dec!(0.05) }));
+    book.add_order(order(3, Side::Buy, dec!(96), dec!(1))).unwrap();
+    assert_eq!(book.best_bid(), Some(dec!(96)));
+}
+
+// --- LADO AGRESOR ---
+
+#[test]
+fn trades_are_tagged_with_the_aggressor_side() {
+    let mut book = OrderBook::new();
+    book.add_order(order(1, Side::Sell, dec!(100), dec!(1))).unwrap();
+    book.add_order(order(2, Side::Sell, dec!(101), dec!(1))).unwrap();
+    let trades = book.add_order(order(3, Side::Buy, dec!(101), dec!(2))).unwrap();
+    assert_eq!(trades.len(), 2);
+    assert!(trades.iter().all(|t| t.aggressor == Side::Buy && t.taker_id == 3));
+
+    book.add_order(order(4, Side::Buy, dec!(99), dec!(1))).unwrap();
+    let trades = book.add_order(order(5, Side::Sell, dec!(99), dec!(1))).unwrap();
+    assert_eq!(trades[0].aggressor, Side::Sell);
+}
+
+// --- INVARIANTE DE CANTIDADES ---
+
+#[test]
+fn filling_exactly_the_resting_amount_leaves_nothing_negative() {
+    let mut resting = RestingOrder::new(order(1, Side::Sell, dec!(100), dec!(5)), 1);
+    resting.fill(dec!(5)).unwrap();
+    assert_eq!((resting.order.amount, resting.shown), (dec!(0), dec!(0)));
+
+    let mut iceberg = RestingOrder::new(Order { visibility: Visibility::Iceberg { peak: dec!(2) }, ..order(2, Side::Sell, dec!(100), dec!(5)) }, 2);
+    iceberg.fill(dec!(2)).unwrap();
+    assert_eq!((iceberg.order.amount, iceberg.shown), (dec!(3), dec!(0)));
+    assert!(iceberg.needs_refill());
+
+    // Por el libro: un taker del tamaño justo vacía el nivel sin dejar restos
+    let mut book = OrderBook::new();
+    book.add_order(order(3, Side::Sell, dec!(100), dec!(5))).unwrap();
+    let trades = book.add_order(order(4, Side::Buy, dec!(100), dec!(5))).unwrap();
+    assert_eq!(trades[0].amount, dec!(5));
+    assert!(book.is_empty());
+    assert_eq!(book.depth(Side::Sell, 10), []);
+}
+
+#[test]
+#[cfg_attr(debug_assertions, should_panic(expected = "fill de 6 sobre #1 con 5 ejecutable"))]
+fn over_fill_is_refused_without_touching_the_order() {
+    let mut resting = RestingOrder::new(order(1, Side::Sell, dec!(100), dec!(5)), 1);
+    let result = resting.fill(dec!(6));
+    // Sólo en release: en debug la aserción corta antes
+    assert!(matches!(result, Err(OrderError::Internal(_))));
+    assert_eq!(resting.order.amount, dec!(5));
+}
+
+// --- TOPE DE NIVELES ---
+
+fn capped_book(policy: DepthCapPolicy) -> OrderBook {
+    let mut book = OrderBook::builder().max_levels_per_side(2).depth_cap_policy(policy).build();
+    book.add_order(order(1, Side::Buy, dec!(100), dec!(1))).unwrap();
+    book.add_order(order(2, Side::Buy, dec!(99), dec!(1))).unwrap();
+    book
+}
+
+#[test]
+fn a_level_past_the_cap_is_rejected() {
+    let mut book = capped_book(DepthCapPolicy::Reject);
+    let result = book.add_order(order(3, Side::Buy, dec!(98), dec!(1)));
+    assert_eq!(result, Err(OrderError::DepthExceeded { side: Side::Buy, max_levels: 2 }));
+    // Un nivel que ya existe no cuenta como nuevo
+    book.add_order(order(4, Side::Buy, dec!(99), dec!(1))).unwrap();
+    assert_eq!(book.depth(Side::Buy, 10).len(), 2);
+}
+
+#[test]
+fn evict_worst_makes_room_for_a_better_level() {
+    let mut book = capped_book(DepthCapPolicy::EvictWorst);
+    book.add_order(order(3, Side::Buy, dec!(101), dec!(1))).unwrap();
+    assert!(book.order(2).is_none(), "el peor nivel (99) se desaloja");
+    assert_eq!(book.depth(Side::Buy, 10).iter().map(|&(price, _)| price).collect::<Vec<_>>(), [dec!(101), dec!(100)]);
+
+    // Un nivel que sería el peor no desaloja a nadie
+    let result = book.add_order(order(4, Side::Buy, dec!(90), dec!(1)));
+    assert!(matches!(result, Err(OrderError::DepthExceeded { .. })));
+    assert_eq!(book.len(), 2);
+}
+
+// --- IDS DE TRADE ---
+
+#[test]
+fn trade_ids_increase_with_each_fill() {
+    let mut book = OrderBook::new();
+    for id in 1..=3 {
+        book.add_order(order(id, Side::Sell, dec!(100) + Decimal::from(id), dec!(1))).unwrap();
+    }
+    let mut ids: Vec<u64> = book.add_order(order(4, Side::Buy, dec!(103), dec!(2))).unwrap().iter().map(|t| t.trade_id).collect();
+    ids.extend(book.add_order(order(5, Side::Buy, dec!(103), dec!(1))).unwrap().iter().map(|t| t.trade_id));
+    assert_eq!(ids, [1, 2, 3]);
+    assert_eq!(book.trade_id_counter().last(), 3);
+}
+
+// --- ITERACIÓN DE ÓRDENES EN REPOSO ---
+
+#[test]
+fn resting_orders_iterate_in_price_time_priority() {
+    let mut book = OrderBook::new();
+    book.add_order(order(1, Side::Buy, dec!(99), dec!(1))).unwrap();
+    book.add_order(order(2, Side::Buy, dec!(100), dec!(1))).unwrap();
+    book.add_order(order(3, Side::Buy, dec!(99), dec!(1))).unwrap();
+    book.add_order(order(4, Side::Sell, dec!(102), dec!(1))).unwrap();
+    book.add_order(order(5, Side::Sell, dec!(101), dec!(1))).unwrap();
+    book.add_order(order(6, Side::Sell, dec!(102), dec!(1))).unwrap();
+
+    let ids = |side| book.iter_orders(side).map(|o| o.id).collect::<Vec<_>>();
+    assert_eq!(ids(Side::Buy), [2, 1, 3]);
+    assert_eq!(ids(Side::Sell), [5, 4, 6]);
+}
+
+// --- PROFUNDIDAD CON CANTIDAD DE ÓRDENES ---
+
+#[test]
+fn depth_counts_the_visible_orders_of_each_level() {
+    let mut book = OrderBook::new();
+    for id in 1..=3 {
+        book.add_order(order(id, Side::Buy, dec!(100), dec!(1))).unwrap();
+    }
+    book.add_order(Order { visibility: Visibility::Hidden, ..order(4, Side::Buy, dec!(100), dec!(5)) }).unwrap();
+    book.add_order(order(5, Side::Buy, dec!(99), dec!(2))).unwrap();
+
+    assert_eq!(book.depth_with_counts(Side::Buy, 10), [(dec!(100), dec!(3), 3), (dec!(99), dec!(2), 1)]);
+}
+
+// --- PRECIOS NEGATIVOS ---
+
+#[test]
+fn negative_prices_cross_when_the_book_allows_them() {
+    let mut book = OrderBook::builder().allow_negative_prices().build();
+    book.add_order(order(1, Side::Sell, dec!(-6), dec!(2))).unwrap();
+    book.add_order(order(2, Side::Sell, dec!(-4), dec!(2))).unwrap();
+
+    let trades = book.add_order(order(3, Side::Buy, dec!(-5), dec!(3))).unwrap();
+    assert_eq!(trades.iter().map(|t| (t.maker_id, t.price, t.amount)).collect::<Vec<_>>(), [(1, dec!(-6), dec!(2))]);
+    assert_eq!(book.best_bid(), Some(dec!(-5)));
+    assert_eq!(book.best_ask(), Some(dec!(-4)));
+}
+
+#[test]
+fn negative_prices_are_rejected_by_default_and_amounts_always() {
+    let mut book = OrderBook::new();
+    assert!(matches!(book.add_order(order(1, Side::Buy, dec!(-5), dec!(1))), Err(OrderError::InvalidPrice(_))));
+
+    let mut negative = OrderBook::builder().allow_negative_prices().build();
+    let zero_amount = Order { amount: dec!(0), ..order(2, Side::Buy, dec!(-5), dec!(1)) };
+    assert!(matches!(negative.add_order(zero_amount), Err(OrderError::InvalidAmount(_))));
+}
+
+// --- ÓRDENES DAY ---
+
+#[test]
+fn end_session_cancels_day_orders_and_keeps_gtc() {
+    let mut book = OrderBook::new();
+    book.add_order(Order { time_in_force: TimeInForce::Day, ..order(1, Side::Buy, dec!(100), dec!(1)) }).unwrap();
+    book.add_order(order(2, Side::Buy, dec!(99), dec!(1))).unwrap();
+
+    assert_eq!(book.end_session().iter().map(|o| o.id).collect::<Vec<_>>(), [1]);
+    assert!(book.order(1).is_none());
+    assert!(book.order(2).is_some());
+}
+
+#[test]
+fn the_session_close_sweeps_day_orders_on_its_own() {
+    let clock = crate::clock::VirtualClock::new(0);
+    let mut book = OrderBook::builder().clock(clock.clone()).session_end(1_000).build();
+    book.add_order(Order { time_in_force: TimeInForce::Day, ..order(1, Side::Buy, dec!(100), dec!(1)) }).unwrap();
+    book.add_order(order(2, Side::Buy, dec!(99), dec!(1))).unwrap();
+
+    clock.set(999);
+    assert!(book.expire_orders().is_empty());
+    clock.set(1_000);
+    assert_eq!(book.expire_orders().iter().map(|o| o.id).collect::<Vec<_>>(), [1]);
+    assert!(book.order(2).is_some());
+}
+
+// --- ACK DE LA ORDEN ---
+
+#[test]
+fn a_partial_ioc_ack_accounts_for_the_whole_amount() {
+    let mut book = OrderBook::new();
+    book.add_order(order(1, Side::Sell, dec!(100), dec!(3))).unwrap();
+
+    let ack = book.submit(Order { time_in_force: TimeInForce::Ioc, ..order(2, Side::Buy, dec!(100), dec!(5)) }).unwrap();
+    assert_eq!((ack.filled_qty, ack.resting_qty, ack.cancelled_qty, ack.pending_qty), (dec!(3), dec!(0), dec!(2), dec!(0)));
+    assert_eq!(ack.filled_qty + ack.resting_qty + ack.cancelled_qty + ack.pending_qty, dec!(5));
+    assert_eq!(ack.trades.len(), 1);
+}
+
+#[test]
+fn the_ack_keeps_last_look_fills_apart() {
+    let mut book = OrderBook::builder().last_look(50).build();
+    book.add_order(order(1, Side::Sell, dec!(100), dec!(3))).unwrap();
+
+    let ack = book.submit(order(2, Side::Buy, dec!(100), dec!(5))).unwrap();
+    assert_eq!((ack.filled_qty, ack.resting_qty, ack.cancelled_qty, ack.pending_qty), (dec!(0), dec!(2), dec!(0), dec!(3)));
+    assert!(ack.trades.is_empty());
+}
+
+// --- DESBORDAMIENTO ---
+
+// Cruza una venta y una compra de `amount` a `price` y devuelve el trade.
+fn single_trade(price: Decimal, amount: Decimal) -> Trade {
+    let mut book = OrderBook::new();
+    book.add_order(order(1, Side::Sell, price, amount)).unwrap();
+    book.add_order(order(2, Side::Buy, price, amount)).unwrap().remove(0)
+}
+
+#[test]
+fn notional_beyond_64_bits_is_still_exact() {
+    let trade = single_trade(dec!(1_000_000_000_000), dec!(10_000_000_000));
+    assert_eq!(trade.notional(), Ok(dec!(10_000_000_000_000_000_000_000)));
+    assert_eq!(trade.fee(dec!(0.001)), Ok(dec!(10_000_000_000_000_000_000)));
+}
+
+#[test]
+fn notional_past_the_decimal_range_is_an_overflow_error() {
+    let trade = single_trade(dec!(100_000_000_000_000_000_000), dec!(10_000_000_000));
+    assert_eq!(trade.notional(), Err(OrderError::Overflow("nocional del trade")));
+    assert!(matches!(trade.fee(dec!(0.001)), Err(OrderError::Overflow(_))));
+
+    let mut book = OrderBook::builder().min_notional(dec!(10)).build();
+    let huge = order(3, Side::Buy, dec!(100_000_000_000_000_000_000), dec!(10_000_000_000));
+    assert_eq!(book.add_order(huge), Err(OrderError::Overflow("nocional de la orden")));
+}
+
+// --- TRADES RECIENTES ---
+
+#[test]
+fn recent_trades_keep_only_the_last_ones_in_order() {
+    let mut book = OrderBook::builder().recent_trades(3).build();
+    book.add_order(order(1, Side::Sell, dec!(100), dec!(5))).unwrap();
+    for id in 2..=6 {
+        book.add_order(order(id, Side::Buy, dec!(100), dec!(1))).unwrap();
+    }
+
+    let takers = |n| book.recent_trades(n).iter().map(|t| t.taker_id).collect::<Vec<_>>();
+    assert_eq!(takers(10), [4, 5, 6]);
+    assert_eq!(takers(2), [5, 6]);
+}
+
+// --- ÓRDENES ABIERTAS POR CUENTA ---
+
+#[test]
+fn each_account_sees_only_its_open_orders() {
+    let mut book = OrderBook::new();
+    for id in 1..=6 {
+        let account = if id % 2 == 0 { 20 } else { 10 };
+        book.add_order(Order { account, ..order(id, Side::Buy, dec!(90) + Decimal::from(id), dec!(1)) }).unwrap();
+    }
+    book.cancel_order(3);
+
+    let ids = |account| book.open_orders(account).iter().map(|o| o.id).collect::<Vec<_>>();
+    assert_eq!(ids(10), [1, 5]);
+    assert_eq!(ids(20), [2, 4, 6]);
+    assert!(ids(30).is_empty());
+}
+
+// --- REDONDEO DE COMISIONES ---
+
+#[test]
+fn each_rounding_mode_rounds_the_fee_its_way() {
+    use crate::config::RoundingMode;
+
+    let (half, above) = (single_trade(dec!(12.5), dec!(1)), single_trade(dec!(12.1), dec!(1)));
+    let cases = [
+        (RoundingMode::HalfEven, dec!(0.12), dec!(0.12)),
+        (RoundingMode::HalfUp, dec!(0.13), dec!(0.12)),
+        (RoundingMode::HalfDown, dec!(0.12), dec!(0.12)),
+        (RoundingMode::Up, dec!(0.13), dec!(0.13)),
+        (RoundingMode::Down, dec!(0.12), dec!(0.12)),
+    ];
+    for (mode, half_fee, above_fee) in cases {
+        let book = OrderBook::builder().rounding(2, mode).build();
+        assert_eq!(book.fee(&half, dec!(0.01)), Ok(half_fee), "{mode:?}");
+        assert_eq!(book.fee(&above, dec!(0.01)), Ok(above_fee), "{mode:?}");
+    }
+    assert_eq!(BookConfig::default().rounding, RoundingMode::HalfEven);
+}
+
+// --- POST-ONLY ---
+
+#[test]
+fn post_only_rejects_at_and_through_the_opposite_best() {
+    let mut book = OrderBook::new();
+    book.add_order(order(1, Side::Sell, dec!(100), dec!(1))).unwrap();
+    book.add_order(order(2, Side::Sell, dec!(102), dec!(1))).unwrap();
+    let post_only = |id, price| Order { order_type: OrderType::PostOnly, ..order(id, Side::Buy, price, dec!(1)) };
+
+    for (id, price) in [(3, dec!(100)), (4, dec!(101)), (5, dec!(105))] {
+        assert_eq!(book.add_order(post_only(id, price)), Err(OrderError::PostOnlyWouldCross { price, best: dec!(100) }));
+    }
+    assert_eq!(book.add_order(post_only(6, dec!(99.99))), Ok(Vec::new()));
+    assert_eq!(book.best_bid(), Some(dec!(99.99)));
+    assert_eq!(book.len(), 3);
+}
+
+// --- BARRIDO DE VARIOS NIVELES ---
+
+#[test]
+fn a_sweep_reports_one_trade_per_maker_in_execution_order() {
+    let mut book = OrderBook::new();
+    book.add_order(order(1, Side::Sell, dec!(100), dec!(1))).unwrap();
+    book.add_order(order(2, Side::Sell, dec!(101), dec!(2))).unwrap();
+    book.add_order(order(3, Side::Sell, dec!(102), dec!(3))).unwrap();
+
+    let trades = book.add_order(order(4, Side::Buy, dec!(102), dec!(4))).unwrap();
+    let fills: Vec<_> = trades.iter().map(|t| (t.maker_id, t.price, t.amount)).collect();
+    assert_eq!(fills, [(1, dec!(100), dec!(1)), (2, dec!(101), dec!(2)), (3, dec!(102), dec!(1))]);
+    assert_eq!(trades.iter().map(|t| t.amount).sum::<Decimal>(), dec!(4));
+    assert_eq!(book.order(3).map(|o| o.amount), Some(dec!(2)));
+}
+
+// --- DESEMPATE DE PRIORIDAD ---
+
+#[test]
+fn arrival_sequence_ignores_out_of_order_client_timestamps() {
+    use crate::config::PriorityTieBreak;
+
+    // La 1 llega primero pero con un timestamp de cliente posterior
+    let late_clock = Order { timestamp: 50, ..order(1, Side::Sell, dec!(100), dec!(1)) };
+    let first_maker = |tie_break| {
+        let mut book = OrderBook::builder().tie_break(tie_break).build();
+        book.add_order(late_clock.clone()).unwrap();
+        book.add_order(Order { timestamp: 10, ..order(2, Side::Sell, dec!(100), dec!(1)) }).unwrap();
+        book.add_order(order(3, Side::Buy, dec!(100), dec!(1))).unwrap()[0].maker_id
+    };
+    assert_eq!(first_maker(PriorityTieBreak::ArrivalSequence), 1);
+    assert_eq!(first_maker(PriorityTieBreak::Timestamp), 2);
+}
+
+// --- COMPRA POR MONTO ---
+
+#[test]
+fn a_budget_market_buy_spends_up_to_its_budget_in_whole_lots() {
+    let mut book = OrderBook::builder().lot_size(dec!(0.1)).build();
+    book.add_order(order(1, Side::Sell, dec!(100), dec!(3))).unwrap();
+    book.add_order(order(2, Side::Sell, dec!(110), dec!(10))).unwrap();
+
+    let budget = dec!(1000);
+    let buy = Order { order_type: OrderType::MarketQuote { budget }, ..order(3, Side::Buy, dec!(0), dec!(100)) };
+    let trades = book.add_order(buy).unwrap();
+    assert_eq!(trades.iter().map(|t| (t.price, t.amount)).collect::<Vec<_>>(), [(dec!(100), dec!(3)), (dec!(110), dec!(6.3))]);
+
+    let spent: Decimal = trades.iter().map(|t| t.price * t.amount).sum();
+    assert_eq!(spent, dec!(993));
+    assert!(spent <= budget && budget - spent < dec!(110) * dec!(0.1));
+}
+
+// --- VOLUMEN POR LADO ---
+
+#[test]
+fn total_volume_follows_inserts_fills_and_cancels() {
+    let mut book = OrderBook::new();
+    book.add_order(order(1, Side::Sell, dec!(100), dec!(2))).unwrap();
+    book.add_order(order(2, Side::Sell, dec!(101), dec!(3))).unwrap();
+    book.add_order(order(3, Side::Buy, dec!(99), dec!(4))).unwrap();
+    assert_eq!((book.total_volume(Side::Buy), book.total_volume(Side::Sell)), (dec!(4), dec!(5)));
+
+    book.add_order(order(4, Side::Buy, dec!(101), dec!(3))).unwrap();
+    assert_eq!((book.total_volume(Side::Buy), book.total_volume(Side::Sell)), (dec!(4), dec!(2)));
+
+    book.cancel_order(3);
+    book.cancel_order(2);
+    assert_eq!((book.total_volume(Side::Buy), book.total_volume(Side::Sell)), (dec!(0), dec!(0)));
+}
+
+// --- LIBRO CRUZADO ---
+
+#[test]
+fn a_crossing_remainder_rests_only_once_it_no_longer_crosses() {
+    let mut book = OrderBook::new();
+    book.add_order(order(1, Side::Sell, dec!(100), dec!(1))).unwrap();
+    book.add_order(order(2, Side::Sell, dec!(101), dec!(1))).unwrap();
+    book.add_order(order(3, Side::Sell, dec!(103), dec!(1))).unwrap();
+
+    let trades = book.add_order(order(4, Side::Buy, dec!(102), dec!(5))).unwrap();
+    assert_eq!(trades.len(), 2);
+    assert_eq!(book.order(4).map(|o| o.amount), Some(dec!(3)));
+    let (bid, ask) = (book.best_bid().unwrap(), book.best_ask().unwrap());
+    assert_eq!((bid, ask), (dec!(102), dec!(103)));
+    assert!(bid < ask);
+}
+
+// --- CIERRE POR PORCENTAJE ---
+
+#[test]
+fn closing_half_of_a_long_sells_half_the_position() {
+    let mut book = OrderBook::new();
+    book.add_order(order(1, Side::Sell, dec!(100), dec!(4))).unwrap();
+    book.add_order(Order { account: 7, ..order(2, Side::Buy, dec!(100), dec!(4)) }).unwrap();
+    assert_eq!(book.positions().get(7), dec!(4));
+
+    // El lado y la cantidad de la orden los pone el cierre
+    let close = Order { account: 7, ..order(3, Side::Buy, dec!(101), dec!(99)) };
+    let ack = book.submit_percent_of_position(close, dec!(50)).unwrap();
+    assert_eq!(ack.resting_qty, dec!(2));
+    assert_eq!(book.order(3).map(|o| (o.side, o.amount)), Some((Side::Sell, dec!(2))));
+
+    let flat = Order { account: 8, ..order(4, Side::Sell, dec!(101), dec!(1)) };
+    assert_eq!(book.submit_percent_of_position(flat, dec!(50)).unwrap_err(), OrderError::FlatPosition { account: 8 });
+}
+
+// --- RESUMEN DE EJECUCIÓN ---
+
+#[test]
+fn the_exec_summary_counts_levels_makers_and_the_average_price() {
+    let mut book = OrderBook::new();
+    book.add_order(order(1, Side::Sell, dec!(100), dec!(1))).unwrap();
+    book.add_order(order(2, Side::Sell, dec!(100), dec!(1))).unwrap();
+    book.add_order(order(3, Side::Sell, dec!(101), dec!(2))).unwrap();
+    book.add_order(order(4, Side::Sell, dec!(104), dec!(5))).unwrap();
+
+    let summary = book.submit(order(5, Side::Buy, dec!(104), dec!(5))).unwrap().summary();
+    // (100 + 100 + 2 * 101 + 104) / 5
+    assert_eq!(summary, ExecSummary { levels: 3, makers: 4, filled_qty: dec!(5), avg_price: Some(dec!(101.2)) });
+
+    let resting = book.submit(order(6, Side::Buy, dec!(90), dec!(1))).unwrap().summary();
+    assert_eq!(resting, ExecSummary::default());
+}
+
+// --- EMPATES DE PRECIO Y TIMESTAMP ---
+
+#[test]
+fn same_price_same_timestamp_orders_are_all_kept_in_arrival_order() {
+    use crate::config::PriorityTieBreak;
+
+    for tie_break in [PriorityTieBreak::ArrivalSequence, PriorityTieBreak::Timestamp] {
+        let mut book = OrderBook::builder().tie_break(tie_break).build();
+        for id in 1..=50 {
+            book.add_order(Order { timestamp: 7, ..order(id, Side::Sell, dec!(100), dec!(1)) }).unwrap();
+        }
+        assert_eq!(book.len(), 50);
+        assert_eq!(book.depth(Side::Sell, 1), [(dec!(100), dec!(50))]);
+
+        let trades = book.add_order(order(51, Side::Buy, dec!(100), dec!(50))).unwrap();
+        assert_eq!(trades.iter().map(|t| t.maker_id).collect::<Vec<_>>(), (1..=50).collect::<Vec<_>>(), "{tie_break:?}");
+    }
+}
+
+// --- ESCALA DECIMAL ---
+
+#[test]
+fn prices_with_different_scales_land_on_one_level() {
+    let mut book = OrderBook::builder().normalize_scale(2, 3).build();
+    book.add_order(order(1, Side::Buy, dec!(50000), dec!(1))).unwrap();
+    book.add_order(order(2, Side::Buy, dec!(50000.00), dec!(1.5))).unwrap();
+
+    assert_eq!(book.depth_with_counts(Side::Buy, 10), [(dec!(50000), dec!(2.5), 2)]);
+    for id in [1, 2] {
+        let stored = book.order(id).unwrap();
+        assert_eq!((stored.price.scale(), stored.amount.scale()), (2, 3));
+    }
+    assert_eq!(book.best_bid().map(|p| p.to_string()), Some("50000.00".to_string()));
+
+    // Normalizar nunca redondea
+    assert_eq!(book.add_order(order(3, Side::Buy, dec!(50000.001), dec!(1))), Err(OrderError::InvalidPrice(dec!(50000.001))));
+}
+
+// --- MID VIGENTE EN CADA TRADE ---
+
+#[test]
+fn each_trade_records_the_mid_just_before_its_fill() {
+    let mut book = OrderBook::new();
+    book.add_order(order(1, Side::Buy, dec!(98), dec!(1))).unwrap();
+    book.add_order(order(2, Side::Sell, dec!(100), dec!(1))).unwrap();
+    book.add_order(order(3, Side::Sell, dec!(102), dec!(2))).unwrap();
+    let mid_before = book.mid_price();
+
+    let trades = book.add_order(order(4, Side::Buy, dec!(102), dec!(3))).unwrap();
+    assert_eq!(trades[0].prevailing_mid, mid_before);
+    // El segundo fill ya ve el ask de 100 consumido
+    assert_eq!(trades.iter().map(|t| t.prevailing_mid).collect::<Vec<_>>(), [Some(dec!(99)), Some(dec!(100))]);
+
+    let one_sided = single_trade(dec!(100), dec!(1));
+    assert_eq!(one_sided.prevailing_mid, None);
+}
+
+// --- PRIORIDAD DE LAS OCULTAS ---
+
+#[test]
+fn hidden_orders_wait_behind_visible_ones_only_when_configured() {
+    use crate::config::HiddenPriority;
+
+    let makers = |priority| {
+        let mut book = OrderBook::builder().hidden_priority(priority).build();
+        book.add_order(Order { visibility: Visibility::Hidden, ..order(1, Side::Sell, dec!(100), dec!(1)) }).unwrap();
+        book.add_order(order(2, Side::Sell, dec!(100), dec!(1))).unwrap();
+        book.add_order(Order { visibility: Visibility::Iceberg { peak: dec!(1) }, ..order(3, Side::Sell, dec!(100), dec!(2)) }).unwrap();
+        let trades = book.add_order(order(4, Side::Buy, dec!(100), dec!(4))).unwrap();
+        trades.iter().map(|t| t.maker_id).collect::<Vec<_>>()
+    };
+    assert_eq!(makers(HiddenPriority::TimeOrder), [1, 2, 3, 3]);
+    // El iceberg repone delante de la oculta
+    assert_eq!(makers(HiddenPriority::BehindVisible), [2, 3, 3, 1]);
+}
+
+// --- CANCELACIÓN EN LOTE ---
+
+#[test]
+fn cancel_batch_reports_each_id_in_order() {
+    let mut book = OrderBook::new();
+    for id in 1..=3 {
+        book.add_order(order(id, Side::Buy, dec!(100), dec!(1))).unwrap();
+    }
+
+    let results = book.cancel_batch(&[2, 9, 2, 1]);
+    assert_eq!(results.iter().map(|o| o.as_ref().map(|o| o.id)).collect::<Vec<_>>(), [Some(2), None, None, Some(1)]);
+    assert_eq!(book.iter_orders(Side::Buy).map(|o| o.id).collect::<Vec<_>>(), [3]);
+
+    book.set_trading_state(TradingState::Halted);
+    assert_eq!(book.cancel_batch(&[3]), [None]);
+    assert_eq!(book.len(), 1);
+}
+
+// --- LOG DE RECHAZOS ---
+
+#[test]
+fn the_reject_log_keeps_each_rejected_order_with_its_reason() {
+    let clock = crate::clock::VirtualClock::new(1_000);
+    let mut book = OrderBook::builder().clock(clock.clone()).recent_rejects(2).tick_size(dec!(0.5)).build();
+    book.add_order(order(1, Side::Sell, dec!(100), dec!(1))).unwrap();
+
+    assert!(book.add_order(order(2, Side::Buy, dec!(-1), dec!(1))).is_err());
+    clock.advance(10);
+    assert!(book.add_order(order(3, Side::Buy, dec!(99.3), dec!(1))).is_err());
+    clock.advance(10);
+    assert!(book.add_order(Order { order_type: OrderType::PostOnly, ..order(4, Side::Buy, dec!(100), dec!(1)) }).is_err());
+
+    let log: Vec<_> = book.recent_rejects(10).into_iter().map(|r| (r.order.id, r.reason.kind(), r.timestamp)).collect();
+    assert_eq!(log, [(3, "InvalidTick", 1_010), (4, "PostOnlyWouldCross", 1_020)]);
+    assert_eq!(book.recent_rejects(1)[0].reason, OrderError::PostOnlyWouldCross { price: dec!(100), best: dec!(100) });
+}
+
+// --- ALL-OR-NONE ---
+
+#[test]
+fn a_small_order_skips_a_large_aon_and_fills_the_order_behind() {
+    let mut book = OrderBook::new();
+    book.add_order(Order { order_type: OrderType::AllOrNone, ..order(1, Side::Sell, dec!(100), dec!(10)) }).unwrap();
+    book.add_order(order(2, Side::Sell, dec!(100), dec!(3))).unwrap();
+
+    let trades = book.add_order(order(3, Side::Buy, dec!(100), dec!(2))).unwrap();
+    assert_eq!(trades.iter().map(|t| (t.maker_id, t.amount)).collect::<Vec<_>>(), [(2, dec!(2))]);
+    assert_eq!(book.order(1).map(|o| o.amount), Some(dec!(10)));
+
+    // Una orden que la cubre entera sí ejecuta contra la AON, que está primera
+    let trades = book.add_order(order(4, Side::Buy, dec!(100), dec!(10))).unwrap();
+    assert_eq!(trades.iter().map(|t| (t.maker_id, t.amount)).collect::<Vec<_>>(), [(1, dec!(10))]);
+}
+
+// --- POSICIÓN EN LA COLA ---
+
+#[test]
+fn queue_position_counts_the_volume_ahead_at_the_level() {
+    let mut book = OrderBook::new();
+    book.add_order(order(1, Side::Buy, dec!(100), dec!(2))).unwrap();
+    book.add_order(Order { visibility: Visibility::Iceberg { peak: dec!(1) }, ..order(2, Side::Buy, dec!(100), dec!(5)) }).unwrap();
+    book.add_order(order(3, Side::Buy, dec!(100), dec!(4))).unwrap();
+    book.add_order(order(4, Side::Buy, dec!(99), dec!(1))).unwrap();
+
+    let total = dec!(11);
+    assert_eq!(book.queue_position(1), Some((dec!(0), total)));
+    assert_eq!(book.queue_position(2), Some((dec!(2), total)));
+    // Del iceberg sólo el pico va por delante
+    assert_eq!(book.queue_position(3), Some((dec!(3), total)));
+    assert_eq!(book.queue_position(4), Some((dec!(0), dec!(1))));
+    assert_eq!(book.queue_position(9), None);
+}
+
+// --- MEJORA DE PRECIO ---
+
+// Precio del cruce de un taker `side` a `limit` contra un maker a `resting`.
+fn crossing_price(builder: OrderBookBuilder, side: Side, resting: Decimal, limit: Decimal) -> Decimal {
+    let mut book = builder.build();
+    book.add_order(order(1, side.opposite(), resting, dec!(1))).unwrap();
+    book.add_order(order(2, side, limit, dec!(1))).unwrap()[0].price
+}
+
+#[test]
+fn midpoint_improvement_splits_the_difference_and_none_keeps_the_maker_price() {
+    let midpoint = || OrderBook::builder().price_improvement(PriceImprovement::Midpoint);
+    assert_eq!(crossing_price(OrderBook::builder(), Side::Buy, dec!(100), dec!(103)), dec!(100));
+    assert_eq!(crossing_price(midpoint(), Side::Buy, dec!(100), dec!(103)), dec!(101.5));
+    assert_eq!(crossing_price(midpoint(), Side::Sell, dec!(100), dec!(97)), dec!(98.5));
+    // Un límite justo en el nivel no tiene nada que repartir
+    assert_eq!(crossing_price(midpoint(), Side::Buy, dec!(100), dec!(100)), dec!(100));
+}
+
+#[test]
+fn midpoint_between_ticks_rounds_toward_the_resting_price() {
+    let on_ticks = || OrderBook::builder().price_improvement(PriceImprovement::Midpoint).tick_size(dec!(1));
+    assert_eq!(crossing_price(on_ticks(), Side::Buy, dec!(99), dec!(102)), dec!(100));
+    assert_eq!(crossing_price(on_ticks(), Side::Sell, dec!(101), dec!(98)), dec!(100));
+    assert_eq!(crossing_price(on_ticks(), Side::Buy, dec!(99), dec!(101)), dec!(100));
+}
+
+// --- MODIFICACIÓN EN EL LUGAR ---
+
+#[test]
+fn an_in_place_size_decrease_is_a_single_modify() {
+    let mut book = OrderBook::new();
+    book.add_order(order(1, Side::Buy, dec!(100), dec!(5))).unwrap();
+    book.add_order(order(2, Side::Buy, dec!(100), dec!(1))).unwrap();
+    book.enable_events();
+
+    book.modify_order(1, dec!(100), dec!(3)).unwrap();
+    let events: Vec<_> = book.drain_events().into_iter().map(|e| e.event).collect();
+    assert_eq!(events, [
+        BookEvent::OrderModified { id: 1, price: dec!(100), amount: dec!(3) },
+        BookEvent::LevelDelta { side: Side::Buy, price: dec!(100), amount: dec!(4) },
+    ]);
+    assert_eq!(book.queue_position(1), Some((dec!(0), dec!(4))));
+
+    // Agrandarla pierde la prioridad: cancelación más alta
+    book.modify_order(1, dec!(100), dec!(6)).unwrap();
+    let events = book.drain_events();
+    assert!(matches!(events[0].event, BookEvent::OrderCancelled { .. }));
+    assert!(events.iter().any(|e| matches!(e.event, BookEvent::OrderAdded { .. })));
+    assert!(!events.iter().any(|e| matches!(e.event, BookEvent::OrderModified { .. })));
+    assert_eq!(book.queue_position(1), Some((dec!(1), dec!(7))));
+}
+
+// --- TIEMPO MÍNIMO EN REPOSO ---
+
+// Libro con reloj virtual y 100 ms de reposo mínimo, con las órdenes 1 y 2 en reposo desde t=0.
+fn resting_clock(policy: MinRestingPolicy) -> (OrderBook, crate::clock::VirtualClock) {
+    let clock = crate::clock::VirtualClock::new(0);
+    let mut book = OrderBook::builder().clock(clock.clone()).min_resting_time(100, policy).build();
+    book.add_order(order(1, Side::Buy, dec!(100), dec!(1))).unwrap();
+    book.add_order(order(2, Side::Buy, dec!(99), dec!(1))).unwrap();
+    (book, clock)
+}
+
+#[test]
+fn fast_cancels_are_flagged_but_go_through() {
+    let (mut book, clock) = resting_clock(MinRestingPolicy::Flag);
+    clock.set(99);
+    assert!(book.cancel_order(1).is_some());
+    assert_eq!(book.fast_cancels(), 1);
+
+    clock.set(100);
+    assert!(book.cancel_order(2).is_some());
+    assert_eq!(book.fast_cancels(), 1);
+}
+
+#[test]
+fn fast_cancels_can_be_refused() {
+    let (mut book, clock) = resting_clock(MinRestingPolicy::Reject);
+    clock.set(99);
+    assert!(book.cancel_order(1).is_none());
+    assert!(book.order(1).is_some());
+
+    clock.set(100);
+    assert!(book.cancel_order(1).is_some());
+    assert_eq!(book.fast_cancels(), 0);
+}
+
+// --- LIBRO PARADO ---
+
+#[test]
+fn a_stopped_book_returns_market_halted_and_cancel_only_still_cancels() {
+    let mut book = OrderBook::new();
+    book.add_order(order(1, Side::Buy, dec!(100), dec!(1))).unwrap();
+    book.add_order(order(2, Side::Buy, dec!(99), dec!(1))).unwrap();
+
+    book.set_trading_state(TradingState::CancelOnly);
+    assert_eq!(book.add_order(order(3, Side::Sell, dec!(101), dec!(1))), Err(OrderError::MarketHalted(TradingState::CancelOnly)));
+    assert!(book.cancel_order(1).is_some());
+
+    book.set_trading_state(TradingState::Halted);
+    assert_eq!(book.add_order(order(4, Side::Sell, dec!(101), dec!(1))), Err(OrderError::MarketHalted(TradingState::Halted)));
+    assert!(book.cancel_order(2).is_none());
+
+    book.set_trading_state(TradingState::Open);
+    assert!(book.add_order(order(5, Side::Sell, dec!(101), dec!(1))).is_ok());
+}
+
+// --- ANTIGÜEDAD DEL LIBRO ---
+
+#[test]
+fn the_book_goes_stale_once_the_clock_passes_the_threshold() {
+    let clock = crate::clock::VirtualClock::new(1_000);
+    let mut book = OrderBook::builder().clock(clock.clone()).build();
+    assert!(book.is_stale(50), "un libro que nunca cambió está viejo");
+
+    book.add_order(order(1, Side::Buy, dec!(100), dec!(1))).unwrap();
+    assert_eq!(book.last_update_ts(), Some(1_000));
+    clock.advance(50);
+    assert!(!book.is_stale(50));
+    clock.advance(1);
+    assert!(book.is_stale(50));
+
+    // Cualquier cambio lo refresca
+    book.cancel_order(1).unwrap();
+    assert_eq!(book.last_update_ts(), Some(1_051));
+    assert!(!book.is_stale(50));
+}
+
+// --- LOTES ---
+
+#[test]
+fn a_mis_sized_order_suggests_the_nearest_valid_sizes() {
+    let mut book = OrderBook::builder().lot_size(dec!(0.5)).build();
+    let error = book.add_order(order(1, Side::Buy, dec!(100), dec!(1.7))).unwrap_err();
+    assert_eq!(error, OrderError::InvalidLot { amount: dec!(1.7), lot: dec!(0.5), lower: Some(dec!(1.5)), upper: dec!(2) });
+    assert_eq!(error.to_string(), "la cantidad 1.7 no es múltiplo del lote 0.5: probá con 1.5 o 2.0");
+
+    // Debajo de un lote no hay tamaño menor válido
+    let error = book.add_order(order(2, Side::Buy, dec!(100), dec!(0.2))).unwrap_err();
+    assert_eq!(error, OrderError::InvalidLot { amount: dec!(0.2), lot: dec!(0.5), lower: None, upper: dec!(0.5) });
+    assert!(book.add_order(order(3, Side::Buy, dec!(100), dec!(1.5))).is_ok());
+}
+
+// --- LÍMITE DE POSICIÓN ---
+
+#[test]
+fn a_large_buy_stops_at_the_position_limit_mid_sweep() {
+    let mut book = OrderBook::builder().position_limit(dec!(5)).build();
+    book.add_order(order(1, Side::Sell, dec!(100), dec!(3))).unwrap();
+    book.add_order(order(2, Side::Sell, dec!(101), dec!(3))).unwrap();
+    book.add_order(order(3, Side::Sell, dec!(102), dec!(3))).unwrap();
+
+    // Ejecuta el primer nivel entero y 2 del segundo; el resto cruzaría y se cancela
+    let ack = book.submit(order(4, Side::Buy, dec!(102), dec!(9))).unwrap();
+    assert_eq!(ack.trades.iter().map(|t| (t.maker_id, t.amount)).collect::<Vec<_>>(), [(1, dec!(3)), (2, dec!(2))]);
+    assert_eq!((ack.filled_qty, ack.resting_qty, ack.cancelled_qty), (dec!(5), dec!(0), dec!(4)));
+    assert_eq!(book.positions().get(4), dec!(5));
+    assert_eq!(book.depth(Side::Sell, 10), [(dec!(101), dec!(1)), (dec!(102), dec!(3))]);
+
+    // Sin lugar, la cuenta ya no compra; vender sí la acerca a cero
+    assert!(book.submit(order(4, Side::Buy, dec!(102), dec!(1))).unwrap().trades.is_empty());
+    book.add_order(order(5, Side::Buy, dec!(99), dec!(2))).unwrap();
+    assert_eq!(book.submit(Order { account: 4, ..order(6, Side::Sell, dec!(99), dec!(2)) }).unwrap().filled_qty, dec!(2));
+    assert_eq!(book.positions().get(4), dec!(3));
+}
+
+// --- MERCADO CONTRA UN LIBRO VACÍO ---
+
+#[test]
+fn a_market_order_in_an_empty_book_is_rejected_by_default() {
+    let mut book = OrderBook::new();
+    book.add_order(order(1, Side::Buy, dec!(99), dec!(1))).unwrap();
+    let market = Order { order_type: OrderType::Market, ..order(2, Side::Buy, dec!(0), dec!(3)) };
+    assert_eq!(book.add_order(market), Err(OrderError::NoLiquidity { side: Side::Sell }));
+    assert_eq!(book.len(), 1);
+}
+
+#[test]
+fn with_a_reference_price_zero_fill_reports_everything_cancelled() {
+    let mut book = OrderBook::builder().market_no_liquidity(MarketNoLiquidity::ZeroFill).build();
+    let market = || Order { order_type: OrderType::Market, ..order(1, Side::Buy, dec!(0), dec!(3)) };
+
+    // Sin referencia se rechaza igual
+    assert_eq!(book.add_order(market()), Err(OrderError::NoLiquidity { side: Side::Sell }));
+
+    book.set_reference_price(dec!(100));
+    let ack = book.submit(market()).unwrap();
+    assert!(ack.trades.is_empty());
+    assert_eq!((ack.filled_qty, ack.resting_qty, ack.cancelled_qty), (dec!(0), dec!(0), dec!(3)));
+    assert!(book.is_empty());
+}
+
+// --- FILL MÍNIMO ---
+
+#[test]
+fn an_order_skips_resting_orders_below_its_min_fill() {
+    let mut book = OrderBook::new();
+    book.add_order(order(1, Side::Sell, dec!(100), dec!(0.5))).unwrap();
+    book.add_order(order(2, Side::Sell, dec!(100), dec!(3))).unwrap();
+    book.add_order(order(3, Side::Sell, dec!(100), dec!(4))).unwrap();
+
+    // Varios fills, cada uno de al menos 2; el de 0.5 se saltea y queda primero
+    let taker = Order { min_fill: Some(dec!(2)), ..order(4, Side::Buy, dec!(100), dec!(5)) };
+    let ack = book.submit(taker).unwrap();
+    assert_eq!(ack.trades.iter().map(|t| (t.maker_id, t.amount)).collect::<Vec<_>>(), [(2, dec!(3)), (3, dec!(2))]);
+    assert_eq!(book.queue_position(1), Some((dec!(0), dec!(2.5))));
+
+    // Sin nada que alcance el mínimo, el resto no puede descansar cruzado y se cancela
+    book.cancel_order(3).unwrap();
+    let ack = book.submit(Order { min_fill: Some(dec!(1)), ..order(5, Side::Buy, dec!(100), dec!(2)) }).unwrap();
+    assert_eq!((ack.filled_qty, ack.resting_qty, ack.cancelled_qty), (dec!(0), dec!(0), dec!(2)));
+    assert_eq!(book.depth(Side::Sell, 10), [(dec!(100), dec!(0.5))]);
+}
+
+// --- REDUCCIÓN DE TAMAÑO ---
+
+#[test]
+fn reducing_size_keeps_the_order_at_the_front_of_its_level() {
+    let mut book = OrderBook::new();
+    book.enable_events();
+    book.add_order(order(1, Side::Sell, dec!(100), dec!(5))).unwrap();
+    book.add_order(order(2, Side::Sell, dec!(100), dec!(4))).unwrap();
+    let mut copy = crate::delta::DeltaApplier::from_snapshot(&book.snapshot(10));
+    book.drain_events();
+
+    book.modify_order(1, dec!(100), dec!(2)).unwrap();
+    for event in book.drain_events() {
+        copy.apply(&event).unwrap();
+    }
+    assert_eq!(book.queue_position(1), Some((dec!(0), dec!(6))));
+    assert_eq!(book.queue_position(2), Some((dec!(2), dec!(6))));
+    assert_eq!(copy.levels(Side::Sell).collect::<Vec<_>>(), [(dec!(100), dec!(6))]);
+
+    // El próximo comprador le pega primero a la orden reducida
+    let ack = book.submit(order(3, Side::Buy, dec!(100), dec!(3))).unwrap();
+    assert_eq!(ack.trades.iter().map(|t| (t.maker_id, t.amount)).collect::<Vec<_>>(), [(1, dec!(2)), (2, dec!(1))]);
+}
+
+// --- FOK O REPOSO ---
+
+#[test]
+fn fok_or_rest_never_partially_fills_then_rests() {
+    let mut book = OrderBook::new();
+    book.add_order(order(1, Side::Sell, dec!(101), dec!(3))).unwrap();
+    let fok_or_rest = |id, price, amount| Order { time_in_force: TimeInForce::FokOrRest, ..order(id, Side::Buy, price, amount) };
+
+    // No llega a 101: sin ejecutar nada, queda entera en reposo
+    let ack = book.submit(fok_or_rest(2, dec!(100), dec!(5))).unwrap();
+    assert_eq!((ack.filled_qty, ack.resting_qty), (dec!(0), dec!(5)));
+
+    // Cruza pero sólo hay 3 de 5: no toma el parcial y se cancela entera
+    let ack = book.submit(fok_or_rest(3, dec!(101), dec!(5))).unwrap();
+    assert_eq!((ack.filled_qty, ack.resting_qty, ack.cancelled_qty), (dec!(0), dec!(0), dec!(5)));
+    assert_eq!(book.depth(Side::Sell, 10), [(dec!(101), dec!(3))]);
+
+    // Si alcanza, se ejecuta entera como cualquier límite
+    let ack = book.submit(fok_or_rest(4, dec!(101), dec!(3))).unwrap();
+    assert_eq!((ack.filled_qty, ack.resting_qty), (dec!(3), dec!(0)));
+    assert_eq!(book.depth(Side::Buy, 10), [(dec!(100), dec!(5))]);
+}
+
+// --- ETIQUETAS DE ESTRATEGIA ---
+
+#[test]
+fn trades_carry_the_strategy_and_venue_tags_of_both_orders() {
+    let mut book = OrderBook::new();
+    let tagged = |id, side, strategy: &str, venue: &str| {
+        Order::builder(id, side, dec!(100), dec!(2)).account(id).strategy_id(strategy).venue_tag(venue).build().unwrap()
+    };
+    book.add_order(tagged(1, Side::Sell, "mm", "lit")).unwrap();
+    book.add_order(order(2, Side::Sell, dec!(100), dec!(2))).unwrap();
+
+    let trades = book.add_order(tagged(3, Side::Buy, "momentum", "sor")).unwrap();
+    assert_eq!(trades.len(), 1);
+    let trade = &trades[0];
+    assert_eq!((trade.taker_strategy_id.as_deref(), trade.taker_venue_tag.as_deref()), (Some("momentum"), Some("sor")));
+    assert_eq!((trade.maker_strategy_id.as_deref(), trade.maker_venue_tag.as_deref()), (Some("mm"), Some("lit")));
+
+    // Sin etiquetas el trade sale sin ellas, y el matching no cambia
+    let trades = book.add_order(order(4, Side::Buy, dec!(100), dec!(2))).unwrap();
+    assert_eq!((trades[0].maker_id, trades[0].taker_strategy_id.as_deref(), trades[0].maker_strategy_id.as_deref()), (2, None, None));
+}
+
+// --- MODIFICACIÓN A CERO ---
+
+#[test]
+fn an_amend_to_zero_cancels_only_when_configured() {
+    let mut book = OrderBook::new();
+    book.add_order(order(1, Side::Buy, dec!(100), dec!(2))).unwrap();
+    assert_eq!(book.modify_order(1, dec!(100), dec!(0)), None);
+    assert_eq!(book.order(1).map(|o| o.amount), Some(dec!(2)));
+    let zero = Order { amount: dec!(0), ..order(2, Side::Buy, dec!(100), dec!(1)) };
+    assert_eq!(book.add_order(zero), Err(OrderError::InvalidAmount(dec!(0))));
+
+    let mut book = OrderBook::builder().amend_to_zero_cancels(true).build();
+    book.enable_events();
+    book.add_order(order(1, Side::Buy, dec!(100), dec!(2))).unwrap();
+    book.drain_events();
+    assert_eq!(book.modify_order(1, dec!(100), dec!(0)), Some(Vec::new()));
+    assert!(book.order(1).is_none());
+    assert!(book.drain_events().iter().any(|e| matches!(&e.event, BookEvent::OrderCancelled { order, .. } if order.id == 1)));
+    // Una orden que no existe sigue siendo `None`
+    assert_eq!(book.modify_order(1, dec!(100), dec!(0)), None);
+}
+
+// --- TOPE ABSOLUTO DE PRECIO ---
+
+#[test]
+fn a_sweep_halts_at_its_absolute_price_cap() {
+    let mut book = OrderBook::new();
+    book.add_order(order(1, Side::Sell, dec!(100), dec!(1))).unwrap();
+    book.add_order(order(2, Side::Sell, dec!(101), dec!(1))).unwrap();
+    book.add_order(order(3, Side::Sell, dec!(105), dec!(5))).unwrap();
+
+    // Mercado: ejecuta hasta 101 y el resto se cancela como siempre
+    let market = Order { order_type: OrderType::Market, price_cap: Some(dec!(101)), ..order(4, Side::Buy, dec!(0), dec!(3)) };
+    let ack = book.submit(market).unwrap();
+    assert_eq!(ack.trades.iter().map(|t| t.price).collect::<Vec<_>>(), [dec!(100), dec!(101)]);
+    assert_eq!((ack.filled_qty, ack.cancelled_qty), (dec!(2), dec!(1)));
+
+    // Límite GTC más allá del tope: el resto cruzaría, así que tampoco descansa
+    let limit = Order { price_cap: Some(dec!(104)), ..order(5, Side::Buy, dec!(106), dec!(2)) };
+    let ack = book.submit(limit).unwrap();
+    assert_eq!((ack.filled_qty, ack.resting_qty, ack.cancelled_qty), (dec!(0), dec!(0), dec!(2)));
+    assert_eq!(book.depth(Side::Sell, 10), [(dec!(105), dec!(5))]);
+
+    // Un GTC cuyo resto ya no cruza queda en reposo a su límite
+    book.add_order(order(6, Side::Sell, dec!(102), dec!(1))).unwrap();
+    let limit = Order { price_cap: Some(dec!(102)), ..order(7, Side::Buy, dec!(103), dec!(3)) };
+    let ack = book.submit(limit).unwrap();
+    assert_eq!((ack.filled_qty, ack.resting_qty), (dec!(1), dec!(2)));
+    assert_eq!(book.best_bid(), Some(dec!(103)));
+}
@@ -0,0 +1,88 @@
+// --- ÓRDENES CONDICIONALES (MARKET-IF-TOUCHED) ---
+//
+// Una orden MIT no entra al libro: espera en la lista de disparadores hasta
+// que el último precio toque su nivel y recién ahí sale como orden de mercado.
+// Es el espejo de un stop: la MIT de compra se dispara cuando el precio baja
+// hasta el disparador, la de venta cuando sube. Después de cada cambio del
+// último precio se revisa la lista; un disparo puede mover el precio y
+// disparar a otras, en cascada.
+
+use rust_decimal::Decimal;
+
+use super::{Order, OrderBook, OrderType, Side, Trade};
+use crate::logging::book_log;
+
+// `true` si el último precio `last` ya tocó el disparador de la orden.
+fn touched(order: &Order, last: Decimal) -> bool {
+    let OrderType::MarketIfTouched { trigger } = order.order_type else { return false };
+    match order.side {
+        Side::Buy => last <= trigger,
+        Side::Sell => last >= trigger,
+    }
+}
+
+impl OrderBook {
+    /// Órdenes MIT esperando su disparador, en orden de llegada.
+    pub fn pending_triggers(&self) -> &[Order] {
+        &self.triggers
+    }
+
+    // Deja la orden esperando su disparador (o la dispara ya, si el precio lo tocó).
+    pub(super) fn park_trigger(&mut self, order: Order) -> Vec<Trade> {
+        book_log!("   🎯 MIT #{} esperando que el último precio toque {:?}", order.id, order.order_type);
+        self.triggers.push(order);
+        self.fire_triggers()
+    }
+
+    // Dispara, en orden de llegada, las MIT que el último precio tocó. Cada una
+    // sale como orden de mercado; si mueve el precio se vuelve a revisar la lista.
+    pub(super) fn fire_triggers(&mut self) -> Vec<Trade> {
+        let mut trades = Vec::new();
+        while let Some(last) = self.last_price
+            && let Some(pos) = self.triggers.iter().position(|order| touched(order, last))
+        {
+            let mut order = self.triggers.remove(pos);
+            book_log!("   🎯 MIT #{} disparada: último precio {} ({:?})", order.id, last, order.order_type);
+            order.order_type = OrderType::Market;
+            match self.execute(order) {
+                Ok(fills) => trades.extend(fills),
+                Err(e) => book_log!("   💥 MIT disparada sin completar: {}", e),
+            }
+            self.refresh_reference_price();
+        }
+        trades
+    }
+
+    // Cancela una MIT que todavía no se disparó.
+    pub(super) fn cancel_trigger(&mut self, id: u64) -> Option<Order> {
+        let pos = self.triggers.iter().position(|order| order.id == id)?;
+        Some(self.triggers.remove(pos))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal_macros::dec;
+
+    use super::*;
+    use crate::orderbook::tests::order;
+
+    #[test]
+    fn a_buy_mit_turns_into_a_market_buy_when_the_price_falls_to_it() {
+        let mut book = OrderBook::new();
+        book.add_order(order(1, Side::Sell, dec!(101), dec!(5))).unwrap();
+        book.add_order(order(2, Side::Buy, dec!(100), dec!(1))).unwrap();
+        book.add_order(order(3, Side::Buy, dec!(96), dec!(1))).unwrap();
+        let mit = Order { order_type: OrderType::MarketIfTouched { trigger: dec!(96) }, ..order(4, Side::Buy, dec!(0), dec!(2)) };
+        assert!(book.add_order(mit).unwrap().is_empty());
+        assert_eq!(book.pending_triggers().len(), 1);
+
+        // La venta lleva el último precio de 100 a 96 y dispara la MIT
+        let market_sell = Order { order_type: OrderType::Market, ..order(5, Side::Sell, dec!(0), dec!(2)) };
+        let trades = book.add_order(market_sell).unwrap();
+        let fills: Vec<_> = trades.iter().map(|t| (t.taker_id, t.maker_id, t.price)).collect();
+        assert_eq!(fills, [(5, 2, dec!(100)), (5, 3, dec!(96)), (4, 1, dec!(101))]);
+        assert!(book.pending_triggers().is_empty());
+        assert_eq!(book.order(1).map(|o| o.amount), Some(dec!(3)));
+    }
+}
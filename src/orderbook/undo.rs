@@ -0,0 +1,200 @@
+// --- DESHACER EL ÚLTIMO COMANDO ---
+//
+// Cada alta, cancelación y modificación en el lugar deja en un log la operación
+// inversa: el alta se deshace sacando la orden; la cancelación, devolviéndola a
+// su lugar exacto en la cola (o en la lista de pendientes / MIT); la reducción,
+// restaurando la cantidad sin perder prioridad. Un comando que generó fills (o
+// que sacó o achicó otras órdenes, como el self-trade prevention o el tope de
+// profundidad) no se puede deshacer: deja una marca que bloquea el undo.
+//
+// Una modificación que pierde prioridad es una cancelación más un alta, y se
+// deshace en dos pasos. Deshacer publica `BookEvent::Undone`; al reaplicar el
+// journal, el libro lleva el mismo log y deshace lo mismo.
+
+use std::collections::VecDeque;
+
+use rust_decimal::Decimal;
+
+use super::{Order, OrderBook, RestingOrder, TradingState};
+use crate::error::OrderError;
+use crate::events::BookEvent;
+use crate::logging::book_log;
+
+// Cuántos comandos hacia atrás se pueden deshacer.
+const UNDO_DEPTH: usize = 64;
+
+// Operación que revierte un comando.
+#[derive(Debug, Clone)]
+pub(super) enum Inverse {
+    /// Sacar la orden que entró (del libro, de pendientes o de las MIT).
+    Remove { id: u64 },
+    /// Devolver una orden cancelada al lugar que ocupaba en su nivel.
+    Resting { resting: RestingOrder, pos: usize },
+    Pending { order: Order, pos: usize },
+    Trigger { order: Order, pos: usize },
+    /// Volver a la cantidad (y pico) anterior a una reducción en el lugar.
+    Resize { id: u64, amount: Decimal, shown: Decimal },
+    /// El comando ejecutó fills o tocó otras órdenes: no hay vuelta atrás.
+    Irreversible,
+}
+
+pub(super) type UndoLog = VecDeque<Inverse>;
+
+impl OrderBook {
+    /// Revierte el último comando (alta, cancelación o modificación) de los
+    /// últimos 64. Falla si no queda nada que deshacer, si el último comando
+    /// ejecutó fills o con el libro en `Halted`.
+    pub fn undo_last(&mut self) -> Result<(), OrderError> {
+        if self.trading_state == TradingState::Halted {
+            return Err(OrderError::MarketHalted(self.trading_state));
+        }
+        match self.undo_log.back() {
+            None => return Err(OrderError::NotSupported("no hay comando para deshacer")),
+            Some(Inverse::Irreversible) => return Err(OrderError::NotSupported("deshacer un comando que generó fills")),
+            Some(_) => {}
+        }
+        let inverse = self.undo_log.pop_back().expect("hay un comando para deshacer");
+        self.emit(BookEvent::Undone);
+        let id = match &inverse {
+            Inverse::Remove { id } | Inverse::Resize { id, .. } => *id,
+            Inverse::Resting { resting, .. } => resting.order.id,
+            Inverse::Pending { order, .. } | Inverse::Trigger { order, .. } => order.id,
+            Inverse::Irreversible => unreachable!("se descartó arriba"),
+        };
+        match inverse {
+            Inverse::Remove { id } => self.undo_add(id),
+            Inverse::Resting { resting, pos } => {
+                book_log!("   ↩️  Deshecha la cancelación de #{}: vuelve a su lugar en la cola", resting.order.id);
+                let (side, price) = (resting.order.side, resting.order.price);
+                self.index.insert(&resting.order);
+                let level_total = self.side_mut(side).insert_at(resting, pos);
+                self.emit_level(side, price, level_total);
+                self.refresh_reference_price();
+            }
+            Inverse::Pending { order, pos } => {
+                book_log!("   ↩️  Deshecha la cancelación de #{}: vuelve a pendientes", order.id);
+                self.pending.insert(pos, order);
+            }
+            Inverse::Trigger { order, pos } => {
+                book_log!("   ↩️  Deshecha la cancelación de la MIT #{}", order.id);
+                self.triggers.insert(pos, order);
+            }
+            Inverse::Resize { id, amount, shown } => {
+                book_log!("   ↩️  Deshecha la modificación de #{}: vuelve a {}", id, amount);
+                let (side, price) = self.index.get(id).expect("la orden modificada sigue en el libro");
+                let book_side = self.side_mut(side);
+                let key = book_side.key(price);
+                let level = book_side.levels.get_mut(&key).expect("nivel de la orden modificada");
+                let resting = level.orders.iter_mut().find(|o| o.order.id == id).expect("orden modificada en su nivel");
+                let increase = amount - resting.order.amount;
+                resting.order.amount = amount;
+                resting.shown = shown;
+                level.total += increase;
+                book_side.volume += increase;
+                let level_total = level.visible_total();
+                self.emit_level(side, price, level_total);
+            }
+            Inverse::Irreversible => unreachable!("se descartó arriba"),
+        }
+        self.sync_hold(id);
+        self.repeg();
+        Ok(())
+    }
+
+    // Saca una orden recién entrada, esté donde esté.
+    fn undo_add(&mut self, id: u64) {
+        book_log!("   ↩️  Deshecha el alta de #{}", id);
+        if let Some(pos) = self.pending.iter().position(|o| o.id == id) {
+            self.pending.remove(pos);
+            return;
+        }
+        if self.cancel_trigger(id).is_some() {
+            return;
+        }
+        if let Some((side, price)) = self.index.get(id)
+            && let Some((resting, _, level_total)) = self.side_mut(side).remove(id, price)
+        {
+            self.index.remove(&resting.order);
+            self.emit_level(side, price, level_total);
+            self.refresh_reference_price();
+        }
+    }
+
+    // Anota el inverso de un comando; el más viejo se descarta al pasar el tope.
+    pub(super) fn record_undo(&mut self, inverse: Inverse) {
+        if self.undo_log.len() == UNDO_DEPTH {
+            self.undo_log.pop_front();
+        }
+        self.undo_log.push_back(inverse);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal_macros::dec;
+
+    use super::*;
+    use crate::orderbook::Side;
+    use crate::orderbook::tests::order;
+
+    fn ids(book: &OrderBook) -> Vec<u64> {
+        book.iter_orders(Side::Buy).map(|o| o.id).collect()
+    }
+
+    #[test]
+    fn undo_removes_an_add_and_restores_a_cancel_in_place() {
+        let mut book = OrderBook::new();
+        for id in 1..=3 {
+            book.add_order(order(id, Side::Buy, dec!(100), dec!(1))).unwrap();
+        }
+
+        book.add_order(order(4, Side::Buy, dec!(101), dec!(2))).unwrap();
+        book.undo_last().unwrap();
+        assert_eq!((ids(&book), book.best_bid()), (vec![1, 2, 3], Some(dec!(100))));
+
+        // La cancelada vuelve a su lugar en la cola, no al final
+        book.cancel_order(2).unwrap();
+        book.undo_last().unwrap();
+        assert_eq!(ids(&book), [1, 2, 3]);
+        assert_eq!(book.queue_position(2), Some((dec!(1), dec!(3))));
+
+        book.modify_order(1, dec!(100), dec!(0.5)).unwrap();
+        book.undo_last().unwrap();
+        assert_eq!(book.order(1).map(|o| o.amount), Some(dec!(1)));
+    }
+
+    #[test]
+    fn undo_refuses_a_command_with_fills() {
+        let mut book = OrderBook::new();
+        assert!(matches!(book.undo_last(), Err(OrderError::NotSupported(_))));
+
+        book.add_order(order(1, Side::Sell, dec!(100), dec!(2))).unwrap();
+        book.add_order(order(2, Side::Buy, dec!(100), dec!(1))).unwrap();
+        assert_eq!(book.undo_last(), Err(OrderError::NotSupported("deshacer un comando que generó fills")));
+        assert_eq!(book.order(1).map(|o| o.amount), Some(dec!(1)));
+    }
+
+    #[test]
+    fn a_replay_undoes_the_same_commands_as_the_live_book() {
+        let clock = crate::VirtualClock::new(0);
+        let mut live = OrderBook::builder().clock(clock.clone()).build();
+        live.enable_events();
+        let gtt = Order { time_in_force: crate::orderbook::TimeInForce::Gtt { expire_at: 100 }, ..order(1, Side::Buy, dec!(100), dec!(1)) };
+        live.add_order(gtt).unwrap();
+        live.add_order(order(2, Side::Buy, dec!(99), dec!(1))).unwrap();
+
+        // El vencimiento de #1 lo decide el libro: no es un comando que se pueda deshacer
+        clock.set(100);
+        live.add_order(order(3, Side::Buy, dec!(98), dec!(1))).unwrap();
+        live.undo_last().unwrap();
+        live.undo_last().unwrap();
+        assert!(ids(&live).is_empty());
+
+        let mut replayed = OrderBook::new();
+        for event in live.drain_events() {
+            replayed.apply(&event.event);
+        }
+        assert_eq!(ids(&replayed), ids(&live));
+        assert_eq!(replayed.depth(Side::Buy, 10), live.depth(Side::Buy, 10));
+    }
+}
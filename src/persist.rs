@@ -0,0 +1,274 @@
+// --- SNAPSHOTS DEL LIBRO A DISCO ---
+//
+// Guarda el estado completo del libro (órdenes en reposo en orden de prioridad,
+// secuencia, precio de referencia, último precio y último id de trade, estado
+// de negociación, posiciones por cuenta y órdenes MIT sin disparar) para
+// arrancar desde ahí en vez de reaplicar toda la grabación. El archivo se
+// describe solo:
+//
+//     b"HFTS" | versión (u8) | compresión (u8) | payload JSON
+//
+// Con la feature `zstd` el payload puede ir comprimido.
+// Para libros grandes, `OrderBook::to_bytes` guarda lo que está en reposo en
+// un formato binario más rápido (ver `binary.rs`).
+
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::balances::Balances;
+use crate::config::BookConfig;
+use crate::logging::book_log;
+use crate::orderbook::{Order, OrderBook, RestingOrder, TradeIdCounter, TradingState};
+use crate::recorder::read_events;
+
+const MAGIC: &[u8; 4] = b"HFTS";
+const FORMAT_VERSION: u8 = 1;
+
+/// Compresión del payload de un snapshot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Compression {
+    #[default]
+    None,
+    /// zstd con el nivel indicado (1-22; 3 es el default de zstd).
+    #[cfg(feature = "zstd")]
+    Zstd { level: i32 },
+}
+
+impl Compression {
+    fn tag(self) -> u8 {
+        match self {
+            Compression::None => 0,
+            #[cfg(feature = "zstd")]
+            Compression::Zstd { .. } => 1,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct RestingState {
+    order: Order,
+    shown: Decimal,
+    #[serde(default)] // Snapshots viejos: la prioridad sale del orden en el archivo
+    arrival_seq: u64,
+    // Lugar en la cola de todo el libro al guardar (1 = la primera del mejor
+    // nivel de compra). Al cargar, las órdenes entran en este orden y no en el
+    // del archivo ni por id: cada nivel queda con su cola exacta.
+    #[serde(default)]
+    priority_seq: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+struct BookState {
+    sequence: u64,
+    reference_price: Option<Decimal>,
+    #[serde(default)]
+    last_price: Option<Decimal>,
+    last_trade_id: u64,
+    #[serde(default)]
+    trading_state: TradingState,
+    orders: Vec<RestingState>,
+    #[serde(default)] // Snapshots viejos: todas las cuentas arrancan planas
+    positions: BTreeMap<u64, Decimal>,
+    #[serde(default)]
+    triggers: Vec<Order>,
+    #[serde(default)]
+    balances: Balances,
+    #[serde(default)] // (tamaño original, ejecutado) de las órdenes con `cancel_at_fill_ratio`
+    fill_progress: BTreeMap<u64, (Decimal, Decimal)>,
+}
+
+fn invalid(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+/// Escribe el estado de `book` en `path`.
+pub fn save_snapshot<P: AsRef<Path>>(path: P, book: &OrderBook, compression: Compression) -> io::Result<()> {
+    let state = BookState {
+        sequence: book.sequence(),
+        reference_price: book.reference_price(),
+        last_price: book.last_price(),
+        last_trade_id: book.trade_id_counter().last(),
+        trading_state: book.trading_state(),
+        orders: book.resting_orders()
+            .zip(1..)
+            .map(|(resting, priority_seq)| RestingState {
+                order: resting.order.clone(),
+                shown: resting.shown,
+                arrival_seq: resting.arrival_seq,
+                priority_seq,
+            })
+            .collect(),
+        positions: book.positions().iter().collect(),
+        triggers: book.pending_triggers().to_vec(),
+        balances: book.balances().clone(),
+        fill_progress: book.fill_progress().clone(),
+    };
+
+    let mut writer = BufWriter::new(File::create(path)?);
+    writer.write_all(MAGIC)?;
+    writer.write_all(&[FORMAT_VERSION, compression.tag()])?;
+    match compression {
+        Compression::None => serde_json::to_writer(&mut writer, &state)?,
+        #[cfg(feature = "zstd")]
+        Compression::Zstd { level } => {
+            let mut encoder = zstd::Encoder::new(&mut writer, level)?;
+            serde_json::to_writer(&mut encoder, &state)?;
+            encoder.finish()?;
+        }
+    }
+    writer.flush()
+}
+
+/// Como `save_snapshot`, pero atómico: escribe a `<path>.tmp`, lo baja a disco
+/// y lo renombra a `path`. Quien lea `path` nunca ve un snapshot a medio escribir.
+pub fn save_snapshot_atomic<P: AsRef<Path>>(path: P, book: &OrderBook, compression: Compression) -> io::Result<()> {
+    let path = path.as_ref();
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    save_snapshot(&tmp, book, compression)?;
+    File::open(&tmp)?.sync_all()?;
+    fs::rename(&tmp, path)
+}
+
+/// Lee un snapshot y reconstruye el libro con `config`. La compresión se
+/// detecta por el encabezado; un snapshot zstd necesita la feature `zstd`.
+pub fn load_snapshot<P: AsRef<Path>>(path: P, config: BookConfig) -> io::Result<OrderBook> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut header = [0u8; 6];
+    reader.read_exact(&mut header)?;
+    if &header[..4] != MAGIC {
+        return Err(invalid("no es un snapshot del libro (magic inválido)"));
+    }
+    if header[4] != FORMAT_VERSION {
+        return Err(invalid(format!("versión de snapshot no soportada: {}", header[4])));
+    }
+
+    let state: BookState = match header[5] {
+        0 => serde_json::from_reader(reader)?,
+        #[cfg(feature = "zstd")]
+        1 => serde_json::from_reader(zstd::Decoder::new(reader)?)?,
+        #[cfg(not(feature = "zstd"))]
+        1 => return Err(invalid("snapshot comprimido con zstd: compilar con --features zstd")),
+        other => return Err(invalid(format!("compresión desconocida: {other}"))),
+    };
+
+    // Orden estable: sin `priority_seq` (todas en 0) se respeta el del archivo
+    let mut orders = state.orders;
+    orders.sort_by_key(|resting| resting.priority_seq);
+    let orders = orders.into_iter().map(|resting| RestingOrder {
+        order: resting.order,
+        shown: resting.shown,
+        arrival_seq: resting.arrival_seq,
+    });
+    let mut book = OrderBook::restore(
+        config,
+        state.sequence,
+        state.reference_price,
+        TradeIdCounter::starting_after(state.last_trade_id),
+        orders,
+    );
+    book.restore_trading_state(state.trading_state);
+    book.restore_last_price(state.last_price);
+    book.restore_positions(state.positions);
+    book.restore_triggers(state.triggers);
+    book.restore_balances(state.balances);
+    book.restore_fill_progress(state.fill_progress);
+    Ok(book)
+}
+
+/// Recupera un libro: carga el snapshot y reaplica del journal (un JSONL de
+/// `EventRecorder`) sólo los eventos con secuencia mayor a la del snapshot.
+/// Los anteriores ya están incluidos en el snapshot y se saltean.
+pub fn recover_book<P: AsRef<Path>, Q: AsRef<Path>>(snapshot: P, journal: Q, config: BookConfig) -> io::Result<OrderBook> {
+    let mut book = load_snapshot(snapshot, config)?;
+    let from = book.sequence();
+    let (mut applied, mut skipped) = (0, 0);
+    for sequenced in read_events(journal)? {
+        if sequenced.seq <= from {
+            skipped += 1;
+            continue;
+        }
+        book.apply(&sequenced.event);
+        applied += 1;
+    }
+    book_log!("♻️  Libro recuperado: snapshot en la secuencia {}, {} eventos reaplicados, {} salteados", from, applied, skipped);
+    Ok(book)
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal_macros::dec;
+
+    use super::*;
+    use crate::orderbook::tests::order;
+    use crate::orderbook::{Side, Visibility};
+
+    fn traded_book() -> OrderBook {
+        let mut book = OrderBook::new();
+        book.add_order(order(1, Side::Sell, dec!(101), dec!(5))).unwrap();
+        book.add_order(Order { visibility: Visibility::Iceberg { peak: dec!(2) }, ..order(2, Side::Sell, dec!(102), dec!(8)) }).unwrap();
+        book.add_order(order(3, Side::Buy, dec!(99), dec!(4))).unwrap();
+        book.add_order(order(4, Side::Buy, dec!(101), dec!(3))).unwrap();
+        book
+    }
+
+    fn assert_restored(restored: &OrderBook, book: &OrderBook) {
+        assert!(restored.diff(book).is_empty(), "{:?}", restored.diff(book));
+        assert_eq!(restored.sequence(), book.sequence());
+        assert_eq!(restored.reference_price(), book.reference_price());
+        assert_eq!(restored.trade_id_counter().last(), 1);
+        assert_eq!(restored.positions(), book.positions());
+    }
+
+    #[test]
+    #[cfg(feature = "zstd")]
+    fn zstd_snapshot_restores_an_identical_book() {
+        let book = traded_book();
+        let dir = tempfile::tempdir().unwrap();
+        let (compressed, plain) = (dir.path().join("book.zst.snap"), dir.path().join("book.snap"));
+        save_snapshot(&compressed, &book, Compression::Zstd { level: 3 }).unwrap();
+        save_snapshot(&plain, &book, Compression::None).unwrap();
+        assert!(fs::metadata(&compressed).unwrap().len() < fs::metadata(&plain).unwrap().len());
+
+        let restored = load_snapshot(&compressed, BookConfig::default()).unwrap();
+        assert_restored(&restored, &book);
+    }
+
+    #[test]
+    fn snapshot_keeps_the_last_trade_price() {
+        let book = traded_book();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("book.snap");
+        save_snapshot(&path, &book, Compression::None).unwrap();
+
+        let restored = load_snapshot(&path, BookConfig::default()).unwrap();
+        assert_restored(&restored, &book);
+        assert_eq!(restored.last_price(), Some(dec!(101)));
+        assert_eq!(restored.touch().and_then(|touch| touch.last), Some(dec!(101)));
+    }
+
+    #[test]
+    fn restore_keeps_the_exact_queue_order_of_each_level() {
+        let mut book = OrderBook::new();
+        for id in 1..=3 {
+            book.add_order(order(id, Side::Sell, dec!(100), dec!(1))).unwrap();
+        }
+        // Agrandar la 1 la manda al final: la cola ya no va por id
+        book.modify_order(1, dec!(100), dec!(2)).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("book.snap");
+        save_snapshot(&path, &book, Compression::None).unwrap();
+
+        let mut restored = load_snapshot(&path, BookConfig::default()).unwrap();
+        for (id, ahead) in [(2, dec!(0)), (3, dec!(1)), (1, dec!(2))] {
+            assert_eq!(restored.queue_position(id), Some((ahead, dec!(4))));
+        }
+        let trades = restored.add_order(order(4, Side::Buy, dec!(100), dec!(4))).unwrap();
+        assert_eq!(trades.iter().map(|t| t.maker_id).collect::<Vec<_>>(), [2, 3, 1]);
+    }
+}
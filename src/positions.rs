@@ -0,0 +1,72 @@
+// --- POSICIONES POR CUENTA ---
+//
+// Posición neta de cada cuenta según los trades del libro: positiva si está
+// comprada, negativa si está vendida. Sirve para los flujos de cierre, donde
+// la cantidad se pide como porcentaje de la posición y no en unidades.
+
+use std::collections::BTreeMap;
+
+use rust_decimal::prelude::*;
+
+use crate::error::OrderError;
+use crate::orderbook::Side;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Positions {
+    by_account: BTreeMap<u64, Decimal>, // Sin entradas en cero: las cuentas planas no figuran
+}
+
+impl Positions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Posición neta de la cuenta (0 si está plana).
+    pub fn get(&self, account: u64) -> Decimal {
+        self.by_account.get(&account).copied().unwrap_or_default()
+    }
+
+    /// Cuentas con posición abierta, por número de cuenta.
+    pub fn iter(&self) -> impl Iterator<Item = (u64, Decimal)> + '_ {
+        self.by_account.iter().map(|(account, position)| (*account, *position))
+    }
+
+    /// Suma un fill de `amount` en `side` a la posición de la cuenta.
+    pub fn record(&mut self, account: u64, side: Side, amount: Decimal) {
+        let delta = match side {
+            Side::Buy => amount,
+            Side::Sell => -amount,
+        };
+        let position = self.by_account.entry(account).or_default();
+        *position += delta;
+        if position.is_zero() {
+            self.by_account.remove(&account);
+        }
+    }
+
+    /// Cuánto puede operar la cuenta en `side` sin que su posición neta pase
+    /// de ±`limit` (0 si ya está en el límite o más allá).
+    pub fn room(&self, account: u64, side: Side, limit: Decimal) -> Decimal {
+        let position = self.get(account);
+        let room = match side {
+            Side::Buy => limit - position,
+            Side::Sell => limit + position,
+        };
+        room.max(Decimal::zero())
+    }
+
+    /// Lado y cantidad de una orden que cierra `percent`% de la posición
+    /// (0 < percent <= 100). Falla si la cuenta está plana.
+    pub fn closing_order(&self, account: u64, percent: Decimal) -> Result<(Side, Decimal), OrderError> {
+        if percent <= Decimal::zero() || percent > Decimal::ONE_HUNDRED {
+            return Err(OrderError::InvalidPercent(percent));
+        }
+        let position = self.get(account);
+        if position.is_zero() {
+            return Err(OrderError::FlatPosition { account });
+        }
+        let side = if position > Decimal::zero() { Side::Sell } else { Side::Buy };
+        let amount = position.abs().checked_mul(percent).ok_or(OrderError::Overflow("cierre de posición"))? / Decimal::ONE_HUNDRED;
+        Ok((side, amount))
+    }
+}
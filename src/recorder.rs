@@ -0,0 +1,122 @@
+// --- GRABACIÓN DE EVENTOS (JSONL) ---
+//
+// Una línea JSON por evento, con su número de secuencia. Los eventos de comando
+// permiten reconstruir el libro completo; el resto queda para auditoría.
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+use crate::events::SequencedEvent;
+use crate::orderbook::OrderBook;
+
+/// Cuándo se baja el buffer a disco.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlushPolicy {
+    /// Después de cada evento (más seguro, más lento).
+    EveryEvent,
+    /// Cada N eventos.
+    EveryN(usize),
+    /// Sólo al llamar a `flush` (o al soltar el recorder).
+    Manual,
+}
+
+pub struct EventRecorder {
+    writer: BufWriter<File>,
+    policy: FlushPolicy,
+    unflushed: usize,
+}
+
+impl EventRecorder {
+    /// Abre (o crea) el archivo y agrega los eventos al final.
+    pub fn create<P: AsRef<Path>>(path: P, policy: FlushPolicy) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self { writer: BufWriter::new(file), policy, unflushed: 0 })
+    }
+
+    pub fn record(&mut self, event: &SequencedEvent) -> io::Result<()> {
+        serde_json::to_writer(&mut self.writer, event)?;
+        self.writer.write_all(b"\n")?;
+        self.unflushed += 1;
+
+        let should_flush = match self.policy {
+            FlushPolicy::EveryEvent => true,
+            FlushPolicy::EveryN(n) => self.unflushed >= n,
+            FlushPolicy::Manual => false,
+        };
+        if should_flush {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    pub fn record_all<'a>(&mut self, events: impl IntoIterator<Item = &'a SequencedEvent>) -> io::Result<()> {
+        for event in events {
+            self.record(event)?;
+        }
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.unflushed = 0;
+        self.writer.flush()
+    }
+}
+
+/// Lee todos los eventos de un archivo JSONL.
+pub fn read_events<P: AsRef<Path>>(path: P) -> io::Result<Vec<SequencedEvent>> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut events = Vec::new();
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        events.push(serde_json::from_str(&line)?);
+    }
+    Ok(events)
+}
+
+/// Reaplica una grabación sobre `book` (normalmente uno recién creado).
+/// Devuelve la cantidad de eventos leídos.
+pub fn replay<P: AsRef<Path>>(path: P, book: &mut OrderBook) -> io::Result<usize> {
+    let events = read_events(path)?;
+    for sequenced in &events {
+        book.apply(&sequenced.event);
+    }
+    Ok(events.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal_macros::dec;
+
+    use super::*;
+    use crate::orderbook::Side;
+    use crate::orderbook::tests::order;
+
+    #[test]
+    fn replaying_a_recording_rebuilds_the_book() {
+        let mut book = OrderBook::new();
+        book.enable_events();
+        book.add_order(order(1, Side::Sell, dec!(101), dec!(5))).unwrap();
+        book.add_order(order(2, Side::Sell, dec!(102), dec!(3))).unwrap();
+        book.add_order(order(3, Side::Buy, dec!(99), dec!(4))).unwrap();
+        book.add_order(order(4, Side::Buy, dec!(101), dec!(2))).unwrap();
+        book.cancel_order(3);
+        book.add_order(order(5, Side::Buy, dec!(98), dec!(1))).unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("session.jsonl");
+        let mut recorder = EventRecorder::create(&path, FlushPolicy::EveryN(3)).unwrap();
+        let events = book.drain_events();
+        recorder.record_all(&events).unwrap();
+        recorder.flush().unwrap();
+
+        assert_eq!(read_events(&path).unwrap(), events);
+        let mut replayed = OrderBook::new();
+        assert_eq!(replay(&path, &mut replayed).unwrap(), events.len());
+        assert!(replayed.diff(&book).is_empty(), "{:?}", replayed.diff(&book));
+        assert_eq!(replayed, book);
+    }
+}
@@ -0,0 +1,142 @@
+// --- REPLAY DETERMINÍSTICO (REGRESIONES) ---
+//
+// Reproduce una grabación de comandos sobre un libro nuevo y devuelve el libro
+// final junto con todos los trades. Pensado para CI: la misma grabación tiene
+// que dar exactamente los mismos trades en cualquier versión del motor.
+// Nada depende del reloj de pared ni del orden de iteración de un HashMap.
+
+use std::io;
+use std::path::Path;
+
+use crate::config::BookConfig;
+use crate::events::{BookEvent, SequencedEvent};
+use crate::orderbook::{OrderBook, Trade};
+use crate::recorder::read_events;
+
+/// Reloj simulado: cada orden reaplicada recibe `start`, `start + step`, ...
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeterministicClock {
+    next: u64,
+    step: u64,
+}
+
+impl DeterministicClock {
+    pub fn new(start: u64, step: u64) -> Self {
+        Self { next: start, step }
+    }
+
+    pub fn tick(&mut self) -> u64 {
+        let now = self.next;
+        self.next += self.step;
+        now
+    }
+}
+
+/// Resultado de un replay.
+#[derive(Debug)]
+pub struct ReplayOutcome {
+    pub book: OrderBook,
+    /// Todos los trades, en orden de ejecución.
+    pub trades: Vec<Trade>,
+    /// Cantidad de comandos reaplicados (los eventos derivados no cuentan).
+    pub commands: usize,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ReplayHarness {
+    config: BookConfig,
+    clock: Option<DeterministicClock>,
+}
+
+impl ReplayHarness {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Configuración del libro sobre el que se reaplica (por defecto la estándar).
+    pub fn config(mut self, config: BookConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Reemplaza los timestamps grabados por los del reloj simulado.
+    /// Sin reloj, se respetan los timestamps de la grabación.
+    pub fn clock(mut self, clock: DeterministicClock) -> Self {
+        self.clock = Some(clock);
+        self
+    }
+
+    /// Reaplica los eventos de comando en orden de secuencia.
+    pub fn run(&self, events: &[SequencedEvent]) -> ReplayOutcome {
+        let mut book = OrderBook::with_config(self.config.clone());
+        let mut clock = self.clock;
+        let mut trades = Vec::new();
+        let mut commands = 0;
+
+        for sequenced in events {
+            let event = match (&sequenced.event, clock.as_mut()) {
+                (BookEvent::OrderAdded { order }, Some(clock)) => {
+                    let mut order = order.clone();
+                    order.timestamp = clock.tick();
+                    BookEvent::OrderAdded { order }
+                }
+                (BookEvent::Trade { .. } | BookEvent::TradePending { .. } | BookEvent::LevelDelta { .. }, _) => continue,
+                (event, _) => event.clone(),
+            };
+            commands += 1;
+            trades.extend(book.apply(&event));
+        }
+        ReplayOutcome { book, trades, commands }
+    }
+
+    /// Como `run`, leyendo la grabación de un archivo JSONL.
+    pub fn run_file<P: AsRef<Path>>(&self, path: P) -> io::Result<ReplayOutcome> {
+        Ok(self.run(&read_events(path)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal_macros::dec;
+
+    use super::*;
+    use crate::orderbook::Side;
+    use crate::orderbook::tests::order;
+
+    fn session() -> (Vec<SequencedEvent>, Vec<Trade>) {
+        let mut book = OrderBook::new();
+        book.enable_events();
+        let mut trades = Vec::new();
+        for (id, side, price, amount) in [
+            (1, Side::Sell, dec!(101), dec!(3)),
+            (2, Side::Sell, dec!(102), dec!(2)),
+            (3, Side::Buy, dec!(100), dec!(4)),
+            (4, Side::Buy, dec!(102), dec!(4)),
+            (5, Side::Sell, dec!(99), dec!(5)),
+        ] {
+            trades.extend(book.add_order(order(id, side, price, amount)).unwrap());
+        }
+        book.cancel_order(2);
+        (book.drain_events(), trades)
+    }
+
+    #[test]
+    fn replay_reproduces_the_recorded_trades() {
+        let (events, trades) = session();
+        let outcome = ReplayHarness::new().run(&events);
+        assert_eq!(outcome.trades, trades);
+        assert_eq!(outcome.commands, 6);
+        assert_eq!(outcome.book.open_orders(5).len(), 1);
+        assert_eq!(outcome.book.len(), 1);
+    }
+
+    #[test]
+    fn replays_with_a_deterministic_clock_are_byte_for_byte_identical() {
+        let (events, _) = session();
+        let harness = ReplayHarness::new().clock(DeterministicClock::new(1_000, 10));
+        let (a, b) = (harness.run(&events), harness.run(&events));
+        assert_eq!(serde_json::to_vec(&a.trades).unwrap(), serde_json::to_vec(&b.trades).unwrap());
+        assert_eq!(a.book, b.book);
+        assert_eq!(a.trades.iter().map(|t| t.timestamp).collect::<Vec<_>>(), [1_030, 1_030, 1_040]);
+    }
+}
@@ -0,0 +1,16 @@
+// --- LIQUIDACIÓN DE TRADES EN LOTES ---
+//
+// Un `SettlementHandler` recibe los trades del libro para liquidarlos afuera
+// (clearing, contabilidad, un ledger). Mandarlos de a uno cuesta una llamada
+// por fill; el libro los junta y entrega lotes de `BookConfig::settlement_batch`
+// trades, o lo que haya juntado cuando pasa `settlement_interval` desde el más
+// viejo sin entregar. Los lotes llegan en orden de emisión, sin huecos.
+
+use std::fmt;
+
+use crate::orderbook::Trade;
+
+pub trait SettlementHandler: fmt::Debug + Send + Sync {
+    /// Un lote de trades, en el orden en que se ejecutaron. Nunca viene vacío.
+    fn settle(&self, trades: &[Trade]);
+}
@@ -0,0 +1,74 @@
+// --- CONTENEDOR INDEXADO POR LADO ---
+//
+// Todo estado que existe una vez por lado (niveles, volúmenes, cachés) va en
+// un `SideMap`: se agrega una sola vez, para los dos lados a la vez, y se
+// accede por `Side` en vez de elegir a mano entre dos campos gemelos.
+
+use crate::orderbook::Side;
+
+/// Un valor de tipo `T` por cada lado del libro.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SideMap<T> {
+    buy: T,
+    sell: T,
+}
+
+impl<T> SideMap<T> {
+    pub fn new(buy: T, sell: T) -> Self {
+        Self { buy, sell }
+    }
+
+    /// Arma cada lado con `f(side)`.
+    pub fn from_fn(mut f: impl FnMut(Side) -> T) -> Self {
+        Self { buy: f(Side::Buy), sell: f(Side::Sell) }
+    }
+
+    pub fn get(&self, side: Side) -> &T {
+        match side {
+            Side::Buy => &self.buy,
+            Side::Sell => &self.sell,
+        }
+    }
+
+    pub fn get_mut(&mut self, side: Side) -> &mut T {
+        match side {
+            Side::Buy => &mut self.buy,
+            Side::Sell => &mut self.sell,
+        }
+    }
+
+    /// Los dos lados a la vez, (compra, venta), para tocarlos juntos.
+    pub fn both_mut(&mut self) -> (&mut T, &mut T) {
+        (&mut self.buy, &mut self.sell)
+    }
+
+    /// (lado, valor), primero la compra.
+    pub fn iter(&self) -> impl Iterator<Item = (Side, &T)> {
+        [(Side::Buy, &self.buy), (Side::Sell, &self.sell)].into_iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn each_side_keeps_its_own_value() {
+        let mut map = SideMap::new(1, 2);
+        assert_eq!((*map.get(Side::Buy), *map.get(Side::Sell)), (1, 2));
+
+        *map.get_mut(Side::Sell) += 10;
+        assert_eq!((*map.get(Side::Buy), *map.get(Side::Sell)), (1, 12));
+
+        let (buy, sell) = map.both_mut();
+        std::mem::swap(buy, sell);
+        assert_eq!(map.iter().collect::<Vec<_>>(), [(Side::Buy, &12), (Side::Sell, &1)]);
+    }
+
+    #[test]
+    fn from_fn_builds_each_side_from_its_side() {
+        let map = SideMap::from_fn(|side| format!("{side:?}"));
+        assert_eq!((map.get(Side::Buy).as_str(), map.get(Side::Sell).as_str()), ("Buy", "Sell"));
+        assert_eq!(SideMap::<Vec<u8>>::default(), SideMap::new(Vec::new(), Vec::new()));
+    }
+}
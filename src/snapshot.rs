@@ -0,0 +1,379 @@
+// --- SNAPSHOTS DE PROFUNDIDAD ---
+//
+// Foto agregada por nivel para publicar o mostrar. El modo decide si se revela
+// la cantidad oculta (órdenes hidden y reservas de icebergs): el público sólo
+// ve lo visible; el dueño del libro (o un cliente autorizado) ve además el total.
+// Los precios salen redondeados según `price_precision`, igual que en los deltas.
+
+use rust_decimal::Decimal;
+use serde::Serialize;
+
+use crate::orderbook::{OrderBook, Side};
+
+/// Quién pide el snapshot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SnapshotMode {
+    /// Sólo cantidades visibles; los niveles totalmente ocultos no aparecen.
+    #[default]
+    Public,
+    /// Incluye `total_amount` con lo oculto y las reservas de icebergs.
+    Owner,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SnapshotLevel {
+    pub price: Decimal,
+    pub visible_amount: Decimal,
+    /// Sólo en modo `Owner`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total_amount: Option<Decimal>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DepthSnapshot {
+    /// Secuencia del último evento incluido en la foto.
+    pub seq: u64,
+    pub bids: Vec<SnapshotLevel>,
+    pub asks: Vec<SnapshotLevel>,
+}
+
+impl DepthSnapshot {
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string(self)
+    }
+
+    /// CRC32 de los niveles visibles como texto `precio:cantidad`, bids y luego
+    /// asks, separados por `|`. Un cliente que mantiene el libro con deltas
+    /// puede calcularlo igual y detectar que se desincronizó.
+    pub fn checksum(&self) -> u32 {
+        let text = self.bids.iter()
+            .chain(&self.asks)
+            .map(|level| format!("{}:{}", level.price, level.visible_amount))
+            .collect::<Vec<_>>()
+            .join("|");
+        crc32(text.as_bytes())
+    }
+}
+
+// CRC-32 (IEEE 802.3), bit a bit: el texto es corto y así no hace falta una tabla.
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+// FNV-1a de 64 bits: estable entre ejecuciones y versiones de Rust, a diferencia
+// del `DefaultHasher` de std, que no garantiza el algoritmo.
+pub(crate) fn fnv1a(hash: u64, bytes: &[u8]) -> u64 {
+    bytes.iter().fold(hash, |hash, &byte| (hash ^ u64::from(byte)).wrapping_mul(0x0000_0100_0000_01B3))
+}
+
+pub(crate) const FNV_OFFSET: u64 = 0xCBF2_9CE4_8422_2325;
+
+/// Foto de sólo lectura del tope del libro. Es dueña de sus datos (`Send + Sync`),
+/// así que se puede mandar a otra tarea o hilo sin tocar el libro.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BookView {
+    pub seq: u64,
+    pub best_bid: Option<Decimal>,
+    pub best_ask: Option<Decimal>,
+    pub last_price: Option<Decimal>,
+    /// (precio, cantidad visible), mejor nivel primero.
+    pub bids: Vec<(Decimal, Decimal)>,
+    pub asks: Vec<(Decimal, Decimal)>,
+}
+
+impl BookView {
+    pub fn mid_price(&self) -> Option<Decimal> {
+        Some((self.best_bid? + self.best_ask?) / Decimal::TWO)
+    }
+
+    pub fn spread(&self) -> Option<Decimal> {
+        Some(self.best_ask? - self.best_bid?)
+    }
+}
+
+/// Foto tipada para un consumidor del mismo proceso que arranca en frío: toma
+/// el `Snapshot` y sigue con los `LevelDelta` de secuencia mayor a `seq`, sin
+/// pasar por JSON. Los precios salen en formato de display, como en los deltas.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    /// Secuencia del último evento incluido en la foto.
+    pub seq: u64,
+    pub last_price: Option<Decimal>,
+    /// Niveles públicos, mejor nivel primero (como mucho `depth`).
+    pub bids: Vec<Level>,
+    pub asks: Vec<Level>,
+}
+
+/// Resumen del tope del libro para feeds de tickers: puntas visibles con su
+/// cantidad y el último precio operado.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Touch {
+    pub bid: Option<Decimal>,
+    pub bid_size: Decimal,
+    pub ask: Option<Decimal>,
+    pub ask_size: Decimal,
+    pub last: Option<Decimal>,
+    /// Hora del libro al tomar el resumen (ver `OrderBook::set_clock`); sin reloj,
+    /// el mayor timestamp de orden recibido.
+    pub ts: u64,
+}
+
+/// Un nivel público de un lado, con lo acumulado desde el mejor precio. Sirve
+/// para gráficos de profundidad y VWAP de barrido en una sola pasada.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Level {
+    pub price: Decimal,
+    /// Cantidad visible del nivel.
+    pub amount: Decimal,
+    /// Suma de `amount` desde el mejor nivel hasta este, inclusive.
+    pub cumulative_amount: Decimal,
+    /// Órdenes visibles del nivel.
+    pub order_count: usize,
+}
+
+fn snapshot_levels(book: &OrderBook, side: Side, depth: usize, mode: SnapshotMode) -> Vec<SnapshotLevel> {
+    book.sides.get(side).levels()
+        .map(|level| SnapshotLevel {
+            price: book.display_price(level.price),
+            visible_amount: level.visible_total(),
+            total_amount: (mode == SnapshotMode::Owner).then(|| level.total()),
+        })
+        .filter(|level| mode == SnapshotMode::Owner || level.visible_amount > Decimal::ZERO)
+        .take(depth)
+        .collect()
+}
+
+impl OrderBook {
+    /// Los primeros `depth` niveles de cada lado, según lo que `mode` deja ver.
+    pub fn depth_snapshot(&self, depth: usize, mode: SnapshotMode) -> DepthSnapshot {
+        DepthSnapshot {
+            seq: self.sequence(),
+            bids: snapshot_levels(self, Side::Buy, depth, mode),
+            asks: snapshot_levels(self, Side::Sell, depth, mode),
+        }
+    }
+
+    /// Foto tipada de los primeros `depth` niveles públicos de cada lado (ver `Snapshot`).
+    pub fn snapshot(&self, depth: usize) -> Snapshot {
+        Snapshot {
+            seq: self.sequence(),
+            last_price: self.last_price(),
+            bids: self.levels(Side::Buy).take(depth).collect(),
+            asks: self.levels(Side::Sell).take(depth).collect(),
+        }
+    }
+
+    /// Captura una `BookView` con los primeros `levels` niveles de cada lado.
+    pub fn view(&self, levels: usize) -> BookView {
+        // Las puntas salen de la profundidad visible: un nivel sólo oculto no se muestra
+        let (bids, asks) = (self.depth(Side::Buy, levels.max(1)), self.depth(Side::Sell, levels.max(1)));
+        BookView {
+            seq: self.sequence(),
+            best_bid: bids.first().map(|(price, _)| *price),
+            best_ask: asks.first().map(|(price, _)| *price),
+            last_price: self.last_price(),
+            bids: bids.into_iter().take(levels).collect(),
+            asks: asks.into_iter().take(levels).collect(),
+        }
+    }
+
+    /// Puntas visibles y último precio en una sola llamada. `None` sólo si el
+    /// libro nunca recibió una orden; un libro que se vació devuelve puntas vacías.
+    pub fn touch(&self) -> Option<Touch> {
+        if !self.has_seen_orders() {
+            return None;
+        }
+        let top = |side| self.depth(side, 1).first().copied().map(|(price, size)| (self.display_price(price), size));
+        let (bid, ask) = (top(Side::Buy), top(Side::Sell));
+        Some(Touch {
+            bid: bid.map(|(price, _)| price),
+            bid_size: bid.map_or(Decimal::ZERO, |(_, size)| size),
+            ask: ask.map(|(price, _)| price),
+            ask_size: ask.map_or(Decimal::ZERO, |(_, size)| size),
+            last: self.last_price(),
+            ts: self.current_time(),
+        })
+    }
+
+    /// Niveles públicos de un lado, del mejor al peor, con el total acumulado.
+    /// Como en `depth_with_counts`, los niveles sólo ocultos no aparecen y las
+    /// órdenes ocultas no se cuentan.
+    pub fn levels(&self, side: Side) -> impl Iterator<Item = Level> + '_ {
+        self.sides.get(side).levels()
+            .filter_map(|level| {
+                let amount = level.visible_total();
+                let order_count = level.orders.iter().filter(|o| o.visible() > Decimal::ZERO).count();
+                (amount > Decimal::ZERO).then_some((level.price, amount, order_count))
+            })
+            .scan(Decimal::ZERO, |cumulative, (price, amount, order_count)| {
+                *cumulative += amount;
+                Some(Level { price: self.display_price(price), amount, cumulative_amount: *cumulative, order_count })
+            })
+    }
+
+    /// Checksum de los primeros `depth` niveles públicos (ver `DepthSnapshot::checksum`).
+    pub fn checksum(&self, depth: usize) -> u32 {
+        self.depth_snapshot(depth, SnapshotMode::Public).checksum()
+    }
+
+    /// Hash estable del estado del libro: secuencia, checksum de todos los
+    /// niveles y posiciones por cuenta. Dos replays del mismo journal dan el
+    /// mismo número.
+    pub fn state_hash(&self) -> u64 {
+        let mut text = format!("{}|{}", self.sequence(), self.checksum(usize::MAX));
+        for (account, position) in self.positions().iter() {
+            text.push_str(&format!("|{account}:{}", position.normalize()));
+        }
+        fnv1a(FNV_OFFSET, text.as_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal_macros::dec;
+
+    use super::*;
+    use crate::orderbook::tests::order;
+    use crate::orderbook::{Order, Visibility};
+
+    // --- MODOS ---
+
+    #[test]
+    fn public_snapshot_hides_reserve_size_and_owner_snapshot_shows_it() {
+        let mut book = OrderBook::new();
+        book.add_order(Order { visibility: Visibility::Iceberg { peak: dec!(2) }, ..order(1, Side::Sell, dec!(101), dec!(10)) }).unwrap();
+        book.add_order(Order { visibility: Visibility::Hidden, ..order(2, Side::Sell, dec!(102), dec!(5)) }).unwrap();
+        book.add_order(order(3, Side::Buy, dec!(99), dec!(4))).unwrap();
+
+        let public = book.depth_snapshot(10, SnapshotMode::Public);
+        assert_eq!(public.asks, [SnapshotLevel { price: dec!(101), visible_amount: dec!(2), total_amount: None }]);
+        assert!(!public.to_json().unwrap().contains("total_amount"));
+
+        let owner = book.depth_snapshot(10, SnapshotMode::Owner);
+        assert_eq!(owner.asks, [
+            SnapshotLevel { price: dec!(101), visible_amount: dec!(2), total_amount: Some(dec!(10)) },
+            SnapshotLevel { price: dec!(102), visible_amount: dec!(0), total_amount: Some(dec!(5)) },
+        ]);
+        assert_eq!(owner.bids, [SnapshotLevel { price: dec!(99), visible_amount: dec!(4), total_amount: Some(dec!(4)) }]);
+    }
+
+    // --- PRECISIÓN DE DISPLAY ---
+
+    #[test]
+    fn json_snapshot_formats_prices_to_the_configured_precision() {
+        let mut book = OrderBook::builder().price_precision(2).build();
+        book.add_order(order(1, Side::Buy, dec!(49500.000000000), dec!(1))).unwrap();
+        book.add_order(order(2, Side::Sell, dec!(49501.5), dec!(1))).unwrap();
+
+        let json = book.depth_snapshot(10, SnapshotMode::Public).to_json().unwrap();
+        assert!(json.contains(r#""price":"49500.00""#), "{json}");
+        assert!(json.contains(r#""price":"49501.50""#), "{json}");
+        // Adentro el precio no se toca
+        assert_eq!(book.order(1).unwrap().price.scale(), 9);
+    }
+
+    // --- VISTA DE SOLO LECTURA ---
+
+    #[test]
+    fn book_view_is_frozen_at_capture_and_crosses_threads() {
+        let mut book = OrderBook::new();
+        book.add_order(order(1, Side::Buy, dec!(99), dec!(2))).unwrap();
+        book.add_order(order(2, Side::Sell, dec!(101), dec!(3))).unwrap();
+        let view = book.view(5);
+
+        book.add_order(order(3, Side::Buy, dec!(101), dec!(3))).unwrap();
+        assert_eq!(book.best_ask(), None);
+
+        let handle = std::thread::spawn(move || view);
+        let view = handle.join().unwrap();
+        assert_eq!((view.best_bid, view.best_ask), (Some(dec!(99)), Some(dec!(101))));
+        assert_eq!((view.mid_price(), view.spread()), (Some(dec!(100)), Some(dec!(2))));
+        assert_eq!(view.asks, [(dec!(101), dec!(3))]);
+        assert_eq!(view.last_price, None);
+        assert!(view.seq < book.sequence());
+    }
+
+    // --- TOUCH ---
+
+    #[test]
+    fn touch_reports_the_top_of_book_and_last_trade() {
+        let mut book = OrderBook::new();
+        assert_eq!(book.touch(), None);
+
+        book.add_order(order(1, Side::Buy, dec!(99), dec!(2))).unwrap();
+        book.add_order(order(2, Side::Buy, dec!(99), dec!(1))).unwrap();
+        book.add_order(order(3, Side::Sell, dec!(101), dec!(4))).unwrap();
+        book.add_order(order(4, Side::Sell, dec!(101), dec!(1))).unwrap();
+        assert_eq!(book.touch().unwrap().last, None);
+
+        book.add_order(order(5, Side::Buy, dec!(101), dec!(3))).unwrap();
+        let touch = book.touch().unwrap();
+        assert_eq!(touch, Touch { bid: Some(dec!(99)), bid_size: dec!(3), ask: Some(dec!(101)), ask_size: dec!(2), last: Some(dec!(101)), ts: 5 });
+
+        // Vacío pero con historia: puntas vacías en vez de `None`
+        book.cancel_order(1);
+        book.cancel_order(2);
+        book.cancel_order(3);
+        book.cancel_order(4);
+        let empty = book.touch().unwrap();
+        assert_eq!((empty.bid, empty.ask, empty.bid_size, empty.last), (None, None, dec!(0), Some(dec!(101))));
+    }
+
+    // --- NIVELES ACUMULADOS ---
+
+    #[test]
+    fn levels_accumulate_the_visible_amount_from_the_best_price() {
+        let mut book = OrderBook::new();
+        book.add_order(order(1, Side::Sell, dec!(101), dec!(2))).unwrap();
+        book.add_order(order(2, Side::Sell, dec!(101), dec!(1))).unwrap();
+        book.add_order(Order { visibility: Visibility::Hidden, ..order(3, Side::Sell, dec!(102), dec!(9)) }).unwrap();
+        book.add_order(order(4, Side::Sell, dec!(103), dec!(4))).unwrap();
+        book.add_order(Order { visibility: Visibility::Iceberg { peak: dec!(1) }, ..order(5, Side::Sell, dec!(104), dec!(6)) }).unwrap();
+
+        let levels: Vec<_> = book.levels(Side::Sell).collect();
+        assert_eq!(levels.iter().map(|l| (l.price, l.amount, l.order_count)).collect::<Vec<_>>(), [
+            (dec!(101), dec!(3), 2),
+            (dec!(103), dec!(4), 1),
+            (dec!(104), dec!(1), 1),
+        ]);
+        let mut manual = Decimal::ZERO;
+        for level in &levels {
+            manual += level.amount;
+            assert_eq!(level.cumulative_amount, manual);
+        }
+        assert!(levels.windows(2).all(|pair| pair[0].cumulative_amount < pair[1].cumulative_amount));
+        assert_eq!(book.levels(Side::Buy).count(), 0);
+    }
+
+    // --- SNAPSHOT TIPADO ---
+
+    #[test]
+    fn typed_snapshot_caps_the_depth_and_carries_the_live_sequence() {
+        let mut book = OrderBook::new();
+        book.enable_events();
+        for (id, price) in (1..=5).zip([dec!(99), dec!(98), dec!(97), dec!(96), dec!(95)]) {
+            book.add_order(order(id, Side::Buy, price, dec!(1))).unwrap();
+        }
+        book.add_order(order(6, Side::Sell, dec!(101), dec!(2))).unwrap();
+        book.add_order(order(7, Side::Sell, dec!(102), dec!(3))).unwrap();
+        book.add_order(order(8, Side::Buy, dec!(101), dec!(1))).unwrap();
+
+        let snapshot = book.snapshot(3);
+        assert_eq!(snapshot.seq, book.sequence());
+        assert_eq!(snapshot.seq, book.drain_events().last().map(|e| e.seq).unwrap());
+        assert_eq!(snapshot.last_price, Some(dec!(101)));
+        assert_eq!(snapshot.bids.iter().map(|l| l.price).collect::<Vec<_>>(), [dec!(99), dec!(98), dec!(97)]);
+        assert_eq!(snapshot.asks.iter().map(|l| (l.price, l.amount)).collect::<Vec<_>>(), [(dec!(101), dec!(1)), (dec!(102), dec!(3))]);
+
+        // Un evento más deja la foto vieja atrás
+        book.cancel_order(7).unwrap();
+        assert!(book.snapshot(3).seq > snapshot.seq);
+    }
+}
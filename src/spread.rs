@@ -0,0 +1,170 @@
+// --- SPREAD EFECTIVO Y REALIZADO ---
+//
+// Métricas de microestructura por trade, con `q` = +1 si el taker compró y -1
+// si vendió:
+//
+// - Spread efectivo: 2·q·(precio − mid al ejecutar). Lo que pagó el taker
+//   contra el mid; usa el `prevailing_mid` que el libro guarda en cada trade.
+// - Spread realizado: 2·q·(precio − mid `delay` ms después). Lo que le quedó
+//   al maker una vez que el precio se acomodó; la diferencia con el efectivo
+//   es el impacto del trade.
+//
+// El mid futuro no viaja en el trade: `SpreadStats` deja cada trade esperando
+// y lo resuelve con el primer mid que se le pase (`observe_mid`) a partir de
+// su timestamp más el delay.
+
+use std::collections::VecDeque;
+
+use rust_decimal::Decimal;
+
+use crate::orderbook::{Side, Trade};
+
+/// Spread realizado de un trade, resuelto al llegar el mid posterior al delay.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RealizedSpread {
+    pub trade_id: u64,
+    pub realized: Decimal,
+    /// Mid con el que se resolvió y la hora a la que se observó.
+    pub mid: Decimal,
+    pub observed_at: u64,
+}
+
+// Trade esperando su mid futuro.
+#[derive(Debug, Clone, Copy)]
+struct Waiting {
+    trade_id: u64,
+    due: u64,
+    price: Decimal,
+    sign: Decimal,
+}
+
+/// Acumula spreads efectivos y realizados de un libro.
+#[derive(Debug, Clone)]
+pub struct SpreadStats {
+    delay: u64,
+    waiting: VecDeque<Waiting>, // Por `due` creciente mientras los trades lleguen en orden
+    effective_sum: Decimal,
+    effective_count: u64,
+    realized_sum: Decimal,
+    realized_count: u64,
+}
+
+// +1 si el taker compró, -1 si vendió.
+fn sign(aggressor: Side) -> Decimal {
+    match aggressor {
+        Side::Buy => Decimal::ONE,
+        Side::Sell => Decimal::NEGATIVE_ONE,
+    }
+}
+
+impl SpreadStats {
+    /// El spread realizado se mide `delay` ms (hora del libro) después de cada trade.
+    pub fn new(delay: u64) -> Self {
+        Self {
+            delay,
+            waiting: VecDeque::new(),
+            effective_sum: Decimal::ZERO,
+            effective_count: 0,
+            realized_sum: Decimal::ZERO,
+            realized_count: 0,
+        }
+    }
+
+    /// Suma el spread efectivo de `trade` y lo deja esperando su mid futuro.
+    /// Devuelve el efectivo, o `None` si el libro no tenía mid al ejecutar.
+    pub fn record_trade(&mut self, trade: &Trade) -> Option<Decimal> {
+        let sign = sign(trade.aggressor);
+        let due = trade.timestamp.saturating_add(self.delay);
+        // Los trades llegan en orden de tiempo; si no, se inserta en su lugar
+        let pos = self.waiting.iter().rposition(|w| w.due <= due).map_or(0, |pos| pos + 1);
+        self.waiting.insert(pos, Waiting { trade_id: trade.trade_id, due, price: trade.price, sign });
+
+        let effective = Decimal::TWO * sign * (trade.price - trade.prevailing_mid?);
+        self.effective_sum += effective;
+        self.effective_count += 1;
+        Some(effective)
+    }
+
+    /// Mid del libro a la hora `timestamp`: resuelve los trades cuyo delay ya
+    /// se cumplió y devuelve sus spreads realizados, el más viejo primero.
+    pub fn observe_mid(&mut self, timestamp: u64, mid: Decimal) -> Vec<RealizedSpread> {
+        let mut resolved = Vec::new();
+        while let Some(waiting) = self.waiting.front()
+            && waiting.due <= timestamp
+        {
+            let realized = Decimal::TWO * waiting.sign * (waiting.price - mid);
+            self.realized_sum += realized;
+            self.realized_count += 1;
+            resolved.push(RealizedSpread { trade_id: waiting.trade_id, realized, mid, observed_at: timestamp });
+            self.waiting.pop_front();
+        }
+        resolved
+    }
+
+    /// Trades que todavía esperan su mid futuro.
+    pub fn pending(&self) -> usize {
+        self.waiting.len()
+    }
+
+    /// Promedio simple de los spreads efectivos (`None` sin trades con mid).
+    pub fn avg_effective(&self) -> Option<Decimal> {
+        (self.effective_count > 0).then(|| self.effective_sum / Decimal::from(self.effective_count))
+    }
+
+    /// Promedio simple de los spreads realizados ya resueltos.
+    pub fn avg_realized(&self) -> Option<Decimal> {
+        (self.realized_count > 0).then(|| self.realized_sum / Decimal::from(self.realized_count))
+    }
+
+    pub fn effective_count(&self) -> u64 {
+        self.effective_count
+    }
+
+    pub fn realized_count(&self) -> u64 {
+        self.realized_count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal_macros::dec;
+
+    use super::*;
+    use crate::clock::VirtualClock;
+    use crate::orderbook::OrderBook;
+    use crate::orderbook::tests::order;
+
+    #[test]
+    fn a_later_mid_resolves_the_realized_spread() {
+        let mut book = OrderBook::builder().clock(VirtualClock::new(1_000)).build();
+        book.add_order(order(1, Side::Buy, dec!(99), dec!(1))).unwrap();
+        book.add_order(order(2, Side::Sell, dec!(101), dec!(1))).unwrap();
+        book.add_order(order(3, Side::Sell, dec!(102), dec!(1))).unwrap();
+
+        // Compra contra el ask con mid 100: paga 2 de spread efectivo
+        let trade = book.add_order(order(4, Side::Buy, dec!(101), dec!(1))).unwrap().remove(0);
+        let mut stats = SpreadStats::new(500);
+        assert_eq!(stats.record_trade(&trade), Some(dec!(2)));
+        assert_eq!(stats.pending(), 1);
+
+        // Antes del delay no se resuelve; después, con el mid que quedó (100.5)
+        assert!(stats.observe_mid(1_499, book.mid_price().unwrap()).is_empty());
+        let resolved = stats.observe_mid(1_500, book.mid_price().unwrap());
+        assert_eq!(resolved, [RealizedSpread { trade_id: trade.trade_id, realized: dec!(1), mid: dec!(100.5), observed_at: 1_500 }]);
+        assert_eq!((stats.avg_effective(), stats.avg_realized()), (Some(dec!(2)), Some(dec!(1))));
+        assert_eq!((stats.effective_count(), stats.realized_count(), stats.pending()), (1, 1, 0));
+    }
+
+    #[test]
+    fn a_sell_taker_gets_the_sign_flipped_and_no_mid_means_no_effective_spread() {
+        let mut book = OrderBook::new();
+        book.add_order(order(1, Side::Buy, dec!(99), dec!(1))).unwrap();
+        let trade = book.add_order(order(2, Side::Sell, dec!(99), dec!(1))).unwrap().remove(0);
+        let mut stats = SpreadStats::new(0);
+        // Con un solo lado no había mid: queda sólo el realizado
+        assert_eq!(stats.record_trade(&trade), None);
+        let resolved = stats.observe_mid(trade.timestamp, dec!(98));
+        assert_eq!(resolved[0].realized, dec!(-2));
+        assert_eq!(stats.avg_effective(), None);
+    }
+}
@@ -0,0 +1,102 @@
+// --- TAPE DE TRADES (RAW + AGREGADOS) ---
+//
+// El engine publica cada trade ejecutado y, aparte, su versión agregada al
+// estilo `aggTrades` de Binance: fills consecutivos de un mismo taker al mismo
+// precio se juntan en uno solo con la cantidad sumada.
+
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::orderbook::{Side, Trade};
+
+/// Un trade del tape, con el símbolo en el que se ejecutó.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SymbolTrade {
+    pub symbol: String,
+    pub trade: Trade,
+}
+
+/// Trades consecutivos al mismo precio y con el mismo agresor, sumados.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AggTrade {
+    pub symbol: String,
+    /// Id propio del agregado, creciente por símbolo.
+    pub agg_id: u64,
+    pub price: Decimal,
+    pub amount: Decimal,
+    pub first_trade_id: u64,
+    pub last_trade_id: u64,
+    pub timestamp: u64,
+    pub aggressor: Side,
+}
+
+/// Agrupa los trades de cada respuesta del libro en `AggTrade`s.
+#[derive(Debug, Clone)]
+pub struct TradeAggregator {
+    symbol: String,
+    next_agg_id: u64,
+}
+
+impl TradeAggregator {
+    pub fn new(symbol: impl Into<String>) -> Self {
+        Self { symbol: symbol.into(), next_agg_id: 1 }
+    }
+
+    /// Junta los trades consecutivos con igual precio y agresor. Los trades de
+    /// una misma llamada al libro son todos del mismo taker, así que nunca se
+    /// mezclan órdenes distintas mientras se agregue de a un lote por vez.
+    pub fn aggregate(&mut self, trades: &[Trade]) -> Vec<AggTrade> {
+        let mut aggregated: Vec<AggTrade> = Vec::new();
+        for trade in trades {
+            if let Some(last) = aggregated.last_mut()
+                && last.price == trade.price
+                && last.aggressor == trade.aggressor
+            {
+                last.amount += trade.amount;
+                last.last_trade_id = trade.trade_id;
+                continue;
+            }
+            aggregated.push(AggTrade {
+                symbol: self.symbol.clone(),
+                agg_id: self.next_agg_id,
+                price: trade.price,
+                amount: trade.amount,
+                first_trade_id: trade.trade_id,
+                last_trade_id: trade.trade_id,
+                timestamp: trade.timestamp,
+                aggressor: trade.aggressor,
+            });
+            self.next_agg_id += 1;
+        }
+        aggregated
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal_macros::dec;
+
+    use super::*;
+    use crate::orderbook::OrderBook;
+    use crate::orderbook::tests::order;
+
+    #[test]
+    fn a_sweep_aggregates_into_one_trade_per_price() {
+        let mut book = OrderBook::new();
+        book.add_order(order(1, Side::Sell, dec!(100), dec!(1))).unwrap();
+        book.add_order(order(2, Side::Sell, dec!(100), dec!(2))).unwrap();
+        book.add_order(order(3, Side::Sell, dec!(100), dec!(1))).unwrap();
+        book.add_order(order(4, Side::Sell, dec!(101), dec!(5))).unwrap();
+        let trades = book.add_order(order(5, Side::Buy, dec!(101), dec!(6))).unwrap();
+        assert_eq!(trades.len(), 4);
+
+        let mut aggregator = TradeAggregator::new("BTC");
+        let aggregated = aggregator.aggregate(&trades);
+        assert_eq!(aggregated.len(), 2);
+        assert_eq!((aggregated[0].price, aggregated[0].amount), (dec!(100), dec!(4)));
+        assert_eq!((aggregated[0].first_trade_id, aggregated[0].last_trade_id), (1, 3));
+        assert_eq!((aggregated[1].price, aggregated[1].amount, aggregated[1].first_trade_id), (dec!(101), dec!(2), 4));
+        assert_eq!(aggregated.iter().map(|agg| agg.agg_id).collect::<Vec<_>>(), [1, 2]);
+        assert!(aggregated.iter().all(|agg| agg.symbol == "BTC" && agg.aggressor == Side::Buy));
+    }
+}
@@ -0,0 +1,86 @@
+// --- WARM-UP DESDE UN SNAPSHOT L2 DE EXCHANGE ---
+//
+// Muchos exchanges publican la profundidad como `{"bids": [[precio, cantidad], ...],
+// "asks": [...]}` (precios y cantidades como string o número). Este adaptador
+// la convierte en órdenes sintéticas, una por nivel, para arrancar el libro con
+// datos reales antes de empezar a procesar el flujo en vivo.
+
+use rust_decimal::Decimal;
+use serde::Deserialize;
+
+use crate::error::OrderError;
+use crate::orderbook::{Order, OrderBook, OrderType, Side, TimeInForce, Visibility};
+
+/// Cuenta dueña de las órdenes sintéticas del warm-up.
+pub const SYNTHETIC_ACCOUNT: u64 = 0;
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct L2Snapshot {
+    /// (precio, cantidad), en el orden que venga; el libro los ordena solo.
+    pub bids: Vec<(Decimal, Decimal)>,
+    pub asks: Vec<(Decimal, Decimal)>,
+}
+
+impl L2Snapshot {
+    pub fn from_json(json: &str) -> serde_json::Result<Self> {
+        serde_json::from_str(json)
+    }
+
+    /// Carga una orden sintética por nivel en `book`, con ids consecutivos desde
+    /// `first_id`. Los niveles con cantidad 0 se saltean. Devuelve el próximo id libre.
+    /// Un snapshot cruzado (bid >= ask) generaría trades: conviene sembrar un libro vacío.
+    pub fn seed(&self, book: &mut OrderBook, first_id: u64) -> Result<u64, OrderError> {
+        let mut next_id = first_id;
+        let levels = self.bids.iter().map(|level| (Side::Buy, level))
+            .chain(self.asks.iter().map(|level| (Side::Sell, level)));
+
+        for (side, &(price, amount)) in levels {
+            if amount.is_zero() {
+                continue;
+            }
+            book.add_order(Order {
+                id: next_id,
+                price,
+                amount,
+                side,
+                timestamp: 0,
+                account: SYNTHETIC_ACCOUNT,
+                subaccount: None,
+                min_fill: None,
+                price_cap: None,
+                cancel_at_fill_ratio: None,
+                client_order_id: None,
+                strategy_id: None,
+                venue_tag: None,
+                order_type: OrderType::Limit,
+                visibility: Visibility::Visible,
+                time_in_force: TimeInForce::Gtc,
+            })?;
+            next_id += 1;
+        }
+        Ok(next_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal_macros::dec;
+
+    use super::*;
+
+    const SAMPLE: &str = r#"{
+        "bids": [["49999.5", "1.25"], ["50000", "0.5"], ["49990", "0"]],
+        "asks": [[50001, 2], ["50002.5", "3"]]
+    }"#;
+
+    #[test]
+    fn seeding_from_a_sample_snapshot_reproduces_its_depth() {
+        let snapshot = L2Snapshot::from_json(SAMPLE).unwrap();
+        let mut book = OrderBook::new();
+        assert_eq!(snapshot.seed(&mut book, 100).unwrap(), 104);
+
+        assert_eq!(book.depth(Side::Buy, 10), [(dec!(50000), dec!(0.5)), (dec!(49999.5), dec!(1.25))]);
+        assert_eq!(book.depth(Side::Sell, 10), [(dec!(50001), dec!(2)), (dec!(50002.5), dec!(3))]);
+        assert!(book.iter_orders(Side::Buy).all(|o| o.account == SYNTHETIC_ACCOUNT));
+    }
+}
@@ -0,0 +1,403 @@
+// Engine de punta a punta: una tarea por símbolo detrás de canales de tokio.
+#![cfg(feature = "async")]
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use hft_orderbook::engine::{latest_snapshot, list_snapshots};
+use hft_orderbook::{BookConfig, Compression, Engine, EngineError, EventRecorder, FlushPolicy, MinRestingPolicy, Order, OrderBook, OrderType, Side, SnapshotPolicy, StreamedTrade, TradeIdScope, load_snapshot, save_snapshot};
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use tokio_stream::StreamExt;
+
+fn order(id: u64, side: Side, price: Decimal, amount: Decimal) -> Order {
+    Order::builder(id, side, price, amount).account(id).timestamp(id).build().unwrap()
+}
+
+#[tokio::test]
+async fn symbols_are_routed_and_processed_independently() {
+    let engine = Engine::builder().symbol("BTC").symbol("ETH").build();
+    assert_eq!(engine.symbols().collect::<Vec<_>>(), ["BTC", "ETH"]);
+
+    // Los mismos ids en los dos símbolos: cada libro es independiente
+    let (btc, eth) = tokio::join!(
+        engine.submit("BTC", order(1, Side::Sell, dec!(50000), dec!(2))),
+        engine.submit("ETH", order(1, Side::Sell, dec!(3000), dec!(10))),
+    );
+    assert!(btc.unwrap().trades.is_empty());
+    assert!(eth.unwrap().trades.is_empty());
+
+    let (btc, eth) = tokio::join!(
+        engine.submit("BTC", order(2, Side::Buy, dec!(50000), dec!(1))),
+        engine.submit("ETH", order(2, Side::Buy, dec!(2990), dec!(4))),
+    );
+    let (btc, eth) = (btc.unwrap(), eth.unwrap());
+    assert_eq!(btc.trades.len(), 1);
+    assert_eq!((btc.trades[0].price, btc.trades[0].amount), (dec!(50000), dec!(1)));
+    assert!(eth.trades.is_empty(), "el bid de ETH no cruza");
+
+    let btc_ask = engine.with_book("BTC", |book| book.best_ask()).await.unwrap();
+    let eth_top = engine.with_book("ETH", |book| (book.best_bid(), book.best_ask(), book.len())).await.unwrap();
+    assert_eq!(btc_ask, Some(dec!(50000)));
+    assert_eq!(eth_top, (Some(dec!(2990)), Some(dec!(3000)), 2));
+
+    let unknown = engine.submit("SOL", order(3, Side::Buy, dec!(1), dec!(1))).await;
+    assert_eq!(unknown.unwrap_err(), EngineError::UnknownSymbol("SOL".into()));
+    engine.shutdown().await;
+}
+
+#[tokio::test]
+async fn resting_order_owner_is_notified_of_each_fill() {
+    let engine = Engine::builder().symbol("BTC").build();
+    let maker = Order::builder(1, Side::Sell, dec!(100), dec!(5)).account(1).client_order_id("mm-1").build().unwrap();
+    let (ack, mut fills) = engine.submit_with_fills("BTC", maker).await.unwrap();
+    assert_eq!(ack.resting_qty, dec!(5));
+
+    engine.submit("BTC", order(2, Side::Buy, dec!(100), dec!(2))).await.unwrap();
+    engine.submit("BTC", order(3, Side::Buy, dec!(100), dec!(3))).await.unwrap();
+
+    let first = fills.recv().await.unwrap();
+    assert_eq!((first.order_id, first.client_order_id.as_deref()), (1, Some("mm-1")));
+    assert_eq!((first.filled, first.remaining, first.price), (dec!(2), dec!(3), dec!(100)));
+    let second = fills.recv().await.unwrap();
+    assert_eq!((second.filled, second.remaining), (dec!(3), dec!(0)));
+    // Completada: el engine suelta el canal
+    assert!(fills.recv().await.is_none());
+    engine.shutdown().await;
+}
+
+// Un fill en cada símbolo, BTC primero.
+async fn trade_ids(scope: TradeIdScope) -> (u64, u64) {
+    let engine = Engine::builder().symbol("BTC").symbol("ETH").trade_id_scope(scope).build();
+    let mut ids = Vec::new();
+    for symbol in ["BTC", "ETH"] {
+        engine.submit(symbol, order(1, Side::Sell, dec!(10), dec!(1))).await.unwrap();
+        ids.push(engine.submit(symbol, order(2, Side::Buy, dec!(10), dec!(1))).await.unwrap().trades[0].trade_id);
+    }
+    engine.shutdown().await;
+    (ids[0], ids[1])
+}
+
+#[tokio::test]
+async fn trade_ids_are_global_or_per_symbol() {
+    assert_eq!(trade_ids(TradeIdScope::Global).await, (1, 2));
+    assert_eq!(trade_ids(TradeIdScope::PerSymbol).await, (1, 1));
+}
+
+#[tokio::test]
+async fn metrics_break_rejects_down_by_reason() {
+    let engine = Engine::builder().symbol("BTC").build();
+    engine.submit("BTC", order(1, Side::Sell, dec!(100), dec!(2))).await.unwrap();
+    engine.submit("BTC", order(2, Side::Buy, dec!(100), dec!(1))).await.unwrap();
+
+    let post_only = Order { order_type: OrderType::PostOnly, ..order(3, Side::Buy, dec!(100), dec!(1)) };
+    let rejected = [
+        Order { amount: dec!(0), ..order(4, Side::Buy, dec!(99), dec!(1)) },
+        order(5, Side::Buy, dec!(-1), dec!(1)),
+        order(6, Side::Buy, dec!(0), dec!(1)),
+        post_only,
+    ];
+    for o in rejected {
+        assert!(matches!(engine.submit("BTC", o).await, Err(EngineError::Rejected(_))));
+    }
+
+    let metrics = engine.metrics();
+    assert_eq!((metrics.orders_processed, metrics.trades), (6, 1));
+    assert_eq!(metrics.rejects.get("InvalidAmount"), Some(&1));
+    assert_eq!(metrics.rejects.get("InvalidPrice"), Some(&2));
+    assert_eq!(metrics.rejects.get("PostOnlyWouldCross"), Some(&1));
+    assert_eq!(metrics.rejects.values().sum::<u64>(), 4);
+    assert_eq!(metrics.resting_orders.get("BTC"), Some(&1));
+    engine.shutdown().await;
+}
+
+#[tokio::test]
+async fn closing_a_connection_cancels_only_its_orders() {
+    let engine = Engine::builder().symbol("BTC").symbol("ETH").build();
+    let (gateway, other) = (engine.connect(), engine.connect());
+    gateway.submit("BTC", order(1, Side::Buy, dec!(100), dec!(1))).await.unwrap();
+    gateway.submit("ETH", order(1, Side::Buy, dec!(10), dec!(1))).await.unwrap();
+    other.submit("BTC", order(2, Side::Buy, dec!(99), dec!(1))).await.unwrap();
+    engine.submit("BTC", order(3, Side::Buy, dec!(98), dec!(1))).await.unwrap();
+    assert_eq!(gateway.open_order_count(), 2);
+
+    let mut cancelled: Vec<_> = gateway.disconnect().await.iter().map(|o| o.id).collect();
+    cancelled.sort();
+    assert_eq!(cancelled, [1, 1]);
+    let ids = |symbol| engine.with_book(symbol, |book| book.iter_orders(Side::Buy).map(|o| o.id).collect::<Vec<_>>());
+    assert_eq!(ids("BTC").await.unwrap(), [2, 3]);
+    assert!(ids("ETH").await.unwrap().is_empty());
+
+    // Soltada sin `disconnect`: el engine cancela igual, antes del próximo comando
+    drop(other);
+    assert_eq!(ids("BTC").await.unwrap(), [3]);
+    engine.shutdown().await;
+}
+
+#[tokio::test]
+async fn a_disconnect_cancels_even_before_the_min_resting_time() {
+    // Sin reloj que avance, ninguna orden llega a cumplir el mínimo
+    let book = || OrderBook::builder().clock(hft_orderbook::VirtualClock::new(0)).min_resting_time(1_000, MinRestingPolicy::Reject).build();
+    let engine = Engine::builder().book("BTC", book()).book("ETH", book()).build();
+    let (gateway, dropped) = (engine.connect(), engine.connect());
+    gateway.submit("BTC", order(1, Side::Buy, dec!(100), dec!(1))).await.unwrap();
+    dropped.submit("ETH", order(2, Side::Buy, dec!(10), dec!(1))).await.unwrap();
+
+    assert_eq!(gateway.disconnect().await.iter().map(|o| o.id).collect::<Vec<_>>(), [1]);
+    drop(dropped);
+    for symbol in ["BTC", "ETH"] {
+        assert_eq!(engine.with_book(symbol, |book| book.len()).await.unwrap(), 0, "{symbol}");
+    }
+    engine.shutdown().await;
+}
+
+#[tokio::test]
+async fn a_refused_cancel_keeps_the_order_covered_by_the_connection() {
+    let book = OrderBook::builder().clock(hft_orderbook::VirtualClock::new(0)).min_resting_time(1_000, MinRestingPolicy::Reject).build();
+    let engine = Engine::builder().book("BTC", book).build();
+    let gateway = engine.connect();
+    gateway.submit("BTC", order(1, Side::Buy, dec!(100), dec!(1))).await.unwrap();
+    gateway.submit("BTC", order(2, Side::Buy, dec!(99), dec!(1))).await.unwrap();
+
+    // Antes del mínimo el libro rechaza la cancelación: la orden sigue cubierta
+    assert_eq!(gateway.cancel("BTC", 1).await.unwrap(), None);
+    assert_eq!(gateway.open_order_count(), 2);
+    let mut cancelled: Vec<_> = gateway.disconnect().await.iter().map(|o| o.id).collect();
+    cancelled.sort();
+    assert_eq!(cancelled, [1, 2]);
+    engine.shutdown().await;
+}
+
+// Órdenes en reposo como (id, precio, cantidad), bids y después asks.
+fn resting(book: &OrderBook) -> Vec<(u64, Decimal, Decimal)> {
+    [Side::Buy, Side::Sell].into_iter().flat_map(|side| book.iter_orders(side)).map(|o| (o.id, o.price, o.amount)).collect()
+}
+
+#[tokio::test]
+async fn periodic_snapshots_restore_the_live_book_and_keep_the_last_ones() {
+    let dir = tempfile::tempdir().unwrap();
+    let policy = SnapshotPolicy::new(dir.path(), Duration::from_millis(20)).keep(2);
+    let engine = Engine::builder().symbol("BTC").snapshots(policy).build();
+
+    for id in 1..=3 {
+        engine.submit("BTC", order(id, Side::Buy, dec!(100) - Decimal::from(id), dec!(1))).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(60)).await;
+    }
+    let snapshots = list_snapshots(dir.path(), "BTC").unwrap();
+    assert_eq!(snapshots.len(), 2, "sólo quedan los últimos K");
+    let latest = latest_snapshot(dir.path(), "BTC").unwrap().unwrap();
+    let live = engine.with_book("BTC", resting).await.unwrap();
+    assert_eq!(resting(&load_snapshot(&latest, BookConfig::default()).unwrap()), live);
+
+    // Al apagarse guarda una última vez
+    engine.submit("BTC", order(4, Side::Sell, dec!(105), dec!(2))).await.unwrap();
+    engine.shutdown().await;
+    let last = latest_snapshot(dir.path(), "BTC").unwrap().unwrap();
+    assert_eq!(resting(&load_snapshot(last, BookConfig::default()).unwrap()).len(), 4);
+}
+
+#[tokio::test]
+async fn recover_replays_the_journal_after_the_snapshot_with_the_book_config() {
+    let dir = tempfile::tempdir().unwrap();
+    let (snapshot, journal) = (dir.path().join("BTC.snap"), dir.path().join("BTC.jsonl"));
+    let config = BookConfig { position_limit: Some(dec!(3)), ..BookConfig::default() };
+    let mut book = OrderBook::with_config(config.clone());
+    book.enable_events();
+    let mut recorder = EventRecorder::create(&journal, FlushPolicy::EveryEvent).unwrap();
+
+    book.add_order(order(1, Side::Sell, dec!(100), dec!(3))).unwrap();
+    book.add_order(order(2, Side::Sell, dec!(100), dec!(3))).unwrap();
+    book.add_order(Order { account: 9, ..order(3, Side::Buy, dec!(100), dec!(2)) }).unwrap();
+    recorder.record_all(&book.drain_events()).unwrap();
+    // El journal sigue teniendo estos eventos: el recover los saltea
+    save_snapshot(&snapshot, &book, Compression::None).unwrap();
+
+    // Con el límite de posición, la cuenta 9 sólo llega a 3
+    book.add_order(Order { account: 9, ..order(4, Side::Buy, dec!(100), dec!(5)) }).unwrap();
+    book.add_order(order(5, Side::Buy, dec!(99), dec!(1))).unwrap();
+    assert_eq!(book.positions().get(9), dec!(3));
+    recorder.record_all(&book.drain_events()).unwrap();
+
+    let engine = Engine::recover("BTC", &snapshot, &journal, config).unwrap();
+    let recovered = engine.with_book("BTC", |book| (resting(book), book.sequence())).await.unwrap();
+    assert_eq!(recovered, (resting(&book), book.sequence()));
+    engine.shutdown().await;
+
+    // Sin la config del libro original el replay cruza distinto
+    let engine = Engine::recover("BTC", &snapshot, &journal, BookConfig::default()).unwrap();
+    assert_ne!(engine.with_book("BTC", resting).await.unwrap(), resting(&book));
+    engine.shutdown().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn a_full_inbox_makes_senders_wait() {
+    let engine = Arc::new(Engine::builder().symbol("BTC").inbox_capacity(2).build());
+
+    // Traba la tarea del símbolo hasta que el test la suelte
+    let (started_tx, started) = tokio::sync::oneshot::channel();
+    let (release, blocked) = std::sync::mpsc::channel::<()>();
+    let blocker = tokio::spawn({
+        let engine = engine.clone();
+        async move {
+            engine.with_book("BTC", move |_| {
+                started_tx.send(()).unwrap();
+                blocked.recv().unwrap();
+            }).await
+        }
+    });
+    started.await.unwrap();
+
+    let queued: Vec<_> = (1..=2)
+        .map(|id| {
+            let engine = engine.clone();
+            tokio::spawn(async move { engine.submit("BTC", order(id, Side::Buy, dec!(100), dec!(1))).await })
+        })
+        .collect();
+    while engine.metrics().queue_depth.get("BTC") != Some(&2) {
+        tokio::time::sleep(Duration::from_millis(1)).await;
+    }
+    let third = tokio::time::timeout(Duration::from_millis(50), engine.submit("BTC", order(3, Side::Buy, dec!(100), dec!(1))));
+    assert!(third.await.is_err(), "con el inbox lleno el envío espera");
+
+    release.send(()).unwrap();
+    blocker.await.unwrap().unwrap();
+    for submit in queued {
+        assert!(submit.await.unwrap().is_ok());
+    }
+    assert_eq!(engine.metrics().queue_depth.get("BTC"), Some(&0));
+    Arc::into_inner(engine).unwrap().shutdown().await;
+}
+
+// Suscriptor de `tracing` que guarda los campos de cada evento de salud.
+#[derive(Clone, Default)]
+struct HealthEvents(Arc<Mutex<Vec<BTreeMap<String, String>>>>);
+
+struct FieldVisitor<'a>(&'a mut BTreeMap<String, String>);
+
+impl tracing::field::Visit for FieldVisitor<'_> {
+    fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+        self.0.insert(field.name().to_string(), format!("{value:?}"));
+    }
+}
+
+impl tracing::Subscriber for HealthEvents {
+    fn enabled(&self, metadata: &tracing::Metadata<'_>) -> bool {
+        metadata.target() == "hft_orderbook::health"
+    }
+    fn new_span(&self, _: &tracing::span::Attributes<'_>) -> tracing::span::Id {
+        tracing::span::Id::from_u64(1)
+    }
+    fn record(&self, _: &tracing::span::Id, _: &tracing::span::Record<'_>) {}
+    fn record_follows_from(&self, _: &tracing::span::Id, _: &tracing::span::Id) {}
+    fn event(&self, event: &tracing::Event<'_>) {
+        let mut fields = BTreeMap::new();
+        event.record(&mut FieldVisitor(&mut fields));
+        self.0.lock().unwrap().push(fields);
+    }
+    fn enter(&self, _: &tracing::span::Id) {}
+    fn exit(&self, _: &tracing::span::Id) {}
+}
+
+#[tokio::test]
+async fn health_reports_log_the_book_summary_periodically() {
+    // Runtime de un hilo: el suscriptor por defecto del hilo ve también la tarea del símbolo
+    let events = HealthEvents::default();
+    let _guard = tracing::subscriber::set_default(events.clone());
+    let engine = Engine::builder().symbol("BTC").health_reports(Duration::from_millis(10)).build();
+    engine.submit("BTC", order(1, Side::Buy, dec!(99), dec!(2))).await.unwrap();
+    engine.submit("BTC", order(2, Side::Sell, dec!(101), dec!(3))).await.unwrap();
+
+    let summary = tokio::time::timeout(Duration::from_secs(2), async {
+        loop {
+            if let Some(fields) = events.0.lock().unwrap().iter().find(|f| f["resting_orders"] == "2") {
+                return fields.clone();
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+    })
+    .await
+    .expect("llega un resumen con las dos órdenes");
+    assert_eq!(summary["symbol"], "BTC");
+    assert_eq!((summary["best_bid"].as_str(), summary["best_ask"].as_str()), ("Some(99)", "Some(101)"));
+    assert_eq!(summary["spread_bps"], "Some(200.00)");
+    assert_eq!((summary["bid_depth_1pct"].as_str(), summary["ask_depth_1pct"].as_str()), ("2", "3"));
+    assert!(summary.contains_key("orders_per_sec"));
+    assert_eq!(summary["message"], "💓 Salud del libro");
+    engine.shutdown().await;
+}
+
+#[tokio::test]
+async fn the_trade_stream_yields_trades_and_survives_lagging() {
+    let engine = Engine::builder().symbol("BTC").symbol("ETH").build();
+    let stream = engine.trade_stream();
+    engine.submit("BTC", order(1, Side::Sell, dec!(100), dec!(3))).await.unwrap();
+    engine.submit("ETH", order(1, Side::Sell, dec!(10), dec!(1))).await.unwrap();
+    engine.submit("BTC", order(2, Side::Buy, dec!(100), dec!(2))).await.unwrap();
+    engine.submit("ETH", order(2, Side::Buy, dec!(10), dec!(1))).await.unwrap();
+    engine.submit("BTC", order(3, Side::Buy, dec!(100), dec!(1))).await.unwrap();
+
+    let streamed: Vec<_> = stream.take(3).collect().await;
+    let trades: Vec<_> = streamed
+        .iter()
+        .map(|item| match item {
+            StreamedTrade::Trade(t) => (t.symbol.as_str(), t.trade.taker_id, t.trade.amount),
+            StreamedTrade::Lagged(missed) => panic!("no debería atrasarse: {missed}"),
+        })
+        .collect();
+    assert_eq!(trades, [("BTC", 2, dec!(2)), ("ETH", 2, dec!(1)), ("BTC", 3, dec!(1))]);
+
+    // Un consumidor que no lee pierde los más viejos y se entera con `Lagged`
+    let mut lagging = engine.trade_stream();
+    let makers = hft_orderbook::engine::TAPE_CAPACITY as u64 + 5;
+    for id in 1..=makers {
+        engine.submit("ETH", order(10 + id, Side::Sell, dec!(10), dec!(1))).await.unwrap();
+    }
+    engine.submit("ETH", order(1000 + makers, Side::Buy, dec!(10), Decimal::from(makers))).await.unwrap();
+    assert_eq!(lagging.next().await, Some(StreamedTrade::Lagged(5)));
+    assert!(matches!(lagging.next().await, Some(StreamedTrade::Trade(t)) if t.trade.maker_id == 16));
+    engine.shutdown().await;
+}
+
+async fn replay(symbols: [&str; 2], extra_fill: bool) -> u64 {
+    let engine = Engine::builder().symbol(symbols[0]).symbol(symbols[1]).build();
+    for symbol in ["BTC", "ETH"] {
+        engine.submit(symbol, order(1, Side::Sell, dec!(100), dec!(5))).await.unwrap();
+        engine.submit(symbol, order(2, Side::Buy, dec!(99), dec!(3))).await.unwrap();
+        engine.submit(symbol, order(3, Side::Buy, dec!(100), dec!(2))).await.unwrap();
+    }
+    if extra_fill {
+        engine.submit("ETH", order(4, Side::Buy, dec!(100), dec!(1))).await.unwrap();
+    }
+    let hash = engine.state_hash().await.unwrap();
+    engine.shutdown().await;
+    hash
+}
+
+#[tokio::test]
+async fn identical_replays_produce_the_same_state_hash() {
+    let hash = replay(["BTC", "ETH"], false).await;
+    assert_eq!(replay(["BTC", "ETH"], false).await, hash);
+    // El orden en que se registraron los símbolos no cuenta
+    assert_eq!(replay(["ETH", "BTC"], false).await, hash);
+    assert_ne!(replay(["BTC", "ETH"], true).await, hash);
+}
+
+#[tokio::test]
+async fn a_rejected_order_still_publishes_the_last_look_fills_that_expired() {
+    let clock = hft_orderbook::VirtualClock::new(0);
+    let engine = Engine::builder().book("BTC", OrderBook::builder().clock(clock.clone()).last_look(50).build()).build();
+    let mut trades = engine.trade_stream();
+    let (_, mut fills) = engine.submit_with_fills("BTC", order(1, Side::Sell, dec!(100), dec!(3))).await.unwrap();
+    let ack = engine.submit("BTC", order(2, Side::Buy, dec!(100), dec!(1))).await.unwrap();
+    assert!(ack.trades.is_empty(), "el fill queda esperando el last look");
+
+    // Vence la ventana y la siguiente orden es rechazada: el fill igual sale
+    clock.set(50);
+    let post_only = Order { order_type: OrderType::PostOnly, ..order(3, Side::Buy, dec!(100), dec!(1)) };
+    assert!(matches!(engine.submit("BTC", post_only).await, Err(EngineError::Rejected(hft_orderbook::OrderError::PostOnlyWouldCross { .. }))));
+    let fill = fills.recv().await.unwrap();
+    assert_eq!((fill.order_id, fill.filled, fill.remaining), (1, dec!(1), dec!(2)));
+    assert!(matches!(trades.next().await, Some(StreamedTrade::Trade(t)) if t.trade.maker_id == 1 && t.trade.taker_id == 2));
+    engine.shutdown().await;
+}
@@ -0,0 +1,74 @@
+// Servicio gRPC de punta a punta: servidor en un puerto local efímero y un
+// cliente generado del mismo .proto.
+#![cfg(feature = "grpc")]
+
+use hft_orderbook::OrderBook;
+use hft_orderbook::grpc::BookService;
+use hft_orderbook::grpc::proto::market_data::Payload;
+use hft_orderbook::grpc::proto::order_book_service_client::OrderBookServiceClient;
+use hft_orderbook::grpc::proto::{self, CancelOrderRequest, StreamMarketDataRequest, SubmitOrderRequest};
+use tokio::net::TcpListener;
+use tokio_stream::StreamExt;
+use tonic::transport::{Channel, Server};
+use tonic::transport::server::TcpIncoming;
+
+async fn start_server() -> OrderBookServiceClient<Channel> {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let service = BookService::new(OrderBook::new()).into_server();
+    tokio::spawn(Server::builder().add_service(service).serve_with_incoming(TcpIncoming::from(listener)));
+    OrderBookServiceClient::connect(format!("http://{addr}")).await.unwrap()
+}
+
+fn limit(id: u64, side: proto::Side, price: &str, amount: &str) -> SubmitOrderRequest {
+    SubmitOrderRequest {
+        id,
+        price: price.into(),
+        amount: amount.into(),
+        side: side as i32,
+        timestamp: id,
+        account: id,
+        order_type: proto::OrderType::Limit as i32,
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn submit_and_stream_market_data() {
+    let mut client = start_server().await;
+    client.submit_order(limit(1, proto::Side::Sell, "101", "5")).await.unwrap();
+
+    let mut stream = client.stream_market_data(StreamMarketDataRequest { depth: 0 }).await.unwrap().into_inner();
+    let Some(Payload::Snapshot(snapshot)) = stream.next().await.unwrap().unwrap().payload else { panic!("primero llega el snapshot") };
+    assert!(snapshot.bids.is_empty());
+    assert_eq!(snapshot.asks.len(), 1);
+    assert_eq!((snapshot.asks[0].price.as_str(), snapshot.asks[0].amount.as_str()), ("101", "5"));
+
+    let mut buy = limit(2, proto::Side::Buy, "101", "3");
+    buy.client_order_id = Some("cliente-2".into());
+    let ack = client.submit_order(buy).await.unwrap().into_inner();
+    assert_eq!(ack.client_order_id.as_deref(), Some("cliente-2"));
+    assert_eq!(ack.trades.len(), 1);
+    assert_eq!((ack.trades[0].maker_id, ack.trades[0].price.as_str()), (1, "101"));
+    assert_eq!((ack.filled_qty.as_str(), ack.resting_qty.as_str(), ack.cancelled_qty.as_str()), ("3", "0", "0"));
+
+    // El fill deja el ask en 2, y después de la secuencia del snapshot
+    let Some(Payload::Delta(delta)) = stream.next().await.unwrap().unwrap().payload else { panic!("después llegan deltas") };
+    assert!(delta.seq > snapshot.seq);
+    assert_eq!((delta.side, delta.price.as_str(), delta.amount.as_str()), (proto::Side::Sell as i32, "101", "2"));
+
+    let cancel = client.cancel_order(CancelOrderRequest { target: Some(proto::cancel_order_request::Target::Id(1)) }).await.unwrap().into_inner();
+    assert!(cancel.cancelled);
+    assert_eq!(cancel.remaining, "2");
+    let Some(Payload::Delta(delta)) = stream.next().await.unwrap().unwrap().payload else { panic!("la cancelación publica un delta") };
+    assert_eq!(delta.amount, "0");
+}
+
+#[tokio::test]
+async fn invalid_orders_are_rejected_with_invalid_argument() {
+    let mut client = start_server().await;
+    let status = client.submit_order(limit(1, proto::Side::Buy, "abc", "1")).await.unwrap_err();
+    assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    let status = client.submit_order(limit(2, proto::Side::Buy, "100", "0")).await.unwrap_err();
+    assert_eq!(status.code(), tonic::Code::InvalidArgument);
+}
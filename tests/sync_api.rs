@@ -0,0 +1,59 @@
+// El núcleo sin runtime: el libro y el exchange se usan desde código síncrono
+// común, sin tokio. Corre también con `--no-default-features`.
+
+use hft_orderbook::{Exchange, Order, OrderBook, OrderError, OrderType, Side, TimeInForce, TradingState};
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+
+fn order(id: u64, side: Side, price: Decimal, amount: Decimal) -> Order {
+    Order::builder(id, side, price, amount).account(id).timestamp(id).build().unwrap()
+}
+
+#[test]
+fn the_book_matches_and_answers_queries_without_a_runtime() {
+    #[cfg(feature = "async")]
+    assert!(tokio::runtime::Handle::try_current().is_err(), "el test no tiene que correr dentro de tokio");
+
+    let mut book = OrderBook::new();
+    book.add_order(order(1, Side::Sell, dec!(101), dec!(2))).unwrap();
+    book.add_order(order(2, Side::Sell, dec!(102), dec!(3))).unwrap();
+    book.add_order(order(3, Side::Buy, dec!(99), dec!(4))).unwrap();
+    assert_eq!((book.best_bid(), book.best_ask(), book.mid_price()), (Some(dec!(99)), Some(dec!(101)), Some(dec!(100))));
+    assert_eq!(book.depth(Side::Sell, 10), [(dec!(101), dec!(2)), (dec!(102), dec!(3))]);
+
+    let sweep = order(4, Side::Buy, dec!(102), dec!(4));
+    let simulated = book.simulate(&sweep);
+    let ack = book.submit(sweep).unwrap();
+    assert_eq!(ack.trades, simulated);
+    assert_eq!((ack.filled_qty, ack.resting_qty), (dec!(4), dec!(0)));
+    assert_eq!(book.last_price(), Some(dec!(102)));
+
+    let ioc = Order { time_in_force: TimeInForce::Ioc, ..order(5, Side::Sell, dec!(99), dec!(6)) };
+    let ack = book.submit(ioc).unwrap();
+    assert_eq!((ack.filled_qty, ack.cancelled_qty), (dec!(4), dec!(2)));
+
+    book.add_order(order(6, Side::Buy, dec!(98), dec!(5))).unwrap();
+    book.modify_order(6, dec!(98), dec!(2)).unwrap();
+    assert_eq!(book.total_volume(Side::Buy), dec!(2));
+    assert_eq!(book.cancel_order(6).map(|o| o.amount), Some(dec!(2)));
+
+    let post_only = Order { order_type: OrderType::PostOnly, ..order(7, Side::Buy, dec!(102), dec!(1)) };
+    assert!(matches!(book.add_order(post_only), Err(OrderError::PostOnlyWouldCross { .. })));
+    assert_eq!(book.touch().map(|t| (t.bid, t.ask, t.last)), Some((None, Some(dec!(102)), Some(dec!(99)))));
+}
+
+#[test]
+fn the_exchange_runs_several_books_in_one_thread() {
+    let mut exchange = Exchange::new();
+    exchange.add_symbol("BTC", OrderBook::new());
+    exchange.add_symbol("ETH", OrderBook::new());
+
+    exchange.submit("BTC", order(1, Side::Sell, dec!(100), dec!(1))).unwrap();
+    let ack = exchange.submit("BTC", order(2, Side::Buy, dec!(100), dec!(1))).unwrap();
+    assert_eq!(ack.trades.len(), 1);
+
+    exchange.halt_all();
+    assert_eq!(exchange.book("ETH").unwrap().trading_state(), TradingState::CancelOnly);
+    exchange.resume_all();
+    assert!(exchange.submit("ETH", order(3, Side::Buy, dec!(10), dec!(1))).is_ok());
+}